@@ -0,0 +1,47 @@
+//! Generates the gRPC server of `merkle_api` from the service described in
+//! `proto/merkle/v1/merkle.proto`. The messages are written by hand in
+//! `src/bin/merkle_api/grpc.rs`, so no `protoc` is needed.
+
+use tonic_build::manual::{Builder, Method, MethodBuilder, Service};
+
+fn method(name: &str, route: &str, input: &str, output: &str) -> MethodBuilder {
+    Method::builder()
+        .name(name)
+        .route_name(route)
+        .input_type(format!("crate::grpc::{input}"))
+        .output_type(format!("crate::grpc::{output}"))
+        .codec_path("tonic::codec::ProstCodec")
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    let service = Service::builder()
+        .name("MerkleProofs")
+        .package("merkle.v1")
+        .method(method("get_proof", "GetProof", "GetProofRequest", "Proof").build())
+        .method(
+            method(
+                "batch_get_proof",
+                "BatchGetProof",
+                "BatchGetProofRequest",
+                "BatchGetProofResponse",
+            )
+            .build(),
+        )
+        .method(method("get_root", "GetRoot", "GetRootRequest", "Root").build())
+        .method(
+            method(
+                "export_proofs",
+                "ExportProofs",
+                "ExportProofsRequest",
+                "Proof",
+            )
+            .server_streaming()
+            .build(),
+        )
+        .build();
+    Builder::new()
+        .build_client(false)
+        .build_transport(false)
+        .compile(&[service]);
+}
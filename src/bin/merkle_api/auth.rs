@@ -0,0 +1,486 @@
+//! API keys, access tiers and per-tier rate limits.
+//!
+//! Callers identify themselves with an `X-API-Key` header (gRPC metadata
+//! works the same way). Each key belongs to a tier that sets its rate limit
+//! and the route groups it may call; every key gets its own bucket. Callers
+//! without a key use the `anonymous` tier and are limited per IP address.
+//!
+//! Keys and tiers come from the TOML file named by `--api-keys`:
+//!
+//! ```toml
+//! [tiers.anonymous]
+//! requests_per_second = 2
+//! burst = 5
+//! routes = ["proof"]
+//!
+//! [tiers.partner]
+//! requests_per_second = 50
+//! burst = 100
+//! routes = ["proof", "batch", "export", "metadata", "voucher"]
+//!
+//! [[keys]]
+//! name = "acme"
+//! key = "acme-3f9c…"
+//! tier = "partner"
+//! secret = "…"   # optional: requests must then be HMAC-signed
+//! ```
+//!
+//! Without the file every caller is anonymous and may call every route at
+//! `RATE_LIMIT_PER_SECOND` / `RATE_LIMIT_BURST_SIZE`. With the file and no
+//! `anonymous` tier, anonymous callers get those limits for `proof` and
+//! `metadata` routes only. `/health`, `/metrics`, the `OpenAPI` routes and the
+//! admin routes belong to no group and are open to every tier.
+//!
+//! A key with a `secret` must sign each request: `X-Timestamp` holds the Unix
+//! time in seconds, and `X-Signature` the hex HMAC-SHA256 of
+//! `"{timestamp}\n{METHOD}\n{path and query}\n{hex SHA-256 of the body}"`.
+//! Timestamps more than five minutes away from the server clock are rejected.
+//!
+//! Client addresses behind proxies, per-route limits for anonymous callers
+//! and the unlimited allowlist are described in [`crate::limits`].
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::net::{IpAddr, SocketAddr};
+use std::num::NonZeroU32;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::extract::{ConnectInfo, MatchedPath, Request, State};
+use axum::http::{header, HeaderMap, HeaderName, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use governor::clock::{Clock, DefaultClock};
+use governor::{DefaultKeyedRateLimiter, Quota, RateLimiter};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::campaigns::unix_now;
+use crate::limits::{self, Limits, NotFoundCount};
+use crate::ApiError;
+
+pub const ANONYMOUS_TIER: &str = "anonymous";
+
+/// How often idle rate-limit buckets are dropped.
+const PRUNE_INTERVAL: Duration = Duration::from_mins(1);
+
+pub const API_KEY_HEADER: HeaderName = HeaderName::from_static("x-api-key");
+pub const TIMESTAMP_HEADER: HeaderName = HeaderName::from_static("x-timestamp");
+pub const SIGNATURE_HEADER: HeaderName = HeaderName::from_static("x-signature");
+
+/// Largest clock difference accepted on a signed request.
+const SIGNATURE_WINDOW_SECONDS: u64 = 300;
+
+/// Largest body buffered to check a signature.
+const MAX_SIGNED_BODY: usize = 2 * 1024 * 1024;
+
+/// Route groups a tier can be granted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RouteGroup {
+    /// Single proofs by address.
+    Proof,
+    /// Batches of proofs.
+    Batch,
+    /// Positional access and `ExportProofs`, which can enumerate the list.
+    Export,
+    /// Roots, build details, signatures and campaign health.
+    Metadata,
+    /// Claim vouchers.
+    Voucher,
+}
+
+const ALL_GROUPS: [RouteGroup; 5] = [
+    RouteGroup::Proof,
+    RouteGroup::Batch,
+    RouteGroup::Export,
+    RouteGroup::Metadata,
+    RouteGroup::Voucher,
+];
+
+impl FromStr for RouteGroup {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "proof" => Ok(RouteGroup::Proof),
+            "batch" => Ok(RouteGroup::Batch),
+            "export" => Ok(RouteGroup::Export),
+            "metadata" => Ok(RouteGroup::Metadata),
+            "voucher" => Ok(RouteGroup::Voucher),
+            _ => Err(format!("unknown route group '{name}'")),
+        }
+    }
+}
+
+/// Group of a matched route, or `None` for routes open to every tier.
+pub fn route_group(route: &str) -> Option<RouteGroup> {
+    if route == "/campaigns" || route == "/campaigns/:id/health" {
+        return Some(RouteGroup::Metadata);
+    }
+    let route = route.strip_prefix("/campaigns/:id").unwrap_or(route);
+    match route {
+        "/proof/:address" | "/merkle.v1.MerkleProofs/GetProof" => Some(RouteGroup::Proof),
+        "/proofs" | "/merkle.v1.MerkleProofs/BatchGetProof" => Some(RouteGroup::Batch),
+        "/proof/index/:index"
+        | "/leaf/:index"
+        | "/node/:level/:index"
+        | "/merkle.v1.MerkleProofs/ExportProofs" => Some(RouteGroup::Export),
+        "/roots"
+        | "/root"
+        | "/info"
+        | "/stats"
+        | "/signature"
+        | "/merkle.v1.MerkleProofs/GetRoot" => Some(RouteGroup::Metadata),
+        "/voucher/:address" => Some(RouteGroup::Voucher),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TierConfig {
+    requests_per_second: u32,
+    burst: u32,
+    routes: Vec<RouteGroup>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeyConfig {
+    name: String,
+    key: String,
+    tier: String,
+    #[serde(default)]
+    secret: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeysFile {
+    #[serde(default)]
+    tiers: BTreeMap<String, TierConfig>,
+    #[serde(default)]
+    keys: Vec<KeyConfig>,
+}
+
+struct Tier {
+    name: String,
+    routes: Vec<RouteGroup>,
+    limiter: DefaultKeyedRateLimiter<String>,
+}
+
+impl Tier {
+    fn new(name: &str, quota: Quota, routes: Vec<RouteGroup>) -> Arc<Self> {
+        Arc::new(Tier {
+            name: name.to_string(),
+            routes,
+            limiter: RateLimiter::keyed(quota),
+        })
+    }
+}
+
+struct ApiKey {
+    name: String,
+    tier: Arc<Tier>,
+    secret: Option<Vec<u8>>,
+}
+
+/// Resolved keys and tiers, shared by every request.
+pub struct Access {
+    anonymous: Arc<Tier>,
+    keys: HashMap<String, ApiKey>,
+    limits: Limits,
+}
+
+impl Access {
+    /// Lets every caller call every route at the anonymous limit.
+    pub fn open(anonymous: Quota) -> Self {
+        Access {
+            anonymous: Tier::new(ANONYMOUS_TIER, anonymous, ALL_GROUPS.to_vec()),
+            keys: HashMap::new(),
+            limits: Limits::default(),
+        }
+    }
+
+    /// Reads the keys file. Performs blocking I/O.
+    pub fn load(path: &Path, anonymous: Quota) -> Result<Self, String> {
+        let raw = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        Self::parse(&raw, anonymous).map_err(|e| format!("Invalid {}: {e}", path.display()))
+    }
+
+    fn parse(raw: &str, anonymous: Quota) -> Result<Self, String> {
+        let file: KeysFile = toml::from_str(raw).map_err(|e| e.to_string())?;
+        let mut tiers = HashMap::new();
+        for (name, tier) in file.tiers {
+            let quota = quota(tier.requests_per_second, tier.burst)
+                .ok_or_else(|| format!("tier '{name}' needs a non-zero rate and burst"))?;
+            tiers.insert(name.clone(), Tier::new(&name, quota, tier.routes));
+        }
+        let anonymous = tiers.get(ANONYMOUS_TIER).cloned().unwrap_or_else(|| {
+            Tier::new(
+                ANONYMOUS_TIER,
+                anonymous,
+                vec![RouteGroup::Proof, RouteGroup::Metadata],
+            )
+        });
+
+        let mut keys = HashMap::new();
+        for key in file.keys {
+            let tier = tiers
+                .get(&key.tier)
+                .cloned()
+                .ok_or_else(|| format!("key '{}' uses unknown tier '{}'", key.name, key.tier))?;
+            if key.key.is_empty() {
+                return Err(format!("key '{}' is empty", key.name));
+            }
+            let name = key.name.clone();
+            let entry = ApiKey {
+                name: key.name,
+                tier,
+                secret: key.secret.map(String::into_bytes),
+            };
+            if keys.insert(key.key, entry).is_some() {
+                return Err(format!("key '{name}' repeats another key"));
+            }
+        }
+        Ok(Access {
+            anonymous,
+            keys,
+            limits: Limits::default(),
+        })
+    }
+
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn key_count(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Forgets the callers of every tier and limit whose buckets are full.
+    pub fn prune(&self) {
+        limits::prune(&self.anonymous.limiter);
+        for key in self.keys.values() {
+            limits::prune(&key.tier.limiter);
+        }
+        self.limits.prune();
+    }
+
+    /// Finds the caller's tier and rate-limit bucket, checking the signature
+    /// of signed keys. Returns the request, rebuilt when its body was read.
+    async fn identify(&self, request: Request) -> Result<Caller<'_>, ApiError> {
+        let ip = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|info| self.limits.client_ip(info.0.ip(), request.headers()));
+        let Some(raw) = request.headers().get(&API_KEY_HEADER) else {
+            return Ok(Caller {
+                tier: &self.anonymous,
+                client: ip.map_or_else(|| "unknown".to_string(), |ip| ip.to_string()),
+                ip,
+                anonymous: true,
+                request,
+            });
+        };
+        let key = raw
+            .to_str()
+            .ok()
+            .and_then(|raw| self.keys.get(raw))
+            .ok_or_else(|| ApiError::Unauthorized("unknown API key".to_string()))?;
+        let request = match &key.secret {
+            Some(secret) => verify_signature(secret, request).await?,
+            None => request,
+        };
+        Ok(Caller {
+            tier: &key.tier,
+            client: key.name.clone(),
+            ip,
+            anonymous: false,
+            request,
+        })
+    }
+}
+
+struct Caller<'a> {
+    tier: &'a Tier,
+    /// Rate-limit bucket: the key name, or the address of anonymous callers.
+    client: String,
+    ip: Option<IpAddr>,
+    anonymous: bool,
+    request: Request,
+}
+
+/// Prunes the rate limiters of `access` every `PRUNE_INTERVAL`.
+pub fn spawn_pruner(access: Arc<Access>) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(PRUNE_INTERVAL).await;
+            access.prune();
+        }
+    });
+}
+
+/// Builds a quota from a rate and burst size, both non-zero.
+pub fn quota(requests_per_second: u32, burst: u32) -> Option<Quota> {
+    Some(
+        Quota::per_second(NonZeroU32::new(requests_per_second)?)
+            .allow_burst(NonZeroU32::new(burst)?),
+    )
+}
+
+/// Middleware enforcing API keys, route groups and rate limits.
+pub async fn enforce(
+    State(access): State<Arc<Access>>,
+    matched: Option<MatchedPath>,
+    request: Request,
+    next: Next,
+) -> Response {
+    // CORS preflights carry no credentials; the CORS layer answers them.
+    if request.method() == Method::OPTIONS {
+        return next.run(request).await;
+    }
+    let group = matched.as_ref().and_then(|path| route_group(path.as_str()));
+    let Caller {
+        tier,
+        client,
+        ip,
+        anonymous,
+        request,
+    } = match access.identify(request).await {
+        Ok(caller) => caller,
+        Err(e) => return e.into_response(),
+    };
+    if let Some(group) = group {
+        if !tier.routes.contains(&group) {
+            return ApiError::Forbidden(format!("the {} tier cannot call this route", tier.name))
+                .into_response();
+        }
+    }
+    let limits = access.limits();
+    if ip.is_some_and(|ip| limits.is_unlimited(ip)) {
+        return next.run(request).await;
+    }
+    if let Err(not_until) = tier.limiter.check_key(&client) {
+        let wait = not_until.wait_time_from(DefaultClock::default().now());
+        return too_many_requests(
+            wait,
+            format!("rate limit of the {} tier exceeded", tier.name),
+        );
+    }
+    if !anonymous {
+        return next.run(request).await;
+    }
+    if let Err(wait) = limits.check_anonymous(group, &client) {
+        return too_many_requests(wait, "rate limit of this route exceeded".to_string());
+    }
+    let response = next.run(request).await;
+    limits.record_not_found(&client, not_found_count(&response));
+    response
+}
+
+fn too_many_requests(wait: Duration, message: String) -> Response {
+    (
+        [(header::RETRY_AFTER, retry_after(wait))],
+        ApiError::TooManyRequests(message),
+    )
+        .into_response()
+}
+
+/// One for HTTP 404 or a gRPC `NOT_FOUND` status sent in the headers,
+/// otherwise the number of `not_found` entries of a batch response.
+fn not_found_count(response: &Response) -> usize {
+    let not_found = response.status() == StatusCode::NOT_FOUND
+        || response
+            .headers()
+            .get("grpc-status")
+            .is_some_and(|status| status == "5");
+    if not_found {
+        return 1;
+    }
+    response
+        .extensions()
+        .get::<NotFoundCount>()
+        .map_or(0, |count| count.0)
+}
+
+fn retry_after(wait: Duration) -> String {
+    (wait.as_secs() + u64::from(wait.subsec_nanos() > 0)).to_string()
+}
+
+async fn verify_signature(secret: &[u8], request: Request) -> Result<Request, ApiError> {
+    let (parts, body) = request.into_parts();
+    let timestamp = header_str(&parts.headers, &TIMESTAMP_HEADER)?;
+    let signature = header_str(&parts.headers, &SIGNATURE_HEADER)?;
+    let signed_at: u64 = timestamp
+        .parse()
+        .map_err(|_| ApiError::Unauthorized("invalid x-timestamp".to_string()))?;
+    if unix_now().abs_diff(signed_at) > SIGNATURE_WINDOW_SECONDS {
+        return Err(ApiError::Unauthorized(
+            "x-timestamp is outside the accepted window".to_string(),
+        ));
+    }
+    let signature = hex::decode(signature.strip_prefix("0x").unwrap_or(signature))
+        .map_err(|_| ApiError::Unauthorized("invalid x-signature".to_string()))?;
+
+    let body = axum::body::to_bytes(body, MAX_SIGNED_BODY)
+        .await
+        .map_err(|e| ApiError::BadRequest(format!("failed to read body: {e}")))?;
+    let path = parts
+        .uri
+        .path_and_query()
+        .map_or_else(|| parts.uri.path(), |path| path.as_str());
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret)
+        .map_err(|e| ApiError::Internal(format!("invalid HMAC secret: {e}")))?;
+    mac.update(
+        format!(
+            "{timestamp}\n{}\n{path}\n{}",
+            parts.method,
+            hex::encode(Sha256::digest(&body))
+        )
+        .as_bytes(),
+    );
+    mac.verify_slice(&signature)
+        .map_err(|_| ApiError::Unauthorized("signature does not match".to_string()))?;
+    Ok(Request::from_parts(parts, Body::from(body)))
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &HeaderName) -> Result<&'a str, ApiError> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| ApiError::Unauthorized(format!("signed keys require {name}")))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prune_forgets_refilled_buckets() {
+        let access = Access::open(quota(1000, 1).unwrap());
+        for client in ["192.0.2.1", "192.0.2.2"] {
+            access
+                .anonymous
+                .limiter
+                .check_key(&client.to_string())
+                .unwrap();
+        }
+        assert_eq!(access.anonymous.limiter.len(), 2);
+        std::thread::sleep(Duration::from_millis(10));
+        access.prune();
+        assert!(access.anonymous.limiter.is_empty());
+    }
+}
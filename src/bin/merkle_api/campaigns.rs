@@ -0,0 +1,395 @@
+//! Campaigns served by one API process.
+//!
+//! A campaign is a Merkle database directory with an ID. `--data-dir` serves a
+//! single campaign named `default`; `--campaigns-dir` serves every
+//! subdirectory holding an `addresses.bin`, using the directory name as ID.
+//!
+//! Campaign directories are resolved to their canonical path when loaded, so a
+//! campaign keeps reading the same files after a symlink is repointed.
+//!
+//! Across reloads the registry also keeps the last few roots of each campaign,
+//! so proofs can still be served against a root that was recently rotated out.
+//! A retained root is read from the directory it was loaded from, so this
+//! needs one directory per version behind a symlink that is repointed; a
+//! root whose directory is rebuilt in place is dropped on the next reload.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use merklebuilder::manifest::{hash_file, Manifest, MANIFEST_FILE};
+use merklebuilder::merkle::{
+    available_layers, ensure_db_present, read_root, to_hex32, validate_database,
+};
+use merklebuilder::signing::{SignedRoot, SIGNATURE_FILE};
+use merklebuilder::{to_checksum_address, ADDRESS_SIZE};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+pub const DEFAULT_CAMPAIGN_ID: &str = "default";
+
+#[derive(Debug)]
+pub struct Campaign {
+    pub id: String,
+    pub db_dir: PathBuf,
+    pub root: [u8; 32],
+    pub leaf_count: usize,
+    pub layer_count: usize,
+    /// Unix timestamp (seconds) at which this version was loaded.
+    pub loaded_at: u64,
+    pub signed_root: Option<SignedRoot>,
+    /// `manifest.json` and its Keccak-256 hash, when the database has one.
+    pub manifest: Option<(Manifest, String)>,
+}
+
+/// One root a campaign has served.
+#[derive(Debug, Clone)]
+pub struct RootVersion {
+    pub campaign: Arc<Campaign>,
+    /// Unix timestamp (seconds) at which this root became the current one.
+    pub activated_at: u64,
+}
+
+impl RootVersion {
+    fn activate(campaign: Arc<Campaign>) -> Self {
+        let activated_at = campaign.loaded_at;
+        RootVersion {
+            campaign,
+            activated_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CampaignHealth {
+    pub id: String,
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub root: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub leaf_count: Option<usize>,
+    pub layers: usize,
+    pub signed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl CampaignHealth {
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+impl Campaign {
+    /// Validates every layer of the database and loads its `signature.json`
+    /// and `manifest.json`. Performs blocking I/O.
+    pub fn load(id: &str, db_dir: &Path, signer: Option<&[u8; 20]>) -> Result<Self, String> {
+        let db_dir = db_dir
+            .canonicalize()
+            .map_err(|e| format!("Campaign '{id}': cannot resolve {}: {e}", db_dir.display()))?;
+        let info = validate_database(&db_dir).map_err(|e| format!("Campaign '{id}': {e}"))?;
+        let signed_root =
+            load_signed_root(&db_dir, signer).map_err(|e| format!("Campaign '{id}': {e}"))?;
+        let manifest =
+            load_manifest(&db_dir, &info.root).map_err(|e| format!("Campaign '{id}': {e}"))?;
+        Ok(Campaign {
+            id: id.to_string(),
+            db_dir,
+            root: info.root,
+            leaf_count: info.leaf_count,
+            layer_count: info.layer_count,
+            loaded_at: unix_now(),
+            signed_root,
+            manifest,
+        })
+    }
+
+    /// Re-reads the root and leaf count from disk. Performs blocking I/O.
+    pub fn health(&self) -> CampaignHealth {
+        let layers = available_layers(&self.db_dir).len();
+        let checked = ensure_db_present(&self.db_dir)
+            .and_then(|()| read_root(&self.db_dir))
+            .map_err(|e| e.to_string())
+            .and_then(|(root, _)| Ok((root, leaf_count(&self.db_dir)?)));
+        let (root, leaf_count, error) = match checked {
+            Ok((root, count)) => (Some(to_hex32(&root)), Some(count), None),
+            Err(e) => (None, None, Some(e)),
+        };
+        CampaignHealth {
+            id: self.id.clone(),
+            status: if error.is_none() { "ok" } else { "unavailable" },
+            root,
+            leaf_count,
+            layers,
+            signed: self.signed_root.is_some(),
+            error,
+        }
+    }
+}
+
+/// Where campaigns are loaded from, at startup and on every reload.
+#[derive(Debug, Clone)]
+pub enum CampaignSource {
+    Single(PathBuf),
+    Directory {
+        dir: PathBuf,
+        default_id: Option<String>,
+    },
+}
+
+impl CampaignSource {
+    /// Loads and validates every campaign. Performs blocking I/O.
+    pub fn load(&self, signer: Option<&[u8; 20]>) -> Result<CampaignRegistry, String> {
+        match self {
+            CampaignSource::Single(db_dir) => CampaignRegistry::single(db_dir, signer),
+            CampaignSource::Directory { dir, default_id } => {
+                CampaignRegistry::load_dir(dir, default_id.as_deref(), signer)
+            }
+        }
+    }
+
+    /// Lists the database directories this source currently points at.
+    pub fn db_dirs(&self) -> Vec<PathBuf> {
+        match self {
+            CampaignSource::Single(db_dir) => vec![db_dir.clone()],
+            CampaignSource::Directory { dir, .. } => fs::read_dir(dir)
+                .map(|entries| {
+                    let mut dirs: Vec<PathBuf> = entries
+                        .filter_map(|entry| entry.ok().map(|e| e.path()))
+                        .filter(|path| path.join("addresses.bin").is_file())
+                        .collect();
+                    dirs.sort();
+                    dirs
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct CampaignRegistry {
+    campaigns: BTreeMap<String, Arc<Campaign>>,
+    /// Retained roots of every campaign, newest first; the first is current.
+    versions: BTreeMap<String, Vec<RootVersion>>,
+    default_id: String,
+}
+
+impl CampaignRegistry {
+    /// Serves one database as the `default` campaign.
+    pub fn single(db_dir: &Path, signer: Option<&[u8; 20]>) -> Result<Self, String> {
+        let campaign = Campaign::load(DEFAULT_CAMPAIGN_ID, db_dir, signer)?;
+        Ok(CampaignRegistry::new(
+            BTreeMap::from([(DEFAULT_CAMPAIGN_ID.to_string(), Arc::new(campaign))]),
+            DEFAULT_CAMPAIGN_ID.to_string(),
+        ))
+    }
+
+    /// Loads every database directory under `dir`.
+    ///
+    /// The default campaign is `default_id` when given, otherwise the
+    /// campaign named `default`, otherwise the only campaign.
+    pub fn load_dir(
+        dir: &Path,
+        default_id: Option<&str>,
+        signer: Option<&[u8; 20]>,
+    ) -> Result<Self, String> {
+        let entries = fs::read_dir(dir)
+            .map_err(|e| format!("Failed to read campaigns directory {}: {e}", dir.display()))?;
+        let mut campaigns = BTreeMap::new();
+        for entry in entries {
+            let path = entry
+                .map_err(|e| format!("Failed to read campaigns directory {}: {e}", dir.display()))?
+                .path();
+            if !path.join("addresses.bin").is_file() {
+                continue;
+            }
+            let Some(id) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            if !is_valid_campaign_id(id) {
+                return Err(format!(
+                    "Invalid campaign ID '{id}': use letters, digits, '-' and '_'"
+                ));
+            }
+            let campaign = Campaign::load(id, &path, signer)?;
+            campaigns.insert(id.to_string(), Arc::new(campaign));
+        }
+        if campaigns.is_empty() {
+            return Err(format!(
+                "No campaigns found in {} (expected subdirectories with addresses.bin)",
+                dir.display()
+            ));
+        }
+
+        let default_id = match default_id {
+            Some(id) if campaigns.contains_key(id) => id.to_string(),
+            Some(id) => return Err(format!("Default campaign '{id}' not found")),
+            None if campaigns.contains_key(DEFAULT_CAMPAIGN_ID) => DEFAULT_CAMPAIGN_ID.to_string(),
+            None if campaigns.len() == 1 => campaigns.keys().next().cloned().unwrap_or_default(),
+            None => {
+                return Err(
+                    "Several campaigns found; pass --default-campaign <id> to pick the one served by the unprefixed routes"
+                        .to_string(),
+                )
+            }
+        };
+        Ok(CampaignRegistry::new(campaigns, default_id))
+    }
+
+    fn new(campaigns: BTreeMap<String, Arc<Campaign>>, default_id: String) -> Self {
+        let versions = campaigns
+            .iter()
+            .map(|(id, campaign)| {
+                (
+                    id.clone(),
+                    vec![RootVersion::activate(Arc::clone(campaign))],
+                )
+            })
+            .collect();
+        CampaignRegistry {
+            campaigns,
+            versions,
+            default_id,
+        }
+    }
+
+    /// Carries over the roots retained by `previous`, keeping at most `retain`
+    /// roots per campaign.
+    ///
+    /// A campaign whose root did not change keeps its activation time.
+    /// Campaigns that are no longer served drop their history. Roots read
+    /// from the directory now holding the current version are dropped too:
+    /// their files were rebuilt in place, so they can no longer be served.
+    pub fn retain_history(&mut self, previous: &CampaignRegistry, retain: usize) {
+        for (id, versions) in &mut self.versions {
+            let Some(older) = previous.versions.get(id) else {
+                continue;
+            };
+            let Some(current) = versions.first_mut() else {
+                continue;
+            };
+            if let Some(previous) = older.first() {
+                if current.campaign.root == previous.campaign.root {
+                    current.activated_at = previous.activated_at;
+                }
+            }
+            let current_dir = current.campaign.db_dir.clone();
+            for version in older {
+                if version.campaign.db_dir != current_dir
+                    && !versions
+                        .iter()
+                        .any(|kept| kept.campaign.root == version.campaign.root)
+                {
+                    versions.push(version.clone());
+                }
+            }
+            versions.truncate(retain.max(1));
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<Campaign>> {
+        self.campaigns.get(id).cloned()
+    }
+
+    pub fn default_id(&self) -> &str {
+        &self.default_id
+    }
+
+    pub fn default_campaign(&self) -> Arc<Campaign> {
+        Arc::clone(&self.campaigns[&self.default_id])
+    }
+
+    /// Retained roots of a campaign, newest first.
+    pub fn versions(&self, id: &str) -> &[RootVersion] {
+        self.versions.get(id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Finds the version of a campaign that served `root`.
+    pub fn at_root(&self, id: &str, root: &[u8; 32]) -> Option<Arc<Campaign>> {
+        self.versions(id)
+            .iter()
+            .find(|version| version.campaign.root == *root)
+            .map(|version| Arc::clone(&version.campaign))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<Campaign>> {
+        self.campaigns.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.campaigns.len()
+    }
+}
+
+pub fn is_valid_campaign_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Loads `signature.json` and checks that it attests the served database.
+///
+/// When `expected` is set, the database must be signed by that address.
+pub fn load_signed_root(
+    db_dir: &Path,
+    expected: Option<&[u8; 20]>,
+) -> Result<Option<SignedRoot>, String> {
+    let Some(signed) = SignedRoot::read(db_dir).map_err(|e| e.to_string())? else {
+        return match expected {
+            Some(_) => Err(format!(
+                "--signer was given but {} is missing from {}",
+                SIGNATURE_FILE,
+                db_dir.display()
+            )),
+            None => Ok(None),
+        };
+    };
+    let recovered = signed.verify_database(db_dir).map_err(|e| e.to_string())?;
+    if let Some(expected) = expected {
+        if recovered != *expected {
+            return Err(format!(
+                "Database is signed by {}, expected {}",
+                to_checksum_address(&recovered),
+                to_checksum_address(expected)
+            ));
+        }
+    }
+    Ok(Some(signed))
+}
+
+/// Loads `manifest.json` and checks that it describes the served root.
+pub fn load_manifest(db_dir: &Path, root: &[u8; 32]) -> Result<Option<(Manifest, String)>, String> {
+    if !db_dir.join(MANIFEST_FILE).exists() {
+        return Ok(None);
+    }
+    let manifest = Manifest::read(db_dir).map_err(|e| e.to_string())?;
+    if !manifest.root.eq_ignore_ascii_case(&to_hex32(root)) {
+        return Err(format!(
+            "{} records root {} but the layers hold {}",
+            MANIFEST_FILE,
+            manifest.root,
+            to_hex32(root)
+        ));
+    }
+    let (hash, _) = hash_file(&db_dir.join(MANIFEST_FILE)).map_err(|e| e.to_string())?;
+    Ok(Some((manifest, hash)))
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn leaf_count(db_dir: &Path) -> Result<usize, String> {
+    let path = db_dir.join("addresses.bin");
+    let len = fs::metadata(&path)
+        .map_err(|e| format!("Failed to stat {}: {e}", path.display()))?
+        .len();
+    usize::try_from(len / ADDRESS_SIZE as u64)
+        .map_err(|_| format!("{} is too large", path.display()))
+}
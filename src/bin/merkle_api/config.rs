@@ -0,0 +1,754 @@
+//! Server settings from flags, environment variables and a config file.
+//!
+//! `--config` names a TOML file holding any of the settings below. A flag
+//! overrides its environment variable, which overrides the file; anything
+//! left unset gets its default. Invalid values and unknown keys are errors,
+//! so a typo stops the server instead of silently falling back. Relative
+//! paths in the file are relative to the file itself.
+//!
+//! ```toml
+//! [listener]
+//! address = "0.0.0.0:3000"          # --listen, or
+//! unix_socket = "/run/merkle_api/api.sock" # --unix-socket
+//! tls_cert = "tls/cert.pem"         # --tls-cert
+//! tls_key = "tls/key.pem"           # --tls-key
+//! tls_client_ca = "tls/clients.pem" # --tls-client-ca
+//!
+//! [data]
+//! dir = "merkledb"                  # --data-dir, or
+//! campaigns_dir = "campaigns"       # --campaigns-dir
+//! default_campaign = "summer"       # --default-campaign
+//! signer = "0x…"                    # --signer
+//! watch_interval = 30               # --watch-interval, in seconds
+//! retain_roots = 5                  # --retain-roots
+//!
+//! [cors]
+//! allowed_origins = ["https://claim.example.org"] # ALLOWED_ORIGINS
+//!
+//! [rate_limit]
+//! requests_per_second = 20          # RATE_LIMIT_PER_SECOND
+//! burst = 50                        # RATE_LIMIT_BURST_SIZE
+//! api_keys = "keys.toml"            # --api-keys
+//! trusted_proxies = ["10.0.0.0/8"]  # TRUSTED_PROXIES
+//! allowlist = ["192.0.2.10"]        # RATE_LIMIT_ALLOWLIST
+//! not_found = "1/10"                # NOT_FOUND_RATE_LIMIT
+//! max_batch_size = 1000             # MAX_BATCH_SIZE
+//! routes = { batch = "1/5" }        # ROUTE_RATE_LIMITS
+//!
+//! [cache]
+//! proof_cache_size = 10000          # PROOF_CACHE_SIZE
+//! pinned_layers = 16                # PINNED_LAYERS
+//! cache_control = "public, max-age=60"                      # PROOF_CACHE_CONTROL
+//! root_cache_control = "public, max-age=86400, immutable"   # ROOT_PROOF_CACHE_CONTROL
+//!
+//! [logging]
+//! level = "info"                    # RUST_LOG
+//! format = "text"                   # LOG_FORMAT, text or json
+//! otlp_endpoint = "http://localhost:4318" # OTEL_EXPORTER_OTLP_ENDPOINT
+//! ```
+//!
+//! Empty variables count as unset, except that an empty `PROOF_CACHE_CONTROL`
+//! or `ROOT_PROOF_CACHE_CONTROL` omits the header, like an empty string in
+//! the file. Secrets (`ADMIN_TOKEN`, `VOUCHER_*`) are only read from the
+//! environment.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use axum::http::HeaderValue;
+use governor::Quota;
+use ipnet::IpNet;
+use merklebuilder::merkle::parse_address;
+use serde::Deserialize;
+use tracing_subscriber::EnvFilter;
+
+use crate::auth::{self, RouteGroup};
+use crate::http_cache::{CachePolicy, DEFAULT_CACHE_CONTROL, DEFAULT_ROOT_CACHE_CONTROL};
+use crate::limits::{self, Limits};
+use crate::listener::ListenAddr;
+use crate::proof_cache::{DEFAULT_PINNED_LAYERS, DEFAULT_PROOF_CACHE_SIZE};
+use crate::telemetry::LogSettings;
+use crate::tls::TlsSettings;
+
+pub const DEFAULT_LISTEN: &str = "127.0.0.1:3000";
+pub const DEFAULT_DATA_DIR: &str = "merkledb";
+pub const DEFAULT_ALLOWED_ORIGINS: &str = "http://localhost:3000";
+pub const DEFAULT_RATE_LIMIT_PER_SECOND: u32 = 20;
+pub const DEFAULT_RATE_LIMIT_BURST_SIZE: u32 = 50;
+pub const DEFAULT_RETAIN_ROOTS: usize = 5;
+pub const DEFAULT_MAX_BATCH_SIZE: usize = 1000;
+pub const DEFAULT_LOG_LEVEL: &str = "info";
+
+/// One source of settings. Unset fields fall through to the next source.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    #[serde(default)]
+    pub listener: ListenerSettings,
+    #[serde(default)]
+    pub data: DataSettings,
+    #[serde(default)]
+    pub cors: CorsSettings,
+    #[serde(default)]
+    pub rate_limit: RateLimitSettings,
+    #[serde(default)]
+    pub cache: CacheSettings,
+    #[serde(default)]
+    pub logging: LoggingSettings,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ListenerSettings {
+    pub address: Option<SocketAddr>,
+    pub unix_socket: Option<PathBuf>,
+    pub tls_cert: Option<PathBuf>,
+    pub tls_key: Option<PathBuf>,
+    pub tls_client_ca: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DataSettings {
+    pub dir: Option<PathBuf>,
+    pub campaigns_dir: Option<PathBuf>,
+    pub default_campaign: Option<String>,
+    pub signer: Option<String>,
+    /// Seconds between checks of the campaign files.
+    pub watch_interval: Option<u64>,
+    pub retain_roots: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CorsSettings {
+    pub allowed_origins: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitSettings {
+    pub requests_per_second: Option<u32>,
+    pub burst: Option<u32>,
+    pub api_keys: Option<PathBuf>,
+    pub trusted_proxies: Option<Vec<String>>,
+    pub allowlist: Option<Vec<String>>,
+    /// `<requests per second>/<burst>` by route group.
+    pub routes: Option<HashMap<RouteGroup, String>>,
+    pub not_found: Option<String>,
+    pub max_batch_size: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CacheSettings {
+    pub proof_cache_size: Option<usize>,
+    pub pinned_layers: Option<usize>,
+    pub cache_control: Option<String>,
+    pub root_cache_control: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LoggingSettings {
+    pub level: Option<String>,
+    pub format: Option<String>,
+    pub otlp_endpoint: Option<String>,
+}
+
+impl Settings {
+    /// Reads the config file. Performs blocking I/O.
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let raw = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        let settings = Self::parse(&raw).map_err(|e| format!("Invalid {}: {e}", path.display()))?;
+        Ok(settings.relative_to(path.parent().unwrap_or(Path::new(""))))
+    }
+
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        toml::from_str(raw).map_err(|e| e.to_string())
+    }
+
+    /// Reads the environment variables through `var`, which returns the
+    /// value of a variable or `None` when it is unset.
+    pub fn from_env(var: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let env = Env(var);
+        Ok(Settings {
+            listener: ListenerSettings::default(),
+            data: DataSettings::default(),
+            cors: CorsSettings {
+                allowed_origins: env.list("ALLOWED_ORIGINS"),
+            },
+            rate_limit: RateLimitSettings {
+                requests_per_second: env.number("RATE_LIMIT_PER_SECOND")?,
+                burst: env.number("RATE_LIMIT_BURST_SIZE")?,
+                api_keys: None,
+                trusted_proxies: env.list("TRUSTED_PROXIES"),
+                allowlist: env.list("RATE_LIMIT_ALLOWLIST"),
+                routes: env
+                    .string("ROUTE_RATE_LIMITS")
+                    .map(|raw| parse_route_limits(&raw))
+                    .transpose()?,
+                not_found: env.string("NOT_FOUND_RATE_LIMIT"),
+                max_batch_size: env.number("MAX_BATCH_SIZE")?,
+            },
+            cache: CacheSettings {
+                proof_cache_size: env.number("PROOF_CACHE_SIZE")?,
+                pinned_layers: env.number("PINNED_LAYERS")?,
+                cache_control: env.header("PROOF_CACHE_CONTROL"),
+                root_cache_control: env.header("ROOT_PROOF_CACHE_CONTROL"),
+            },
+            logging: LoggingSettings {
+                level: env.string("RUST_LOG"),
+                format: env.string("LOG_FORMAT"),
+                otlp_endpoint: env.string("OTEL_EXPORTER_OTLP_ENDPOINT"),
+            },
+        })
+    }
+
+    /// Resolves the paths of the file against `base`.
+    fn relative_to(mut self, base: &Path) -> Self {
+        let paths = [
+            &mut self.listener.unix_socket,
+            &mut self.listener.tls_cert,
+            &mut self.listener.tls_key,
+            &mut self.listener.tls_client_ca,
+            &mut self.data.dir,
+            &mut self.data.campaigns_dir,
+            &mut self.rate_limit.api_keys,
+        ];
+        for path in paths.into_iter().flatten() {
+            *path = base.join(&*path);
+        }
+        self
+    }
+
+    /// Fills the fields left unset here from `lower`.
+    #[must_use]
+    pub fn or(self, lower: Settings) -> Settings {
+        Settings {
+            listener: self.listener.or(lower.listener),
+            data: self.data.or(lower.data),
+            cors: CorsSettings {
+                allowed_origins: self.cors.allowed_origins.or(lower.cors.allowed_origins),
+            },
+            rate_limit: RateLimitSettings {
+                requests_per_second: self
+                    .rate_limit
+                    .requests_per_second
+                    .or(lower.rate_limit.requests_per_second),
+                burst: self.rate_limit.burst.or(lower.rate_limit.burst),
+                api_keys: self.rate_limit.api_keys.or(lower.rate_limit.api_keys),
+                trusted_proxies: self
+                    .rate_limit
+                    .trusted_proxies
+                    .or(lower.rate_limit.trusted_proxies),
+                allowlist: self.rate_limit.allowlist.or(lower.rate_limit.allowlist),
+                routes: self.rate_limit.routes.or(lower.rate_limit.routes),
+                not_found: self.rate_limit.not_found.or(lower.rate_limit.not_found),
+                max_batch_size: self
+                    .rate_limit
+                    .max_batch_size
+                    .or(lower.rate_limit.max_batch_size),
+            },
+            cache: CacheSettings {
+                proof_cache_size: self.cache.proof_cache_size.or(lower.cache.proof_cache_size),
+                pinned_layers: self.cache.pinned_layers.or(lower.cache.pinned_layers),
+                cache_control: self.cache.cache_control.or(lower.cache.cache_control),
+                root_cache_control: self
+                    .cache
+                    .root_cache_control
+                    .or(lower.cache.root_cache_control),
+            },
+            logging: LoggingSettings {
+                level: self.logging.level.or(lower.logging.level),
+                format: self.logging.format.or(lower.logging.format),
+                otlp_endpoint: self.logging.otlp_endpoint.or(lower.logging.otlp_endpoint),
+            },
+        }
+    }
+}
+
+impl ListenerSettings {
+    /// A TCP address replaces the Unix socket of a lower source, and the
+    /// other way round.
+    fn or(self, mut lower: ListenerSettings) -> ListenerSettings {
+        if self.address.is_some() {
+            lower.unix_socket = None;
+        }
+        if self.unix_socket.is_some() {
+            lower.address = None;
+        }
+        ListenerSettings {
+            address: self.address.or(lower.address),
+            unix_socket: self.unix_socket.or(lower.unix_socket),
+            tls_cert: self.tls_cert.or(lower.tls_cert),
+            tls_key: self.tls_key.or(lower.tls_key),
+            tls_client_ca: self.tls_client_ca.or(lower.tls_client_ca),
+        }
+    }
+}
+
+impl DataSettings {
+    /// A data directory replaces the campaigns directory of a lower source,
+    /// and the other way round, so `--data-dir` can override a file that
+    /// sets `campaigns_dir`.
+    fn or(self, mut lower: DataSettings) -> DataSettings {
+        if self.dir.is_some() {
+            lower.campaigns_dir = None;
+            lower.default_campaign = None;
+        }
+        if self.campaigns_dir.is_some() {
+            lower.dir = None;
+        }
+        DataSettings {
+            dir: self.dir.or(lower.dir),
+            campaigns_dir: self.campaigns_dir.or(lower.campaigns_dir),
+            default_campaign: self.default_campaign.or(lower.default_campaign),
+            signer: self.signer.or(lower.signer),
+            watch_interval: self.watch_interval.or(lower.watch_interval),
+            retain_roots: self.retain_roots.or(lower.retain_roots),
+        }
+    }
+}
+
+struct Env<F>(F);
+
+impl<F: Fn(&str) -> Option<String>> Env<F> {
+    /// The trimmed value of a variable that is set and not blank.
+    fn string(&self, name: &str) -> Option<String> {
+        (self.0)(name)
+            .map(|raw| raw.trim().to_string())
+            .filter(|raw| !raw.is_empty())
+    }
+
+    /// Like [`Env::string`], but keeps empty values, which omit a header.
+    fn header(&self, name: &str) -> Option<String> {
+        (self.0)(name).map(|raw| raw.trim().to_string())
+    }
+
+    fn number<T: FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        self.string(name)
+            .map(|raw| {
+                raw.parse()
+                    .map_err(|_| format!("{name} must be a non-negative integer, got '{raw}'"))
+            })
+            .transpose()
+    }
+
+    /// Comma-separated entries.
+    fn list(&self, name: &str) -> Option<Vec<String>> {
+        self.string(name).map(|raw| {
+            raw.split(',')
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty())
+                .map(String::from)
+                .collect()
+        })
+    }
+}
+
+/// Parses `ROUTE_RATE_LIMITS`, for example `batch=1/5,export=1/2`.
+fn parse_route_limits(raw: &str) -> Result<HashMap<RouteGroup, String>, String> {
+    let mut routes = HashMap::new();
+    for entry in raw.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (group, rate) = entry
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid ROUTE_RATE_LIMITS entry '{entry}'"))?;
+        let group: RouteGroup = group
+            .trim()
+            .parse()
+            .map_err(|e| format!("Invalid ROUTE_RATE_LIMITS: {e}"))?;
+        routes.insert(group, rate.trim().to_string());
+    }
+    Ok(routes)
+}
+
+/// Validated settings with defaults filled in.
+#[derive(Debug)]
+pub struct Config {
+    /// The `--config` file, if any.
+    pub file: Option<PathBuf>,
+    /// Where to listen unless systemd passes a socket.
+    pub listen: ListenAddr,
+    pub data_dir: PathBuf,
+    pub campaigns_dir: Option<PathBuf>,
+    pub default_campaign: Option<String>,
+    pub signer: Option<[u8; 20]>,
+    pub watch_interval: Option<Duration>,
+    pub retain_roots: usize,
+    pub tls: Option<TlsSettings>,
+    pub allowed_origins: Vec<HeaderValue>,
+    pub api_keys: Option<PathBuf>,
+    /// Rate limit of anonymous callers, unless the keys file sets one.
+    pub anonymous: Quota,
+    pub trusted_proxies: Vec<IpNet>,
+    pub allowlist: Vec<IpNet>,
+    pub route_limits: Vec<(RouteGroup, Quota)>,
+    pub not_found_limit: Option<Quota>,
+    pub max_batch_size: usize,
+    pub proof_cache_size: usize,
+    pub pinned_layers: usize,
+    pub cache_policy: CachePolicy,
+    pub logging: LogSettings,
+}
+
+impl Config {
+    /// Layers the flags over the environment and the `--config` file.
+    /// Performs blocking I/O.
+    pub fn load(file: Option<PathBuf>, flags: Settings) -> Result<Self, String> {
+        let env = Settings::from_env(|name| env::var(name).ok())?;
+        let from_file = match &file {
+            Some(path) => Settings::from_file(path)?,
+            None => Settings::default(),
+        };
+        let mut config = Config::resolve(flags.or(env).or(from_file))?;
+        config.file = file;
+        Ok(config)
+    }
+
+    /// Validates merged settings and applies the defaults.
+    pub fn resolve(settings: Settings) -> Result<Self, String> {
+        let Settings {
+            listener,
+            data,
+            cors,
+            rate_limit,
+            cache,
+            logging,
+        } = settings;
+
+        if data.dir.is_some() && data.campaigns_dir.is_some() {
+            return Err("--data-dir and --campaigns-dir cannot be combined".to_string());
+        }
+        if data.default_campaign.is_some() && data.campaigns_dir.is_none() {
+            return Err("--default-campaign requires --campaigns-dir".to_string());
+        }
+        let anonymous = auth::quota(
+            rate_limit
+                .requests_per_second
+                .unwrap_or(DEFAULT_RATE_LIMIT_PER_SECOND),
+            rate_limit.burst.unwrap_or(DEFAULT_RATE_LIMIT_BURST_SIZE),
+        )
+        .ok_or_else(|| {
+            "RATE_LIMIT_PER_SECOND and RATE_LIMIT_BURST_SIZE (rate_limit.requests_per_second and rate_limit.burst) must be non-zero".to_string()
+        })?;
+        let networks = |entries: Option<Vec<String>>, name: &str| {
+            limits::parse_networks(&entries.unwrap_or_default().join(","))
+                .map_err(|e| format!("Invalid {name}: {e}"))
+        };
+        let mut route_limits = Vec::new();
+        for (group, rate) in rate_limit.routes.unwrap_or_default() {
+            let quota = limits::parse_rate(&rate)
+                .map_err(|e| format!("Invalid ROUTE_RATE_LIMITS (rate_limit.routes): {e}"))?;
+            route_limits.push((group, quota));
+        }
+
+        Ok(Config {
+            file: None,
+            listen: listen_addr(&listener)?,
+            data_dir: data.dir.unwrap_or_else(|| PathBuf::from(DEFAULT_DATA_DIR)),
+            campaigns_dir: data.campaigns_dir,
+            default_campaign: data.default_campaign,
+            signer: data
+                .signer
+                .map(|raw| parse_address(&raw).map_err(|e| format!("Invalid signer '{raw}': {e}")))
+                .transpose()?,
+            watch_interval: data
+                .watch_interval
+                .map(|seconds| non_zero(seconds, "--watch-interval (data.watch_interval)").map(Duration::from_secs))
+                .transpose()?,
+            retain_roots: non_zero(
+                data.retain_roots.unwrap_or(DEFAULT_RETAIN_ROOTS),
+                "--retain-roots (data.retain_roots)",
+            )?,
+            tls: tls_settings(listener)?,
+            allowed_origins: cors
+                .allowed_origins
+                .unwrap_or_else(|| vec![DEFAULT_ALLOWED_ORIGINS.to_string()])
+                .iter()
+                .map(|origin| {
+                    HeaderValue::from_str(origin)
+                        .map_err(|_| format!("Invalid CORS origin '{origin}' in ALLOWED_ORIGINS (cors.allowed_origins)"))
+                })
+                .collect::<Result<_, _>>()?,
+            api_keys: rate_limit.api_keys,
+            anonymous,
+            trusted_proxies: networks(rate_limit.trusted_proxies, "TRUSTED_PROXIES (rate_limit.trusted_proxies)")?,
+            allowlist: networks(rate_limit.allowlist, "RATE_LIMIT_ALLOWLIST (rate_limit.allowlist)")?,
+            route_limits,
+            not_found_limit: rate_limit
+                .not_found
+                .map(|rate| {
+                    limits::parse_rate(&rate).map_err(|e| format!("Invalid NOT_FOUND_RATE_LIMIT (rate_limit.not_found): {e}"))
+                })
+                .transpose()?,
+            max_batch_size: non_zero(
+                rate_limit.max_batch_size.unwrap_or(DEFAULT_MAX_BATCH_SIZE),
+                "MAX_BATCH_SIZE (rate_limit.max_batch_size)",
+            )?,
+            proof_cache_size: cache.proof_cache_size.unwrap_or(DEFAULT_PROOF_CACHE_SIZE),
+            pinned_layers: cache.pinned_layers.unwrap_or(DEFAULT_PINNED_LAYERS),
+            cache_policy: CachePolicy::new(
+                cache_control(
+                    cache.cache_control.as_deref(),
+                    DEFAULT_CACHE_CONTROL,
+                    "PROOF_CACHE_CONTROL (cache.cache_control)",
+                )?,
+                cache_control(
+                    cache.root_cache_control.as_deref(),
+                    DEFAULT_ROOT_CACHE_CONTROL,
+                    "ROOT_PROOF_CACHE_CONTROL (cache.root_cache_control)",
+                )?,
+            ),
+            logging: log_settings(logging)?,
+        })
+    }
+
+    /// Proxy trust, allowlist and anonymous limits for [`crate::auth::Access`].
+    pub fn limits(&self) -> Limits {
+        let mut limits = Limits::default()
+            .with_trusted_proxies(self.trusted_proxies.clone())
+            .with_unlimited(self.allowlist.clone());
+        for (group, quota) in &self.route_limits {
+            limits = limits.with_route_limit(*group, *quota);
+        }
+        if let Some(quota) = self.not_found_limit {
+            limits = limits.with_not_found_limit(quota);
+        }
+        limits
+    }
+}
+
+fn listen_addr(listener: &ListenerSettings) -> Result<ListenAddr, String> {
+    match (listener.address, &listener.unix_socket) {
+        (Some(_), Some(_)) => Err("--listen and --unix-socket cannot be combined".to_string()),
+        (None, Some(path)) => Ok(ListenAddr::Unix(path.clone())),
+        (Some(address), None) => Ok(ListenAddr::Tcp(address)),
+        (None, None) => DEFAULT_LISTEN
+            .parse()
+            .map(ListenAddr::Tcp)
+            .map_err(|e| format!("Invalid default listen address: {e}")),
+    }
+}
+
+fn tls_settings(listener: ListenerSettings) -> Result<Option<TlsSettings>, String> {
+    let tls = listener.tls_cert.is_some() || listener.tls_key.is_some();
+    if tls && listener.unix_socket.is_some() {
+        return Err("TLS is not supported on --unix-socket".to_string());
+    }
+    match (listener.tls_cert, listener.tls_key) {
+        (Some(cert), Some(key)) => Ok(Some(TlsSettings {
+            cert,
+            key,
+            client_ca: listener.tls_client_ca,
+        })),
+        (None, None) if listener.tls_client_ca.is_some() => {
+            Err("--tls-client-ca requires --tls-cert and --tls-key".to_string())
+        }
+        (None, None) => Ok(None),
+        _ => Err("--tls-cert and --tls-key must be given together".to_string()),
+    }
+}
+
+fn non_zero<T: Default + PartialEq>(value: T, name: &str) -> Result<T, String> {
+    if value == T::default() {
+        Err(format!("{name} must be greater than zero"))
+    } else {
+        Ok(value)
+    }
+}
+
+/// An empty value omits the header.
+fn cache_control(
+    raw: Option<&str>,
+    default: &'static str,
+    name: &str,
+) -> Result<Option<HeaderValue>, String> {
+    match raw.map(str::trim) {
+        None => Ok(Some(HeaderValue::from_static(default))),
+        Some("") => Ok(None),
+        Some(value) => HeaderValue::from_str(value)
+            .map(Some)
+            .map_err(|_| format!("Invalid {name} '{value}'")),
+    }
+}
+
+fn log_settings(logging: LoggingSettings) -> Result<LogSettings, String> {
+    let filter = logging
+        .level
+        .unwrap_or_else(|| DEFAULT_LOG_LEVEL.to_string());
+    EnvFilter::try_new(&filter)
+        .map_err(|e| format!("Invalid RUST_LOG (logging.level) '{filter}': {e}"))?;
+    let json = match logging.format.as_deref() {
+        Some("json") => true,
+        Some("text") | None => false,
+        Some(other) => {
+            return Err(format!(
+                "Invalid LOG_FORMAT (logging.format) '{other}', use text or json"
+            ))
+        }
+    };
+    Ok(LogSettings {
+        filter,
+        json,
+        otlp_endpoint: logging
+            .otlp_endpoint
+            .filter(|endpoint| !endpoint.trim().is_empty()),
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use axum::http::{header, HeaderMap};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_config_precedence() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("merkle_api.toml");
+        std::fs::write(
+            &file,
+            r#"
+[listener]
+address = "0.0.0.0:4000"
+
+[data]
+campaigns_dir = "campaigns"
+default_campaign = "summer"
+retain_roots = 3
+
+[cors]
+allowed_origins = ["https://claim.example.org"]
+
+[rate_limit]
+requests_per_second = 5
+burst = 10
+trusted_proxies = ["10.0.0.0/8"]
+routes = { batch = "1/5" }
+
+[cache]
+proof_cache_size = 100
+cache_control = ""
+
+[logging]
+format = "json"
+"#,
+        )
+        .unwrap();
+        let from_file = Settings::from_file(&file).unwrap();
+        assert_eq!(
+            from_file.data.campaigns_dir,
+            Some(temp.path().join("campaigns"))
+        );
+
+        let env = Settings::from_env(|name| match name {
+            "RATE_LIMIT_PER_SECOND" => Some("7".to_string()),
+            "PROOF_CACHE_SIZE" => Some("200".to_string()),
+            "ALLOWED_ORIGINS" => Some(" ".to_string()),
+            _ => None,
+        })
+        .unwrap();
+        let mut flags = Settings::default();
+        flags.listener.address = Some("127.0.0.1:5000".parse().unwrap());
+        flags.rate_limit.requests_per_second = Some(9);
+
+        let config = Config::resolve(flags.or(env).or(from_file)).unwrap();
+        assert_eq!(
+            config.listen,
+            ListenAddr::Tcp("127.0.0.1:5000".parse().unwrap())
+        );
+        assert_eq!(config.campaigns_dir, Some(temp.path().join("campaigns")));
+        assert_eq!(config.default_campaign.as_deref(), Some("summer"));
+        assert_eq!(config.retain_roots, 3);
+        assert_eq!(config.anonymous, auth::quota(9, 10).unwrap());
+        assert_eq!(config.proof_cache_size, 200);
+        assert_eq!(config.pinned_layers, DEFAULT_PINNED_LAYERS);
+        assert_eq!(
+            config.allowed_origins,
+            vec![HeaderValue::from_static("https://claim.example.org")]
+        );
+        assert_eq!(config.trusted_proxies.len(), 1);
+        assert_eq!(
+            config.route_limits,
+            vec![(auth::RouteGroup::Batch, auth::quota(1, 5).unwrap())]
+        );
+        assert!(config.logging.json);
+        let mut headers = HeaderMap::new();
+        config
+            .cache_policy
+            .apply(&mut headers, HeaderValue::from_static("\"etag\""), false);
+        assert!(headers.get(header::CACHE_CONTROL).is_none());
+
+        // A data directory from a flag replaces the campaigns directory.
+        let mut flags = Settings::default();
+        flags.data.dir = Some(PathBuf::from("merkledb"));
+        let config = Config::resolve(flags.or(Settings::from_file(&file).unwrap())).unwrap();
+        assert_eq!(config.data_dir, PathBuf::from("merkledb"));
+        assert_eq!(config.campaigns_dir, None);
+        assert_eq!(config.default_campaign, None);
+
+        // So does a Unix socket from a flag for the TCP address.
+        let mut flags = Settings::default();
+        flags.listener.unix_socket = Some(PathBuf::from("api.sock"));
+        let config = Config::resolve(flags.or(Settings::from_file(&file).unwrap())).unwrap();
+        assert_eq!(config.listen, ListenAddr::Unix(PathBuf::from("api.sock")));
+
+        let defaults = Config::resolve(Settings::default()).unwrap();
+        assert_eq!(
+            defaults.listen,
+            ListenAddr::Tcp(DEFAULT_LISTEN.parse().unwrap())
+        );
+        assert_eq!(defaults.data_dir, PathBuf::from(DEFAULT_DATA_DIR));
+        assert_eq!(defaults.max_batch_size, DEFAULT_MAX_BATCH_SIZE);
+        assert_eq!(defaults.logging.filter, DEFAULT_LOG_LEVEL);
+    }
+
+    #[test]
+    fn test_config_rejects_invalid_values() {
+        let env = |name: &'static str, value: &'static str| {
+            Settings::from_env(move |var| (var == name).then(|| value.to_string()))
+                .and_then(Config::resolve)
+        };
+        assert!(env("RATE_LIMIT_PER_SECOND", "fast").is_err());
+        assert!(env("RATE_LIMIT_BURST_SIZE", "0").is_err());
+        assert!(env("MAX_BATCH_SIZE", "-1").is_err());
+        assert!(env("MAX_BATCH_SIZE", "0").is_err());
+        assert!(env("ALLOWED_ORIGINS", "https://ok.example,bad\norigin").is_err());
+        assert!(env("TRUSTED_PROXIES", "10.0.0.0/33").is_err());
+        assert!(env("ROUTE_RATE_LIMITS", "batch=fast").is_err());
+        assert!(env("ROUTE_RATE_LIMITS", "admin=1/1").is_err());
+        assert!(env("PROOF_CACHE_SIZE", "lots").is_err());
+        assert!(env("PROOF_CACHE_CONTROL", "max-age=60\u{1}").is_err());
+        assert!(env("PROOF_CACHE_CONTROL", "").is_ok());
+        assert!(env("LOG_FORMAT", "xml").is_err());
+        assert!(env("RUST_LOG", "info,[").is_err());
+        assert!(env("RUST_LOG", "warn,merkle_api=debug").is_ok());
+
+        let file = |raw: &str| Settings::parse(raw).and_then(Config::resolve);
+        assert!(file("[listener]\naddress = \"localhost\"").is_err());
+        assert!(file("[listener]\nport = 3000").is_err());
+        assert!(file("[rate_limit]\nburst = -5").is_err());
+        assert!(file("[data]\nretain_roots = 0").is_err());
+        assert!(file("[data]\nsigner = \"0x1234\"").is_err());
+        assert!(file("[data]\ndir = \"a\"\ncampaigns_dir = \"b\"").is_err());
+        assert!(file("[listener]\ntls_cert = \"cert.pem\"").is_err());
+        assert!(
+            file("[listener]\naddress = \"127.0.0.1:3000\"\nunix_socket = \"a.sock\"").is_err()
+        );
+        assert!(file(
+            "[listener]\nunix_socket = \"a.sock\"\ntls_cert = \"c.pem\"\ntls_key = \"k.pem\""
+        )
+        .is_err());
+        assert!(file("[rate_limit]\nroutes = { admin = \"1/1\" }").is_err());
+        assert!(file("[cache]\ncache_control = \"no\\u0001\"").is_err());
+        assert!(file("[logging]\nformat = \"json\"").is_ok());
+    }
+}
@@ -0,0 +1,500 @@
+//! gRPC interface served on the same port as the REST API.
+//!
+//! The service is described in `proto/merkle/v1/merkle.proto`. `build.rs`
+//! generates the server stubs and the messages below mirror that file. gRPC
+//! calls go through the same router as HTTP requests, so they share the rate
+//! limiter, request IDs and metrics (with the gRPC method as the route), and
+//! proofs come from the same `build_proof` path.
+//!
+//! An empty `campaign` selects the default campaign and an empty `root` the
+//! current root. `ExportProofs` streams proofs against the root loaded when the
+//! call started, using the pinned top layers, and fails if the files change on
+//! disk mid-export or if the client stops reading for 30 seconds.
+
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::Router;
+use merklebuilder::merkle::{build_proof_at_index_with_layers, ProofResult, TopLayers};
+use tokio::sync::mpsc;
+use tokio::time;
+use tokio_stream::adapters::Chain;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::StreamExt;
+use tonic::server::NamedService;
+use tonic::{Request, Response, Status};
+use tracing::{warn, Instrument};
+
+use crate::campaigns::Campaign;
+use crate::limits::NotFoundCount;
+use crate::reload::CampaignStore;
+use crate::{
+    build_campaign_proof, check_batch_size, check_root, classify_index_error, metrics, select_root,
+    ApiError,
+};
+
+#[allow(clippy::all, clippy::pedantic)]
+mod generated {
+    include!(concat!(env!("OUT_DIR"), "/merkle.v1.MerkleProofs.rs"));
+}
+
+pub use generated::merkle_proofs_server::{MerkleProofs, MerkleProofsServer};
+
+/// Proofs buffered ahead of a slow `ExportProofs` reader, and built per
+/// blocking task.
+const EXPORT_BUFFER: usize = 64;
+
+/// How long `ExportProofs` waits for a client that stopped reading.
+const EXPORT_SEND_TIMEOUT: Duration = Duration::from_secs(30);
+
+type ExportSender = mpsc::Sender<Result<Proof, Status>>;
+
+const METHODS: [&str; 4] = ["GetProof", "BatchGetProof", "GetRoot", "ExportProofs"];
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GetProofRequest {
+    #[prost(string, tag = "1")]
+    pub campaign: String,
+    #[prost(string, tag = "2")]
+    pub address: String,
+    #[prost(string, tag = "3")]
+    pub root: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ProofStep {
+    #[prost(uint64, tag = "1")]
+    pub level: u64,
+    #[prost(uint64, tag = "2")]
+    pub sibling_index: u64,
+    #[prost(string, tag = "3")]
+    pub side: String,
+    #[prost(bytes = "vec", tag = "4")]
+    pub hash: Vec<u8>,
+}
+
+// Field names follow the proto file.
+#[allow(clippy::struct_field_names)]
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Proof {
+    #[prost(string, tag = "1")]
+    pub address: String,
+    #[prost(uint64, tag = "2")]
+    pub index: u64,
+    #[prost(uint64, tag = "3")]
+    pub total: u64,
+    #[prost(uint64, tag = "4")]
+    pub lookups: u64,
+    #[prost(bytes = "vec", tag = "5")]
+    pub leaf: Vec<u8>,
+    #[prost(bytes = "vec", tag = "6")]
+    pub root: Vec<u8>,
+    #[prost(uint64, tag = "7")]
+    pub root_level: u64,
+    #[prost(message, repeated, tag = "8")]
+    pub proof: Vec<ProofStep>,
+    #[prost(bool, repeated, tag = "9")]
+    pub proof_flags: Vec<bool>,
+    #[prost(string, tag = "10")]
+    pub signature: String,
+    #[prost(string, tag = "11")]
+    pub signer: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct BatchGetProofRequest {
+    #[prost(string, tag = "1")]
+    pub campaign: String,
+    #[prost(string, repeated, tag = "2")]
+    pub addresses: Vec<String>,
+    #[prost(string, tag = "3")]
+    pub root: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct BatchProofResult {
+    #[prost(string, tag = "1")]
+    pub address: String,
+    #[prost(string, tag = "2")]
+    pub status: String,
+    #[prost(message, optional, tag = "3")]
+    pub proof: Option<Proof>,
+    #[prost(string, tag = "4")]
+    pub error: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct BatchGetProofResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub results: Vec<BatchProofResult>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GetRootRequest {
+    #[prost(string, tag = "1")]
+    pub campaign: String,
+}
+
+#[allow(clippy::struct_field_names)]
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Root {
+    #[prost(string, tag = "1")]
+    pub campaign: String,
+    #[prost(bytes = "vec", tag = "2")]
+    pub root: Vec<u8>,
+    #[prost(uint64, tag = "3")]
+    pub root_level: u64,
+    #[prost(uint64, tag = "4")]
+    pub leaf_count: u64,
+    #[prost(uint64, tag = "5")]
+    pub layer_count: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ExportProofsRequest {
+    #[prost(string, tag = "1")]
+    pub campaign: String,
+    #[prost(uint64, tag = "2")]
+    pub start: u64,
+    #[prost(uint64, tag = "3")]
+    pub limit: u64,
+}
+
+impl Proof {
+    fn new(campaign: &Campaign, proof: ProofResult) -> Self {
+        let ProofResult {
+            normalized_address,
+            index,
+            total,
+            lookups,
+            leaf,
+            root,
+            root_level,
+            steps,
+        } = proof;
+        let (proof_flags, steps) = steps
+            .into_iter()
+            .map(|step| {
+                let node = ProofStep {
+                    level: step.level as u64,
+                    sibling_index: step.sibling_index as u64,
+                    side: step.side.as_str().to_string(),
+                    hash: step.sibling_hash.to_vec(),
+                };
+                (step.side.proof_flag(), node)
+            })
+            .unzip();
+        let signed = campaign.signed_root.as_ref();
+        Proof {
+            address: normalized_address,
+            index: index as u64,
+            total: total as u64,
+            lookups: lookups as u64,
+            leaf: leaf.to_vec(),
+            root: root.to_vec(),
+            root_level: root_level as u64,
+            proof: steps,
+            proof_flags,
+            signature: signed.map(|s| s.signature.clone()).unwrap_or_default(),
+            signer: signed.map(|s| s.signer.clone()).unwrap_or_default(),
+        }
+    }
+}
+
+impl From<ApiError> for Status {
+    fn from(err: ApiError) -> Self {
+        match err {
+            ApiError::BadRequest(message) => Status::invalid_argument(message),
+            ApiError::Unauthorized(message) => Status::unauthenticated(message),
+            ApiError::Forbidden(message) => Status::permission_denied(message),
+            ApiError::NotFound(message) => Status::not_found(message),
+            ApiError::TooManyRequests(message) => Status::resource_exhausted(message),
+            ApiError::Internal(message) => Status::internal(message),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MerkleService {
+    campaigns: Arc<CampaignStore>,
+    max_batch_size: usize,
+}
+
+impl MerkleService {
+    pub fn new(campaigns: Arc<CampaignStore>, max_batch_size: usize) -> Self {
+        MerkleService {
+            campaigns,
+            max_batch_size,
+        }
+    }
+
+    /// Picks a campaign version like the REST routes; also reports whether a
+    /// root was given.
+    fn select(&self, id: &str, root: &str) -> Result<(Arc<Campaign>, bool), ApiError> {
+        let registry = self.campaigns.current();
+        let id = if id.is_empty() {
+            registry.default_id()
+        } else {
+            id
+        };
+        let root = Some(root).filter(|root| !root.is_empty());
+        Ok((select_root(&registry, id, root)?, root.is_some()))
+    }
+}
+
+fn join_error(e: &tokio::task::JoinError) -> Status {
+    Status::internal(format!("spawn_blocking error: {e}"))
+}
+
+#[tonic::async_trait]
+impl MerkleProofs for MerkleService {
+    type ExportProofsStream =
+        Chain<ReceiverStream<Result<Proof, Status>>, ReceiverStream<Result<Proof, Status>>>;
+
+    async fn get_proof(
+        &self,
+        request: Request<GetProofRequest>,
+    ) -> Result<Response<Proof>, Status> {
+        let GetProofRequest {
+            campaign,
+            address,
+            root,
+        } = request.into_inner();
+        let (campaign, explicit_root) = self.select(&campaign, &root)?;
+        let proofs = Arc::clone(self.campaigns.proofs());
+        let proof = metrics::spawn_blocking(move || {
+            build_campaign_proof(&proofs, &campaign, &address, explicit_root)
+                .map(|proof| Proof::new(&campaign, proof))
+        })
+        .await
+        .map_err(|e| join_error(&e))??;
+        Ok(Response::new(proof))
+    }
+
+    async fn batch_get_proof(
+        &self,
+        request: Request<BatchGetProofRequest>,
+    ) -> Result<Response<BatchGetProofResponse>, Status> {
+        let BatchGetProofRequest {
+            campaign,
+            addresses,
+            root,
+        } = request.into_inner();
+        let (campaign, explicit_root) = self.select(&campaign, &root)?;
+        check_batch_size(addresses.len(), self.max_batch_size)?;
+        let proofs = Arc::clone(self.campaigns.proofs());
+        let results: Vec<_> = metrics::spawn_blocking(move || {
+            addresses
+                .into_iter()
+                .map(|address| {
+                    match build_campaign_proof(&proofs, &campaign, &address, explicit_root) {
+                        Ok(proof) => BatchProofResult {
+                            address,
+                            status: "ok".to_string(),
+                            proof: Some(Proof::new(&campaign, proof)),
+                            error: String::new(),
+                        },
+                        Err(e) => BatchProofResult {
+                            address,
+                            status: e.batch_status().to_string(),
+                            proof: None,
+                            error: e.to_string(),
+                        },
+                    }
+                })
+                .collect()
+        })
+        .await
+        .map_err(|e| join_error(&e))?;
+        let not_found = results
+            .iter()
+            .filter(|result| result.status == "not_found")
+            .count();
+        let mut response = Response::new(BatchGetProofResponse { results });
+        response.extensions_mut().insert(NotFoundCount(not_found));
+        Ok(response)
+    }
+
+    async fn get_root(&self, request: Request<GetRootRequest>) -> Result<Response<Root>, Status> {
+        let (campaign, _) = self.select(&request.into_inner().campaign, "")?;
+        Ok(Response::new(Root {
+            campaign: campaign.id.clone(),
+            root: campaign.root.to_vec(),
+            root_level: campaign.layer_count.saturating_sub(1) as u64,
+            leaf_count: campaign.leaf_count as u64,
+            layer_count: campaign.layer_count as u64,
+        }))
+    }
+
+    async fn export_proofs(
+        &self,
+        request: Request<ExportProofsRequest>,
+    ) -> Result<Response<Self::ExportProofsStream>, Status> {
+        let ExportProofsRequest {
+            campaign,
+            start,
+            limit,
+        } = request.into_inner();
+        let (campaign, _) = self.select(&campaign, "")?;
+        let leaf_count = campaign.leaf_count;
+        let start = usize::try_from(start).unwrap_or(usize::MAX).min(leaf_count);
+        let end = match usize::try_from(limit) {
+            Ok(0) | Err(_) => leaf_count,
+            Ok(limit) => start.saturating_add(limit).min(leaf_count),
+        };
+
+        let top = self
+            .campaigns
+            .proofs()
+            .layers(&campaign)
+            .unwrap_or_default();
+        let (sender, receiver) = mpsc::channel(EXPORT_BUFFER);
+        let (abort, aborted) = mpsc::channel(1);
+        let export = export(
+            campaign,
+            top,
+            start..end,
+            (sender, abort),
+            EXPORT_SEND_TIMEOUT,
+        );
+        tokio::spawn(export.in_current_span());
+        Ok(Response::new(
+            ReceiverStream::new(receiver).chain(ReceiverStream::new(aborted)),
+        ))
+    }
+}
+
+/// Streams the proofs of `leaves` into `sender`, building them on the blocking
+/// pool `EXPORT_BUFFER` at a time so no thread waits on the client.
+///
+/// Stops when the client goes away, and fails the stream through `abort`
+/// when it stops reading for `send_timeout`.
+async fn export(
+    campaign: Arc<Campaign>,
+    top: Arc<TopLayers>,
+    leaves: Range<usize>,
+    (sender, abort): (ExportSender, ExportSender),
+    send_timeout: Duration,
+) {
+    let mut next = leaves.start;
+    while next < leaves.end && !sender.is_closed() {
+        let chunk = next..next.saturating_add(EXPORT_BUFFER).min(leaves.end);
+        next = chunk.end;
+        let (campaign, top) = (Arc::clone(&campaign), Arc::clone(&top));
+        let proofs = metrics::spawn_blocking(move || {
+            let mut proofs = Vec::with_capacity(chunk.len());
+            for index in chunk {
+                let proof = metrics::time_proof(|| {
+                    build_proof_at_index_with_layers(&campaign.db_dir, index, &top)
+                })
+                .map_err(|e| classify_index_error(&e))
+                .and_then(|proof| {
+                    check_root(&campaign, &proof)?;
+                    Ok(Proof::new(&campaign, proof))
+                })
+                .map_err(Status::from);
+                let failed = proof.is_err();
+                proofs.push(proof);
+                if failed {
+                    break;
+                }
+            }
+            proofs
+        })
+        .await
+        .unwrap_or_else(|e| vec![Err(join_error(&e))]);
+
+        for proof in proofs {
+            let failed = proof.is_err();
+            match time::timeout(send_timeout, sender.send(proof)).await {
+                Ok(Ok(())) if !failed => {}
+                Ok(_) => return,
+                Err(_) => {
+                    warn!(
+                        "ExportProofs client stopped reading for {send_timeout:?}, ending the export"
+                    );
+                    let _ = abort
+                        .send(Err(Status::deadline_exceeded(
+                            "the client stopped reading the export",
+                        )))
+                        .await;
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Mounts the service at one route per gRPC method, so metrics see each
+/// method as its own route.
+pub fn routes<S>(service: MerkleService) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let server = MerkleProofsServer::new(service);
+    METHODS.iter().fold(Router::new(), |router, method| {
+        router.route_service(
+            &format!("/{}/{method}", MerkleProofsServer::<MerkleService>::NAME),
+            server.clone(),
+        )
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn test_campaign() -> (TempDir, Arc<Campaign>) {
+        let temp = TempDir::new().unwrap();
+        crate::tests::write_test_db(temp.path());
+        let campaign = Campaign::load("default", temp.path(), None).unwrap();
+        (temp, Arc::new(campaign))
+    }
+
+    #[tokio::test]
+    async fn test_export_uses_pinned_layers() {
+        let (temp, campaign) = test_campaign();
+        let top = Arc::new(TopLayers::load(&campaign.db_dir, 2).unwrap());
+        std::fs::remove_file(temp.path().join("layer01.bin")).unwrap();
+
+        let (sender, mut receiver) = mpsc::channel(EXPORT_BUFFER);
+        let (abort, _aborted) = mpsc::channel(1);
+        export(campaign, top, 0..3, (sender, abort), EXPORT_SEND_TIMEOUT).await;
+        let mut indexes = Vec::new();
+        while let Some(proof) = receiver.recv().await {
+            indexes.push(proof.unwrap().index);
+        }
+        assert_eq!(indexes, [0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_export_stops_for_gone_or_stalled_clients() {
+        let (_temp, campaign) = test_campaign();
+        let top = Arc::new(TopLayers::default());
+
+        let (sender, receiver) = mpsc::channel(1);
+        let (abort, mut aborted) = mpsc::channel(1);
+        drop(receiver);
+        export(
+            Arc::clone(&campaign),
+            Arc::clone(&top),
+            0..3,
+            (sender, abort),
+            EXPORT_SEND_TIMEOUT,
+        )
+        .await;
+        assert!(aborted.recv().await.is_none());
+
+        let (sender, mut receiver) = mpsc::channel(1);
+        let (abort, mut aborted) = mpsc::channel(1);
+        let send_timeout = Duration::from_millis(50);
+        export(campaign, top, 0..3, (sender, abort), send_timeout).await;
+        assert_eq!(receiver.recv().await.unwrap().unwrap().index, 0);
+        assert!(receiver.recv().await.is_none());
+        let status = aborted.recv().await.unwrap().unwrap_err();
+        assert_eq!(status.code(), tonic::Code::DeadlineExceeded);
+    }
+}
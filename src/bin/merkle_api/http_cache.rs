@@ -0,0 +1,116 @@
+//! HTTP caching of proof responses.
+//!
+//! A proof never changes while its root does not, so `/proof/{address}` and
+//! `/campaigns/{id}/proof/{address}` send an `ETag` derived from the root, the
+//! address and the root signature, and answer `If-None-Match` with
+//! `304 Not Modified` without building the proof. The `ETag` can be computed
+//! by anyone, so the address must first be found in the proof cache or in
+//! `addresses.bin`; other addresses get their usual 404. `Cache-Control` lets
+//! CDNs and browsers keep proofs for a while:
+//!
+//! - `PROOF_CACHE_CONTROL` applies to proofs against the current root
+//!   (default `public, max-age=60`). Keep it short: the root can rotate.
+//! - `ROOT_PROOF_CACHE_CONTROL` applies to proofs requested with `?root=`
+//!   (default `public, max-age=86400, immutable`), which cannot change.
+//!
+//! Both can also be set in the `[cache]` section of the config file (see
+//! [`crate::config`]). Set either to an empty string to omit the header.
+//! Error responses carry neither header.
+
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use merklebuilder::merkle::parse_address;
+use sha3::{Digest, Keccak256};
+
+use crate::campaigns::Campaign;
+
+pub const DEFAULT_CACHE_CONTROL: &str = "public, max-age=60";
+pub const DEFAULT_ROOT_CACHE_CONTROL: &str = "public, max-age=86400, immutable";
+
+/// `Cache-Control` values for proofs against the current root and an explicit
+/// `?root=`.
+#[derive(Debug, Clone)]
+pub struct CachePolicy {
+    current: Option<HeaderValue>,
+    explicit_root: Option<HeaderValue>,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        CachePolicy {
+            current: Some(HeaderValue::from_static(DEFAULT_CACHE_CONTROL)),
+            explicit_root: Some(HeaderValue::from_static(DEFAULT_ROOT_CACHE_CONTROL)),
+        }
+    }
+}
+
+impl CachePolicy {
+    /// `None` omits the header.
+    pub fn new(current: Option<HeaderValue>, explicit_root: Option<HeaderValue>) -> Self {
+        CachePolicy {
+            current,
+            explicit_root,
+        }
+    }
+
+    fn cache_control(&self, explicit_root: bool) -> Option<&HeaderValue> {
+        if explicit_root {
+            self.explicit_root.as_ref()
+        } else {
+            self.current.as_ref()
+        }
+    }
+
+    /// `304 Not Modified` when the request's `If-None-Match` lists `etag`.
+    /// Only send it once the address is known to be in the tree.
+    pub fn not_modified(
+        &self,
+        request: &HeaderMap,
+        etag: &HeaderValue,
+        explicit_root: bool,
+    ) -> Option<Response> {
+        let matched = request
+            .get_all(header::IF_NONE_MATCH)
+            .iter()
+            .any(|value| etag_matches(value, etag));
+        matched.then(|| {
+            let mut response = StatusCode::NOT_MODIFIED.into_response();
+            self.apply(response.headers_mut(), etag.clone(), explicit_root);
+            response
+        })
+    }
+
+    /// Adds `ETag` and `Cache-Control` to a proof response.
+    pub fn apply(&self, headers: &mut HeaderMap, etag: HeaderValue, explicit_root: bool) {
+        headers.insert(header::ETAG, etag);
+        if let Some(value) = self.cache_control(explicit_root) {
+            headers.insert(header::CACHE_CONTROL, value.clone());
+        }
+    }
+}
+
+/// Strong `ETag` of the proof of `address` in `campaign`, or `None` when the
+/// address is malformed.
+pub fn proof_etag(campaign: &Campaign, address: &str) -> Option<HeaderValue> {
+    let address = parse_address(address).ok()?;
+    let mut hasher = Keccak256::new();
+    hasher.update(campaign.root);
+    hasher.update(address);
+    if let Some(signed) = &campaign.signed_root {
+        hasher.update(signed.signature.as_bytes());
+    }
+    let digest = hasher.finalize();
+    HeaderValue::from_str(&format!("\"{}\"", hex::encode(&digest[..16]))).ok()
+}
+
+/// Weak comparison, as `If-None-Match` requires.
+fn etag_matches(if_none_match: &HeaderValue, etag: &HeaderValue) -> bool {
+    let Ok(candidates) = if_none_match.to_str() else {
+        return false;
+    };
+    let etag = etag.as_bytes();
+    candidates
+        .split(',')
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/").as_bytes() == etag)
+}
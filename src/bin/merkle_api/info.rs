@@ -0,0 +1,154 @@
+//! Database metadata served by `/root`, `/info` and `/stats`.
+//!
+//! `/root` answers from the loaded campaign without touching the disk, so UIs
+//! and monitors can poll it to compare against the contract's `MERKLE_ROOT`.
+//! `/info` adds the build details recorded in `manifest.json`, and `/stats`
+//! reports the current size of every database file and the proof cache
+//! counters.
+
+use std::fs;
+use std::time::UNIX_EPOCH;
+
+use merklebuilder::manifest::{ToolInfo, TreeParameters, MANIFEST_FILE};
+use merklebuilder::merkle::to_hex32;
+use merklebuilder::signing::SIGNATURE_FILE;
+use merklebuilder::{ADDRESS_SIZE, HASH_SIZE};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::campaigns::Campaign;
+use crate::openapi::{ToolInfoSchema, TreeParametersSchema};
+use crate::proof_cache::ProofCacheStats;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RootResponse {
+    campaign: String,
+    root: String,
+    root_level: usize,
+    leaf_count: usize,
+    layer_count: usize,
+}
+
+impl From<&Campaign> for RootResponse {
+    fn from(campaign: &Campaign) -> Self {
+        RootResponse {
+            campaign: campaign.id.clone(),
+            root: to_hex32(&campaign.root),
+            root_level: campaign.layer_count.saturating_sub(1),
+            leaf_count: campaign.leaf_count,
+            layer_count: campaign.layer_count,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct InfoResponse {
+    #[serde(flatten)]
+    root: RootResponse,
+    /// Unix timestamp (seconds) at which the server loaded this root.
+    loaded_at: u64,
+    /// Unix timestamp (seconds) of the build: `created_at` from the manifest,
+    /// else the modification time of `addresses.bin`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    built_at: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<ToolInfoSchema>)]
+    tool: Option<ToolInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<TreeParametersSchema>)]
+    parameters: Option<TreeParameters>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    manifest_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signer: Option<String>,
+}
+
+impl InfoResponse {
+    /// Describes a campaign. Performs blocking I/O when it has no manifest.
+    pub fn new(campaign: &Campaign) -> Self {
+        let manifest = campaign.manifest.as_ref();
+        InfoResponse {
+            root: RootResponse::from(campaign),
+            loaded_at: campaign.loaded_at,
+            built_at: match manifest {
+                Some((manifest, _)) => Some(manifest.created_at),
+                None => modified_at(campaign, "addresses.bin"),
+            },
+            tool: manifest.map(|(manifest, _)| manifest.tool.clone()),
+            parameters: manifest.map(|(manifest, _)| manifest.parameters.clone()),
+            manifest_hash: manifest.map(|(_, hash)| hash.clone()),
+            signer: campaign
+                .signed_root
+                .as_ref()
+                .map(|signed| signed.signer.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FileStats {
+    name: String,
+    size: u64,
+    /// Addresses in `addresses.bin` or nodes in a layer file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entries: Option<u64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StatsResponse {
+    campaign: String,
+    root: String,
+    files: Vec<FileStats>,
+    total_size: u64,
+    proof_cache: ProofCacheStats,
+}
+
+impl StatsResponse {
+    /// Reads the size of every database file. Performs blocking I/O.
+    pub fn new(campaign: &Campaign, proof_cache: ProofCacheStats) -> Result<Self, String> {
+        let mut files = vec![file_stats(campaign, "addresses.bin", Some(ADDRESS_SIZE))?];
+        for level in 0..campaign.layer_count {
+            files.push(file_stats(
+                campaign,
+                &format!("layer{level:02}.bin"),
+                Some(HASH_SIZE),
+            )?);
+        }
+        for name in [MANIFEST_FILE, SIGNATURE_FILE] {
+            if campaign.db_dir.join(name).exists() {
+                files.push(file_stats(campaign, name, None)?);
+            }
+        }
+        Ok(StatsResponse {
+            campaign: campaign.id.clone(),
+            root: to_hex32(&campaign.root),
+            total_size: files.iter().map(|file| file.size).sum(),
+            files,
+            proof_cache,
+        })
+    }
+}
+
+fn file_stats(
+    campaign: &Campaign,
+    name: &str,
+    entry_size: Option<usize>,
+) -> Result<FileStats, String> {
+    let path = campaign.db_dir.join(name);
+    let size = fs::metadata(&path)
+        .map_err(|e| format!("Failed to stat {}: {e}", path.display()))?
+        .len();
+    Ok(FileStats {
+        name: name.to_string(),
+        size,
+        entries: entry_size.map(|entry_size| size / entry_size as u64),
+    })
+}
+
+fn modified_at(campaign: &Campaign, name: &str) -> Option<u64> {
+    fs::metadata(campaign.db_dir.join(name))
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|elapsed| elapsed.as_secs())
+}
@@ -0,0 +1,337 @@
+//! Client addresses behind proxies, per-route limits and the unlimited
+//! allowlist.
+//!
+//! Requests from a proxy listed in `TRUSTED_PROXIES` are attributed to the
+//! client named in its `Forwarded` (or, without it, `X-Forwarded-For`) header:
+//! the header is read from the right, skipping trusted proxies, and the first
+//! other address is the client. The headers of other peers are ignored, so
+//! clients cannot pick their own rate-limit bucket.
+//!
+//! Anonymous callers are also subject to `ROUTE_RATE_LIMITS`, one bucket per
+//! route group, and to `NOT_FOUND_RATE_LIMIT`: a client that runs out of 404
+//! responses gets HTTP 429 on every request until the bucket refills, which
+//! makes probing the address list for members slow. Every `not_found` entry
+//! of a batch response spends a token as well. Addresses matching
+//! `RATE_LIMIT_ALLOWLIST` skip every rate limit, keyed or not.
+//!
+//! Every keyed limiter is pruned once a minute, dropping the clients whose
+//! buckets have refilled, so memory does not grow with every address seen.
+//!
+//! Networks are comma-separated CIDRs or single addresses. Limits are written
+//! `<requests per second>/<burst>`, for example
+//! `ROUTE_RATE_LIMITS=batch=1/5,export=1/2` and `NOT_FOUND_RATE_LIMIT=1/10`.
+//! The `[rate_limit]` section of the config file takes the same settings
+//! (see [`crate::config`]).
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use axum::http::{header, HeaderMap};
+use governor::clock::{Clock, DefaultClock};
+use governor::{DefaultKeyedRateLimiter, Quota, RateLimiter};
+use ipnet::IpNet;
+
+use crate::auth::{self, RouteGroup};
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// Number of `not_found` entries in a batch response, set by the batch
+/// handlers so that each one spends a 404 token like a single lookup would.
+#[derive(Clone, Copy, Debug)]
+pub struct NotFoundCount(pub usize);
+
+/// Proxy trust, allowlist and the limits applied to anonymous callers.
+#[derive(Default)]
+pub struct Limits {
+    trusted_proxies: Vec<IpNet>,
+    unlimited: Vec<IpNet>,
+    routes: HashMap<RouteGroup, DefaultKeyedRateLimiter<String>>,
+    not_found: Option<NotFoundLimit>,
+}
+
+struct NotFoundLimit {
+    limiter: DefaultKeyedRateLimiter<String>,
+    blocked: Mutex<HashMap<String, Instant>>,
+}
+
+impl Limits {
+    pub fn with_trusted_proxies(mut self, networks: Vec<IpNet>) -> Self {
+        self.trusted_proxies = networks;
+        self
+    }
+
+    pub fn with_unlimited(mut self, networks: Vec<IpNet>) -> Self {
+        self.unlimited = networks;
+        self
+    }
+
+    pub fn with_route_limit(mut self, group: RouteGroup, quota: Quota) -> Self {
+        self.routes.insert(group, RateLimiter::keyed(quota));
+        self
+    }
+
+    pub fn with_not_found_limit(mut self, quota: Quota) -> Self {
+        self.not_found = Some(NotFoundLimit {
+            limiter: RateLimiter::keyed(quota),
+            blocked: Mutex::new(HashMap::new()),
+        });
+        self
+    }
+
+    pub fn trusted_proxy_count(&self) -> usize {
+        self.trusted_proxies.len()
+    }
+
+    /// Address of the client behind any trusted proxies.
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !self.is_trusted(peer) {
+            return peer;
+        }
+        let forwarded = forwarded_hops(headers);
+        let hops = if forwarded.is_empty() {
+            header_list(headers, X_FORWARDED_FOR)
+        } else {
+            forwarded
+        };
+        let mut client = peer;
+        for hop in hops.iter().rev() {
+            let Some(ip) = parse_hop(hop) else {
+                break;
+            };
+            client = ip;
+            if !self.is_trusted(ip) {
+                break;
+            }
+        }
+        client
+    }
+
+    fn is_trusted(&self, ip: IpAddr) -> bool {
+        contains(&self.trusted_proxies, ip)
+    }
+
+    pub fn is_unlimited(&self, ip: IpAddr) -> bool {
+        contains(&self.unlimited, ip)
+    }
+
+    /// Checks the 404 block and the route limit of an anonymous client,
+    /// returning how long to wait when either is exhausted.
+    pub fn check_anonymous(&self, group: Option<RouteGroup>, client: &str) -> Result<(), Duration> {
+        if let Some(not_found) = &self.not_found {
+            let mut blocked = not_found
+                .blocked
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            match blocked.get(client) {
+                Some(until) if *until > Instant::now() => {
+                    return Err(until.saturating_duration_since(Instant::now()));
+                }
+                Some(_) => {
+                    blocked.remove(client);
+                }
+                None => {}
+            }
+        }
+        let Some(limiter) = group.and_then(|group| self.routes.get(&group)) else {
+            return Ok(());
+        };
+        limiter
+            .check_key(&client.to_string())
+            .map_err(|not_until| not_until.wait_time_from(DefaultClock::default().now()))
+    }
+
+    /// Forgets the clients whose buckets are full again, so the limiters do
+    /// not grow with every address ever seen.
+    pub fn prune(&self) {
+        for limiter in self.routes.values() {
+            prune(limiter);
+        }
+        if let Some(not_found) = &self.not_found {
+            prune(&not_found.limiter);
+            let now = Instant::now();
+            let mut blocked = not_found
+                .blocked
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            blocked.retain(|_, until| *until > now);
+            blocked.shrink_to_fit();
+        }
+    }
+
+    /// Spends `count` 404 tokens of an anonymous client, blocking it once the
+    /// bucket is empty.
+    pub fn record_not_found(&self, client: &str, count: usize) {
+        let Some(not_found) = &self.not_found else {
+            return;
+        };
+        let key = client.to_string();
+        for _ in 0..count {
+            if let Err(not_until) = not_found.limiter.check_key(&key) {
+                let now = Instant::now();
+                let until = now + not_until.wait_time_from(DefaultClock::default().now());
+                let mut blocked = not_found
+                    .blocked
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner);
+                blocked.retain(|_, until| *until > now);
+                blocked.insert(key, until);
+                return;
+            }
+        }
+    }
+}
+
+/// Drops the keys of a limiter whose buckets are full.
+pub fn prune(limiter: &DefaultKeyedRateLimiter<String>) {
+    limiter.retain_recent();
+    limiter.shrink_to_fit();
+}
+
+fn contains(networks: &[IpNet], ip: IpAddr) -> bool {
+    let ip = ip.to_canonical();
+    networks.iter().any(|network| network.contains(&ip))
+}
+
+/// Parses comma-separated CIDRs, treating a bare address as a single host.
+pub fn parse_networks(raw: &str) -> Result<Vec<IpNet>, String> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            entry
+                .parse::<IpNet>()
+                .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from))
+                .map_err(|_| format!("'{entry}' is not an address or CIDR"))
+        })
+        .collect()
+}
+
+/// Parses `<requests per second>/<burst>`.
+pub fn parse_rate(raw: &str) -> Result<Quota, String> {
+    let (rate, burst) = raw
+        .trim()
+        .split_once('/')
+        .ok_or_else(|| format!("'{raw}' is not <requests per second>/<burst>"))?;
+    let number = |value: &str| {
+        value
+            .trim()
+            .parse::<u32>()
+            .map_err(|_| format!("'{raw}' is not <requests per second>/<burst>"))
+    };
+    auth::quota(number(rate)?, number(burst)?)
+        .ok_or_else(|| format!("'{raw}' needs a non-zero rate and burst"))
+}
+
+fn header_list(headers: &HeaderMap, name: &str) -> Vec<String> {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|hop| hop.trim().to_string())
+        .collect()
+}
+
+/// `for=` values of the `Forwarded` header, in order.
+fn forwarded_hops(headers: &HeaderMap) -> Vec<String> {
+    header_list(headers, header::FORWARDED.as_str())
+        .iter()
+        .map(|element| {
+            element
+                .split(';')
+                .find_map(|pair| {
+                    let (name, value) = pair.split_once('=')?;
+                    name.trim()
+                        .eq_ignore_ascii_case("for")
+                        .then(|| value.trim().trim_matches('"').to_string())
+                })
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Parses `192.0.2.1`, `192.0.2.1:443`, `2001:db8::1` or `[2001:db8::1]:443`.
+/// Obfuscated identifiers and `unknown` yield `None`.
+fn parse_hop(hop: &str) -> Option<IpAddr> {
+    hop.parse::<IpAddr>()
+        .ok()
+        .or_else(|| hop.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+        .or_else(|| {
+            hop.strip_prefix('[')
+                .and_then(|hop| hop.strip_suffix(']'))
+                .and_then(|hop| hop.parse().ok())
+        })
+        .map(|ip: IpAddr| ip.to_canonical())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_ip_behind_proxies() {
+        let limits = Limits::default().with_trusted_proxies(parse_networks("10.0.0.0/8").unwrap());
+        let headers = |name: &str, value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                axum::http::HeaderName::from_bytes(name.as_bytes()).unwrap(),
+                value.parse().unwrap(),
+            );
+            headers
+        };
+        let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+        let outsider: IpAddr = "203.0.113.9".parse().unwrap();
+
+        let spoofed = headers("x-forwarded-for", "198.51.100.1");
+        assert_eq!(limits.client_ip(outsider, &spoofed), outsider);
+        assert_eq!(
+            limits.client_ip(proxy, &spoofed).to_string(),
+            "198.51.100.1"
+        );
+        let chain = headers("x-forwarded-for", "1.2.3.4, 198.51.100.1, 10.2.3.4");
+        assert_eq!(limits.client_ip(proxy, &chain).to_string(), "198.51.100.1");
+        let forwarded = headers(
+            "forwarded",
+            r#"for="[2001:db8::1]:4711";proto=https, for=10.0.0.2"#,
+        );
+        assert_eq!(
+            limits.client_ip(proxy, &forwarded).to_string(),
+            "2001:db8::1"
+        );
+        let obfuscated = headers("forwarded", "for=_hidden, for=10.0.0.2");
+        assert_eq!(limits.client_ip(proxy, &obfuscated).to_string(), "10.0.0.2");
+
+        assert!(parse_networks("10.0.0.0/8, ::1").is_ok());
+        assert!(parse_networks("10.0.0.0/33").is_err());
+        assert!(parse_rate("1/10").is_ok());
+        assert!(parse_rate("0/10").is_err());
+        assert!(parse_rate("5").is_err());
+    }
+
+    #[test]
+    fn test_prune_forgets_refilled_buckets() {
+        let quota = auth::quota(1000, 1).unwrap();
+        let limits = Limits::default()
+            .with_route_limit(RouteGroup::Batch, quota)
+            .with_not_found_limit(quota);
+        for client in ["192.0.2.1", "192.0.2.2"] {
+            limits
+                .check_anonymous(Some(RouteGroup::Batch), client)
+                .unwrap();
+            limits.record_not_found(client, 2);
+        }
+        let not_found = limits.not_found.as_ref().unwrap();
+        assert_eq!(limits.routes[&RouteGroup::Batch].len(), 2);
+        assert_eq!(not_found.limiter.len(), 2);
+        assert_eq!(not_found.blocked.lock().unwrap().len(), 2);
+
+        std::thread::sleep(Duration::from_millis(10));
+        limits.prune();
+        assert!(limits.routes[&RouteGroup::Batch].is_empty());
+        assert!(not_found.limiter.is_empty());
+        assert!(not_found.blocked.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,340 @@
+//! Listening sockets: TCP, Unix domain sockets and systemd socket activation.
+//!
+//! `--unix-socket <path>` serves on a Unix domain socket instead of a TCP
+//! port, for a proxy on the same host. A socket file left behind by a
+//! previous run is replaced unless a server still answers on it, and the
+//! file is removed on shutdown. The file gets the permissions allowed by the
+//! umask. There is no client address on a Unix socket, so its connections
+//! count as coming from `127.0.0.1`: add that address to `TRUSTED_PROXIES`
+//! to rate-limit by the proxy's `X-Forwarded-For` instead.
+//!
+//! Under systemd socket activation (`LISTEN_FDS`), the one socket passed by
+//! systemd, TCP or Unix, is used and `--listen`/`--unix-socket` are ignored.
+//! With `Type=notify`, `READY=1` is sent once every campaign has been found
+//! on disk and validated and the socket is accepting connections, and
+//! `STOPPING=1` when a graceful shutdown starts.
+
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+use std::path::PathBuf;
+
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use axum_server::Handle;
+use tokio::signal;
+use tracing::{info, warn};
+
+/// Address to bind when systemd passes no socket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListenAddr {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+pub enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix {
+        listener: std::os::unix::net::UnixListener,
+        /// Socket file to remove on shutdown; `None` when systemd owns it.
+        path: Option<PathBuf>,
+    },
+}
+
+impl Listener {
+    /// Takes the socket passed by systemd, if any, or binds `address`.
+    pub fn open(address: &ListenAddr) -> Result<Self, String> {
+        if let Some(listener) = from_systemd()? {
+            info!("Using the socket passed by systemd");
+            return Ok(listener);
+        }
+        match address {
+            ListenAddr::Tcp(address) => TcpListener::bind(address)
+                .map(Listener::Tcp)
+                .map_err(|e| format!("Failed to bind {address}: {e}")),
+            #[cfg(unix)]
+            ListenAddr::Unix(path) => bind_unix(path),
+            #[cfg(not(unix))]
+            ListenAddr::Unix(_) => {
+                Err("Unix sockets are not supported on this platform".to_string())
+            }
+        }
+    }
+
+    /// `http://…` or `https://…` for TCP, `unix:…` for a Unix socket.
+    pub fn url(&self, tls: bool) -> String {
+        match self {
+            Listener::Tcp(listener) => {
+                let scheme = if tls { "https" } else { "http" };
+                match listener.local_addr() {
+                    Ok(address) => format!("{scheme}://{address}"),
+                    Err(_) => format!("{scheme}://<unknown>"),
+                }
+            }
+            #[cfg(unix)]
+            Listener::Unix { listener, .. } => {
+                match listener.local_addr().ok().and_then(|address| {
+                    address.as_pathname().map(|path| path.display().to_string())
+                }) {
+                    Some(path) => format!("unix:{path}"),
+                    None => "unix:<unnamed>".to_string(),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn from_systemd() -> Result<Option<Listener>, String> {
+    let mut fds = listenfd::ListenFd::from_env();
+    match fds.len() {
+        0 => return Ok(None),
+        1 => {}
+        count => return Err(format!("Expected one socket from systemd, got {count}")),
+    }
+    if let Ok(Some(listener)) = fds.take_tcp_listener(0) {
+        return Ok(Some(Listener::Tcp(listener)));
+    }
+    match fds.take_unix_listener(0) {
+        Ok(listener) => Ok(listener.map(|listener| Listener::Unix {
+            listener,
+            path: None,
+        })),
+        Err(e) => Err(format!(
+            "The socket passed by systemd is not a TCP or Unix stream socket: {e}"
+        )),
+    }
+}
+
+#[cfg(not(unix))]
+fn from_systemd() -> Result<Option<Listener>, String> {
+    Ok(None)
+}
+
+#[cfg(unix)]
+fn bind_unix(path: &std::path::Path) -> Result<Listener, String> {
+    use std::fs;
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    let stale = fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket());
+    if stale {
+        if UnixStream::connect(path).is_ok() {
+            return Err(format!("{} is in use by another server", path.display()));
+        }
+        fs::remove_file(path)
+            .map_err(|e| format!("Failed to remove the stale socket {}: {e}", path.display()))?;
+    }
+    let listener =
+        UnixListener::bind(path).map_err(|e| format!("Failed to bind {}: {e}", path.display()))?;
+    Ok(Listener::Unix {
+        listener,
+        path: Some(path.to_path_buf()),
+    })
+}
+
+/// Serves the router until Ctrl-C or `SIGTERM`, over TLS when configured,
+/// and tells systemd the server is ready once the socket is accepting.
+pub async fn serve(listener: Listener, app: Router, tls: Option<RustlsConfig>) -> io::Result<()> {
+    match listener {
+        Listener::Tcp(listener) => {
+            let handle = Handle::new();
+            let shutdown = handle.clone();
+            tokio::spawn(async move {
+                shutdown_signal().await;
+                shutdown.graceful_shutdown(None);
+            });
+
+            notify(Status::Ready);
+            let service = app.into_make_service_with_connect_info::<SocketAddr>();
+            match tls {
+                Some(tls) => {
+                    axum_server::from_tcp_rustls(listener, tls)
+                        .handle(handle)
+                        .serve(service)
+                        .await
+                }
+                None => {
+                    axum_server::from_tcp(listener)
+                        .handle(handle)
+                        .serve(service)
+                        .await
+                }
+            }
+        }
+        #[cfg(unix)]
+        Listener::Unix { listener, path } => {
+            if tls.is_some() {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "TLS is not supported on Unix sockets",
+                ));
+            }
+            notify(Status::Ready);
+            let served = unix::serve(listener, app, shutdown_signal()).await;
+            if let Some(path) = path {
+                if let Err(e) = std::fs::remove_file(&path) {
+                    warn!("Failed to remove {}: {e}", path.display());
+                }
+            }
+            served
+        }
+    }
+}
+
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    let terminate = async {
+        match signal::unix::signal(signal::unix::SignalKind::terminate()) {
+            Ok(mut stream) => {
+                stream.recv().await;
+            }
+            Err(e) => {
+                warn!("Cannot listen for SIGTERM: {e}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = signal::ctrl_c() => {}
+        () = terminate => {}
+    }
+    info!("Shutting down gracefully...");
+    notify(Status::Stopping);
+}
+
+#[derive(Clone, Copy)]
+enum Status {
+    Ready,
+    Stopping,
+}
+
+/// Reports the service state to systemd; does nothing unless systemd set
+/// `NOTIFY_SOCKET`.
+#[cfg(unix)]
+fn notify(status: Status) {
+    let state = match status {
+        Status::Ready => sd_notify::NotifyState::Ready,
+        Status::Stopping => sd_notify::NotifyState::Stopping,
+    };
+    if let Err(e) = sd_notify::notify(false, &[state]) {
+        warn!("Failed to notify systemd: {e}");
+    }
+}
+
+#[cfg(not(unix))]
+fn notify(_status: Status) {}
+
+#[cfg(unix)]
+pub mod unix {
+    use std::future::Future;
+    use std::io;
+    use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+    use std::os::unix::net::UnixListener;
+    use std::pin::pin;
+
+    use axum::extract::ConnectInfo;
+    use axum::http::Request;
+    use axum::Router;
+    use hyper::body::Incoming;
+    use hyper::service::service_fn;
+    use hyper_util::rt::{TokioExecutor, TokioIo};
+    use hyper_util::server::conn::auto::Builder;
+    use hyper_util::server::graceful::GracefulShutdown;
+    use tower::Service;
+    use tracing::{debug, warn};
+
+    /// Peer address given to requests on a Unix socket.
+    pub const PEER: SocketAddr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0));
+
+    /// Serves HTTP/1.1 and HTTP/2 (for gRPC) on a Unix socket until
+    /// `shutdown` completes, then waits for open connections to finish.
+    pub async fn serve(
+        listener: UnixListener,
+        app: Router,
+        shutdown: impl Future<Output = ()>,
+    ) -> io::Result<()> {
+        listener.set_nonblocking(true)?;
+        let listener = tokio::net::UnixListener::from_std(listener)?;
+        let builder = Builder::new(TokioExecutor::new());
+        let graceful = GracefulShutdown::new();
+        let mut shutdown = pin!(shutdown);
+        loop {
+            let stream = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        warn!("Failed to accept a connection: {e}");
+                        continue;
+                    }
+                },
+                () = &mut shutdown => break,
+            };
+            let app = app.clone();
+            let service = service_fn(move |mut request: Request<Incoming>| {
+                request.extensions_mut().insert(ConnectInfo(PEER));
+                app.clone().call(request)
+            });
+            let connection = builder
+                .serve_connection_with_upgrades(TokioIo::new(stream), service)
+                .into_owned();
+            let connection = graceful.watch(connection);
+            tokio::spawn(async move {
+                if let Err(e) = connection.await {
+                    debug!("Connection closed with an error: {e}");
+                }
+            });
+        }
+        graceful.shutdown().await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_unix_socket_listener() {
+        use axum::extract::ConnectInfo;
+        use axum::routing::get;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("api.sock");
+        // A socket file left behind by a previous run is replaced.
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        let address = ListenAddr::Unix(path.clone());
+        let Listener::Unix { listener, .. } = Listener::open(&address).unwrap() else {
+            panic!("expected a Unix socket");
+        };
+        assert!(Listener::open(&address).err().unwrap().contains("in use"));
+
+        let app = Router::new().route(
+            "/peer",
+            get(|ConnectInfo(peer): ConnectInfo<SocketAddr>| async move { peer.to_string() }),
+        );
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(unix::serve(listener, app, async {
+            let _ = stopped.await;
+        }));
+
+        let mut stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+        stream
+            .write_all(b"GET /peer HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+        assert!(response.ends_with(&unix::PEER.to_string()), "{response}");
+
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,2655 @@
+//! Merkle proof API server.
+//!
+//! Provides a REST API for fetching Merkle proofs for Ethereum addresses.
+//!
+//! # Endpoints
+//! - `GET /health` - Health check
+//! - `GET /metrics` - Prometheus metrics (see [`metrics`])
+//! - `GET /openapi.json` - `OpenAPI` 3 document (see [`openapi`]), plus `GET /docs` with the `docs-ui` feature
+//! - `GET /proof/{address}` - Get Merkle proof for an address (`?root=0x…` for a retained root)
+//! - `GET /proof/index/{index}` - Merkle proof for the leaf at a position (accepts `?root=`)
+//! - `GET /leaf/{index}` - Address and leaf hash at a position
+//! - `GET /node/{level}/{index}` - One node hash of the tree
+//! - `POST /proofs` - Merkle proofs for a JSON array of addresses (accepts `?root=`)
+//! - `GET /roots` - Retained roots of the default campaign with their activation times
+//! - `GET /root` - Current root, root level, leaf count and layer count
+//! - `GET /info` - Root plus build details from `manifest.json`
+//! - `GET /stats` - Sizes of the database files and proof cache hit rates
+//! - `GET /signature` - EIP-191 signature over the root, when the database is signed
+//! - `GET /voucher/{address}` - EIP-712 claim voucher, when vouchers are enabled
+//! - `GET /campaigns` - List campaigns with their health status
+//! - `GET /campaigns/{id}/health` - Health status of one campaign
+//! - `GET /campaigns/{id}/proof/{address}` - Merkle proof within a campaign (accepts `?root=`)
+//! - `POST /campaigns/{id}/proofs` - Batch of Merkle proofs within a campaign
+//! - `GET /campaigns/{id}/roots` - Retained roots of a campaign
+//! - `GET /campaigns/{id}/root`, `/info`, `/stats` - Metadata of a campaign
+//! - `GET /campaigns/{id}/proof/index/{index}`, `/leaf/{index}`, `/node/{level}/{index}` -
+//!   Positional access within a campaign
+//! - `GET /campaigns/{id}/signature` - Root signature of a campaign
+//! - `POST /admin/reload` - Reload and validate all campaigns (requires `ADMIN_TOKEN`)
+//!
+//! The unprefixed `/proof` and `/signature` routes serve the default campaign.
+//! The same port also serves the `merkle.v1.MerkleProofs` gRPC service (see
+//! [`grpc`]).
+//! Sending `SIGHUP` also reloads the campaigns and the TLS certificate.
+//! `--tls-cert`/`--tls-key` serve HTTPS directly, and `--tls-client-ca`
+//! requires client certificates (see [`tls`]). `--unix-socket` serves on a
+//! Unix domain socket instead of TCP, and a socket passed by systemd socket
+//! activation takes precedence over both (see [`listener`]). `--api-keys`
+//! loads API keys and access tiers (see [`auth`]). `--config` reads every
+//! setting below from a TOML file; flags override environment variables,
+//! which override the file (see [`config`]).
+//!
+//! # Environment Variables
+//! - `ALLOWED_ORIGINS` - CORS origins (comma-separated)
+//! - `RATE_LIMIT_PER_SECOND` - Anonymous requests per second and IP address
+//! - `RATE_LIMIT_BURST_SIZE` - Burst size for anonymous requests
+//! - `TRUSTED_PROXIES`, `RATE_LIMIT_ALLOWLIST`, `ROUTE_RATE_LIMITS`, `NOT_FOUND_RATE_LIMIT` -
+//!   Client addresses behind proxies and further rate limits (see [`limits`])
+//! - `MAX_BATCH_SIZE` - Maximum number of addresses per `POST /proofs` request
+//! - `PROOF_CACHE_SIZE`, `PINNED_LAYERS` - Proof cache and layers kept in memory
+//!   (see [`proof_cache`])
+//! - `PROOF_CACHE_CONTROL`, `ROOT_PROOF_CACHE_CONTROL` - `Cache-Control` of proof
+//!   responses (see [`http_cache`])
+//! - `ADMIN_TOKEN` - Bearer token for the admin endpoints (disabled when unset)
+//! - `VOUCHER_*` - Voucher signing key and EIP-712 domain (see [`voucher`])
+//! - `RUST_LOG`, `LOG_FORMAT`, `OTEL_EXPORTER_OTLP_ENDPOINT` - Logging and tracing (see [`telemetry`])
+
+mod auth;
+mod campaigns;
+mod config;
+mod grpc;
+mod http_cache;
+mod info;
+mod limits;
+mod listener;
+mod metrics;
+mod openapi;
+mod proof_cache;
+mod reload;
+mod telemetry;
+mod tls;
+mod voucher;
+
+use std::env;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use auth::Access;
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use axum::middleware;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::Extension;
+use axum::Json;
+use axum::Router;
+use campaigns::{Campaign, CampaignHealth, CampaignRegistry, CampaignSource};
+use config::{Config, Settings};
+use http_cache::CachePolicy;
+use info::{InfoResponse, RootResponse, StatsResponse};
+use limits::NotFoundCount;
+use listener::Listener;
+use merklebuilder::merkle::{
+    build_proof, build_proof_at_index, find_address_index, parse_address, parse_hash, read_address,
+    read_layer_node, to_hex20, to_hex32, MerkleError, ProofResult,
+};
+use merklebuilder::signing::SignedRoot;
+use merklebuilder::to_checksum_address;
+use proof_cache::ProofCache;
+use reload::{report_reload, spawn_sighup_handler, spawn_watcher, CampaignStore};
+use serde::{Deserialize, Serialize};
+use telemetry::{with_request_tracing, Telemetry};
+use thiserror::Error;
+use tls::TlsSettings;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tracing::{debug, error, info, instrument};
+use utoipa::{IntoParams, ToSchema};
+use voucher::{VoucherIssuer, VoucherResponse};
+
+#[derive(Clone)]
+struct AppState {
+    campaigns: Arc<CampaignStore>,
+    vouchers: Option<Arc<VoucherIssuer>>,
+    admin_token: Option<Arc<str>>,
+    max_batch_size: usize,
+    access: Arc<Access>,
+    cache: Arc<CachePolicy>,
+    allowed_origins: Arc<[HeaderValue]>,
+}
+
+#[derive(Debug, Error)]
+enum ApiError {
+    #[error("bad request: {0}")]
+    BadRequest(String),
+    #[error("unauthorized: {0}")]
+    Unauthorized(String),
+    #[error("forbidden: {0}")]
+    Forbidden(String),
+    #[error("not found: {0}")]
+    NotFound(String),
+    #[error("too many requests: {0}")]
+    TooManyRequests(String),
+    #[error("internal error: {0}")]
+    Internal(String),
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        if status.is_server_error() {
+            error!("{self}");
+        } else {
+            debug!("{self}");
+        }
+        let body = Json(ErrorBody {
+            error: self.to_string(),
+        });
+        (status, body).into_response()
+    }
+}
+
+impl ApiError {
+    /// Status of a failed entry within a batch of proofs.
+    fn batch_status(&self) -> &'static str {
+        match self {
+            ApiError::BadRequest(_) => "invalid",
+            ApiError::NotFound(_) => "not_found",
+            _ => "error",
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct ErrorBody {
+    error: String,
+}
+
+#[derive(Serialize, ToSchema)]
+struct HealthResponse {
+    status: &'static str,
+}
+
+#[derive(Serialize, ToSchema)]
+struct CampaignsResponse {
+    default: String,
+    campaigns: Vec<CampaignHealth>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct LoadedCampaign {
+    id: String,
+    root: String,
+    leaf_count: usize,
+    loaded_at: u64,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ReloadResponse {
+    status: &'static str,
+    default: String,
+    campaigns: Vec<LoadedCampaign>,
+}
+
+impl From<&CampaignRegistry> for ReloadResponse {
+    fn from(registry: &CampaignRegistry) -> Self {
+        ReloadResponse {
+            status: "reloaded",
+            default: registry.default_id().to_string(),
+            campaigns: registry
+                .iter()
+                .map(|campaign| LoadedCampaign {
+                    id: campaign.id.clone(),
+                    root: to_hex32(&campaign.root),
+                    leaf_count: campaign.leaf_count,
+                    loaded_at: campaign.loaded_at,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct RootEntry {
+    root: String,
+    leaf_count: usize,
+    activated_at: u64,
+    current: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+struct RootsResponse {
+    campaign: String,
+    current: String,
+    roots: Vec<RootEntry>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct BatchProofResult {
+    address: String,
+    /// `ok`, `not_found`, `invalid` or `error`.
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    proof: Option<ProofResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl BatchProofResult {
+    fn new(address: String, result: Result<ProofResponse, ApiError>) -> Self {
+        match result {
+            Ok(proof) => BatchProofResult {
+                address,
+                status: "ok",
+                proof: Some(proof),
+                error: None,
+            },
+            Err(e) => BatchProofResult {
+                address,
+                status: e.batch_status(),
+                proof: None,
+                error: Some(e.to_string()),
+            },
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct BatchProofResponse {
+    results: Vec<BatchProofResult>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ProofQuery {
+    /// Retained root to prove against, as 0x-prefixed hex; the current root when absent.
+    root: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct LeafResponse {
+    index: usize,
+    address: String,
+    leaf: String,
+}
+
+#[derive(Serialize, ToSchema)]
+struct NodeResponse {
+    level: usize,
+    index: usize,
+    hash: String,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ProofNode {
+    level: usize,
+    sibling_index: usize,
+    side: String,
+    hash: String,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ProofResponse {
+    address: String,
+    index: usize,
+    total: usize,
+    lookups: usize,
+    leaf: String,
+    root: String,
+    root_level: usize,
+    proof: Vec<ProofNode>,
+    proof_flags: Vec<bool>,
+    /// EIP-191 signature over the root attestation, when the database is signed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signature: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signer: Option<String>,
+}
+
+impl From<ProofResult> for ProofResponse {
+    fn from(proof: ProofResult) -> Self {
+        let ProofResult {
+            normalized_address,
+            index,
+            total,
+            lookups,
+            leaf,
+            root,
+            root_level,
+            steps,
+        } = proof;
+
+        let mut proof_flags = Vec::with_capacity(steps.len());
+        let mut proof_nodes = Vec::with_capacity(steps.len());
+        for step in steps {
+            proof_flags.push(step.side.proof_flag());
+            proof_nodes.push(ProofNode {
+                level: step.level,
+                sibling_index: step.sibling_index,
+                side: step.side.as_str().to_string(),
+                hash: to_hex32(&step.sibling_hash),
+            });
+        }
+
+        ProofResponse {
+            address: normalized_address,
+            index,
+            total,
+            lookups,
+            leaf: to_hex32(&leaf),
+            root: to_hex32(&root),
+            root_level,
+            proof: proof_nodes,
+            proof_flags,
+            signature: None,
+            signer: None,
+        }
+    }
+}
+
+/// Reads the flags, which override every other source of settings, and the
+/// path given with `--config`.
+fn parse_args() -> Result<(Option<PathBuf>, Settings), String> {
+    let mut args = env::args().skip(1);
+    let mut file = None;
+    let mut flags = Settings::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" | "-c" => file = Some(path_arg(&mut args, "--config")?),
+            "--listen" | "-l" => {
+                let raw = args
+                    .next()
+                    .ok_or_else(|| "--listen requires an address like 0.0.0.0:3000".to_string())?;
+                flags.listener.address = Some(
+                    raw.parse()
+                        .map_err(|e| format!("Invalid listen address '{raw}': {e}"))?,
+                );
+            }
+            "--unix-socket" => {
+                flags.listener.unix_socket = Some(path_arg(&mut args, "--unix-socket")?);
+            }
+            "--data-dir" | "-d" => flags.data.dir = Some(path_arg(&mut args, "--data-dir")?),
+            "--campaigns-dir" => {
+                flags.data.campaigns_dir = Some(path_arg(&mut args, "--campaigns-dir")?);
+            }
+            "--default-campaign" => {
+                flags.data.default_campaign = Some(
+                    args.next()
+                        .ok_or_else(|| "--default-campaign requires a campaign ID".to_string())?,
+                );
+            }
+            "--signer" => {
+                flags.data.signer = Some(
+                    args.next()
+                        .ok_or_else(|| "--signer requires an address".to_string())?,
+                );
+            }
+            "--watch-interval" => {
+                let raw = args
+                    .next()
+                    .ok_or_else(|| "--watch-interval requires a number of seconds".to_string())?;
+                flags.data.watch_interval = Some(
+                    raw.parse()
+                        .map_err(|_| format!("Invalid watch interval '{raw}'"))?,
+                );
+            }
+            "--retain-roots" => {
+                let raw = args
+                    .next()
+                    .ok_or_else(|| "--retain-roots requires a number of roots".to_string())?;
+                flags.data.retain_roots = Some(
+                    raw.parse()
+                        .map_err(|_| format!("Invalid number of retained roots '{raw}'"))?,
+                );
+            }
+            "--tls-cert" => flags.listener.tls_cert = Some(path_arg(&mut args, "--tls-cert")?),
+            "--tls-key" => flags.listener.tls_key = Some(path_arg(&mut args, "--tls-key")?),
+            "--tls-client-ca" => {
+                flags.listener.tls_client_ca = Some(path_arg(&mut args, "--tls-client-ca")?);
+            }
+            "--api-keys" => flags.rate_limit.api_keys = Some(path_arg(&mut args, "--api-keys")?),
+            other => return Err(format!("Unknown argument: {other}")),
+        }
+    }
+    Ok((file, flags))
+}
+
+fn path_arg(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<PathBuf, String> {
+    args.next()
+        .map(PathBuf::from)
+        .ok_or_else(|| format!("{flag} requires a path"))
+}
+
+fn campaign_source(config: &Config) -> CampaignSource {
+    match &config.campaigns_dir {
+        Some(dir) => CampaignSource::Directory {
+            dir: dir.clone(),
+            default_id: config.default_campaign.clone(),
+        },
+        None => CampaignSource::Single(config.data_dir.clone()),
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let (file, flags) = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{e}");
+            eprintln!(
+                "Usage: merkle_api [--config <toml>] [--listen <addr:port> | --unix-socket <path>] [--data-dir <path> | --campaigns-dir <path> [--default-campaign <id>]] [--signer <address>] [--watch-interval <secs>] [--retain-roots <n>] [--tls-cert <pem> --tls-key <pem> [--tls-client-ca <pem>]] [--api-keys <toml>]"
+            );
+            std::process::exit(1);
+        }
+    };
+
+    let config = match Config::load(file, flags) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    let telemetry = match Telemetry::init(&config.logging) {
+        Ok(telemetry) => telemetry,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    let tls = match config.tls.as_ref().map(TlsSettings::load).transpose() {
+        Ok(tls) => tls,
+        Err(e) => {
+            error!("{e}");
+            std::process::exit(1);
+        }
+    };
+
+    let campaigns = match CampaignStore::load(
+        campaign_source(&config),
+        config.signer,
+        config.retain_roots,
+        ProofCache::new(config.proof_cache_size, config.pinned_layers),
+    ) {
+        Ok(campaigns) => campaigns,
+        Err(e) => {
+            error!("{e}");
+            std::process::exit(1);
+        }
+    };
+
+    let vouchers = match VoucherIssuer::from_env() {
+        Ok(issuer) => issuer,
+        Err(e) => {
+            error!("{e}");
+            std::process::exit(1);
+        }
+    };
+
+    let access = match load_access(&config) {
+        Ok(access) => access,
+        Err(e) => {
+            error!("{e}");
+            std::process::exit(1);
+        }
+    };
+
+    let listener = match Listener::open(&config.listen) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("{e}");
+            std::process::exit(1);
+        }
+    };
+
+    print_startup(
+        &config,
+        &listener,
+        &campaigns.current(),
+        vouchers.as_ref(),
+        &access,
+    );
+
+    let campaigns = Arc::new(campaigns);
+    spawn_sighup_handler(Arc::clone(&campaigns));
+    if let Some(interval) = config.watch_interval {
+        spawn_watcher(Arc::clone(&campaigns), interval);
+    }
+    if let (Some(settings), Some(tls)) = (&config.tls, &tls) {
+        tls::spawn_sighup_handler(settings.clone(), tls.clone());
+    }
+
+    let access = Arc::new(access);
+    auth::spawn_pruner(Arc::clone(&access));
+
+    let state = AppState {
+        campaigns,
+        vouchers: vouchers.map(Arc::new),
+        admin_token: env::var("ADMIN_TOKEN")
+            .ok()
+            .filter(|token| !token.is_empty())
+            .map(Arc::from),
+        max_batch_size: config.max_batch_size,
+        access,
+        cache: Arc::new(config.cache_policy.clone()),
+        allowed_origins: Arc::from(config.allowed_origins.clone()),
+    };
+
+    let app = build_router(state);
+    let served = listener::serve(listener, app, tls).await;
+    telemetry.shutdown().await;
+    if let Err(e) = served {
+        error!("Server error: {e}");
+        std::process::exit(1);
+    }
+}
+
+/// Builds the access tiers from `--api-keys`, the anonymous rate limit and
+/// the proxy and route limit settings.
+fn load_access(config: &Config) -> Result<Access, String> {
+    let access = match &config.api_keys {
+        Some(path) => Access::load(path, config.anonymous)?,
+        None => Access::open(config.anonymous),
+    };
+    Ok(access.with_limits(config.limits()))
+}
+
+fn print_startup(
+    config: &Config,
+    listener: &Listener,
+    registry: &CampaignRegistry,
+    vouchers: Option<&VoucherIssuer>,
+    access: &Access,
+) {
+    if let Some(file) = &config.file {
+        info!("Loaded settings from {}", file.display());
+    }
+    info!(
+        "Serving {} campaign(s) on {} (default: {})",
+        registry.len(),
+        listener.url(config.tls.is_some()),
+        registry.default_id()
+    );
+    for campaign in registry.iter() {
+        let health = campaign.health();
+        info!(
+            "Campaign {}: {} ({} layer files){}",
+            campaign.id,
+            campaign.db_dir.display(),
+            health.layers,
+            match &campaign.signed_root {
+                Some(signed) => format!(", root {} signed by {}", signed.root, signed.signer),
+                None => ", unsigned".to_string(),
+            }
+        );
+    }
+    if let Some(issuer) = vouchers {
+        info!(
+            "Issuing vouchers for {} on chain {} signed by {}",
+            to_checksum_address(&issuer.domain().verifying_contract),
+            issuer.domain().chain_id,
+            issuer.signer()
+        );
+    }
+    let origins: Vec<&str> = config
+        .allowed_origins
+        .iter()
+        .filter_map(|origin| origin.to_str().ok())
+        .collect();
+    info!("CORS enabled for origins: {}", origins.join(","));
+    if let Some(interval) = config.watch_interval {
+        info!("Watching campaign files every {}s", interval.as_secs());
+    }
+    if let Some(path) = &config.api_keys {
+        info!(
+            "Loaded {} API key(s) from {}",
+            access.key_count(),
+            path.display()
+        );
+    }
+    let proxies = access.limits().trusted_proxy_count();
+    if proxies > 0 {
+        info!("Trusting forwarded client addresses from {proxies} proxy network(s)");
+    }
+    if let Some(TlsSettings {
+        client_ca: Some(ca),
+        ..
+    }) = &config.tls
+    {
+        info!("Requiring client certificates issued by {}", ca.display());
+    }
+}
+
+fn build_router(state: AppState) -> Router {
+    let cors = CorsLayer::new()
+        .allow_origin(AllowOrigin::list(state.allowed_origins.iter().cloned()))
+        .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
+        .allow_headers([
+            header::CONTENT_TYPE,
+            header::ACCEPT,
+            auth::API_KEY_HEADER,
+            auth::TIMESTAMP_HEADER,
+            auth::SIGNATURE_HEADER,
+        ]);
+
+    let router = Router::new()
+        .route("/health", get(health))
+        .route("/metrics", get(metrics::render))
+        .route("/proof/:address", get(proof))
+        .route("/proof/index/:index", get(proof_at_index))
+        .route("/leaf/:index", get(leaf))
+        .route("/node/:level/:index", get(node))
+        .route("/proofs", post(proofs))
+        .route("/roots", get(roots))
+        .route("/root", get(root))
+        .route("/info", get(info))
+        .route("/stats", get(stats))
+        .route("/signature", get(signature))
+        .route("/voucher/:address", get(voucher))
+        .route("/campaigns", get(list_campaigns))
+        .route("/campaigns/:id/health", get(campaign_health))
+        .route("/campaigns/:id/proof/:address", get(campaign_proof))
+        .route(
+            "/campaigns/:id/proof/index/:index",
+            get(campaign_proof_at_index),
+        )
+        .route("/campaigns/:id/leaf/:index", get(campaign_leaf))
+        .route("/campaigns/:id/node/:level/:index", get(campaign_node))
+        .route("/campaigns/:id/proofs", post(campaign_proofs))
+        .route("/campaigns/:id/roots", get(campaign_roots))
+        .route("/campaigns/:id/root", get(campaign_root))
+        .route("/campaigns/:id/info", get(campaign_info))
+        .route("/campaigns/:id/stats", get(campaign_stats))
+        .route("/campaigns/:id/signature", get(campaign_signature))
+        .route("/admin/reload", post(admin_reload))
+        .merge(openapi::routes())
+        .merge(grpc::routes(grpc::MerkleService::new(
+            Arc::clone(&state.campaigns),
+            state.max_batch_size,
+        )))
+        .layer(cors)
+        .layer(middleware::from_fn_with_state(
+            Arc::clone(&state.access),
+            auth::enforce,
+        ))
+        .layer(middleware::from_fn(metrics::track_requests));
+    with_request_tracing(router).with_state(state)
+}
+
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "health",
+    responses(
+        (status = 200, description = "Server is up", body = HealthResponse),
+    )
+)]
+async fn health() -> Json<HealthResponse> {
+    Json(HealthResponse { status: "ok" })
+}
+
+#[utoipa::path(
+    get,
+    path = "/proof/{address}",
+    tag = "proofs",
+    params(("address" = String, Path, description = "Ethereum address, 0x-prefixed hex"), ProofQuery),
+    responses(
+        (status = 200, description = "Merkle proof", body = ProofResponse),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag"),
+        (status = 400, description = "Malformed input", body = ErrorBody),
+        (status = 404, description = "Address or root not found", body = ErrorBody),
+        (status = 500, description = "Database error", body = ErrorBody),
+    )
+)]
+async fn proof(
+    Path(address): Path<String>,
+    Query(query): Query<ProofQuery>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let registry = state.campaigns.current();
+    let campaign = select_root(&registry, registry.default_id(), query.root.as_deref())?;
+    cached_proof_for(&state, campaign, address, query.root.is_some(), &headers).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/campaigns/{id}/proof/{address}",
+    tag = "proofs",
+    params(("id" = String, Path, description = "Campaign ID"), ("address" = String, Path, description = "Ethereum address, 0x-prefixed hex"), ProofQuery),
+    responses(
+        (status = 200, description = "Merkle proof", body = ProofResponse),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag"),
+        (status = 400, description = "Malformed input", body = ErrorBody),
+        (status = 404, description = "Campaign, address or root not found", body = ErrorBody),
+        (status = 500, description = "Database error", body = ErrorBody),
+    )
+)]
+async fn campaign_proof(
+    Path((id, address)): Path<(String, String)>,
+    Query(query): Query<ProofQuery>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let campaign = select_root(&state.campaigns.current(), &id, query.root.as_deref())?;
+    cached_proof_for(&state, campaign, address, query.root.is_some(), &headers).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/proofs",
+    tag = "proofs",
+    request_body(content = Vec<String>, description = "Addresses to prove"),
+    params(ProofQuery),
+    responses(
+        (status = 200, description = "One result per address, in request order", body = BatchProofResponse),
+        (status = 400, description = "Malformed input", body = ErrorBody),
+        (status = 404, description = "Root not found", body = ErrorBody),
+        (status = 500, description = "Database error", body = ErrorBody),
+    )
+)]
+async fn proofs(
+    Query(query): Query<ProofQuery>,
+    State(state): State<AppState>,
+    Json(addresses): Json<Vec<String>>,
+) -> Result<(Extension<NotFoundCount>, Json<BatchProofResponse>), ApiError> {
+    let registry = state.campaigns.current();
+    let campaign = select_root(&registry, registry.default_id(), query.root.as_deref())?;
+    batch_proofs_for(
+        Arc::clone(state.campaigns.proofs()),
+        campaign,
+        addresses,
+        query.root.is_some(),
+        state.max_batch_size,
+    )
+    .await
+}
+
+#[utoipa::path(
+    post,
+    path = "/campaigns/{id}/proofs",
+    tag = "proofs",
+    request_body(content = Vec<String>, description = "Addresses to prove"),
+    params(("id" = String, Path, description = "Campaign ID"), ProofQuery),
+    responses(
+        (status = 200, description = "One result per address, in request order", body = BatchProofResponse),
+        (status = 400, description = "Malformed input", body = ErrorBody),
+        (status = 404, description = "Campaign or root not found", body = ErrorBody),
+        (status = 500, description = "Database error", body = ErrorBody),
+    )
+)]
+async fn campaign_proofs(
+    Path(id): Path<String>,
+    Query(query): Query<ProofQuery>,
+    State(state): State<AppState>,
+    Json(addresses): Json<Vec<String>>,
+) -> Result<(Extension<NotFoundCount>, Json<BatchProofResponse>), ApiError> {
+    let campaign = select_root(&state.campaigns.current(), &id, query.root.as_deref())?;
+    batch_proofs_for(
+        Arc::clone(state.campaigns.proofs()),
+        campaign,
+        addresses,
+        query.root.is_some(),
+        state.max_batch_size,
+    )
+    .await
+}
+
+/// Picks the campaign version serving `root`, or the current one without it.
+fn select_root(
+    registry: &CampaignRegistry,
+    id: &str,
+    root: Option<&str>,
+) -> Result<Arc<Campaign>, ApiError> {
+    let current = registry
+        .get(id)
+        .ok_or_else(|| ApiError::NotFound(format!("campaign '{id}'")))?;
+    let Some(raw) = root else {
+        return Ok(current);
+    };
+    let root = parse_hash(raw).map_err(|e| classify_error(&e))?;
+    registry.at_root(id, &root).ok_or_else(|| {
+        ApiError::NotFound(format!(
+            "root {} is not retained for campaign '{id}'",
+            to_hex32(&root)
+        ))
+    })
+}
+
+#[instrument(skip_all, fields(campaign = %campaign.id, address = %address))]
+async fn proof_for(
+    proofs: Arc<ProofCache>,
+    campaign: Arc<Campaign>,
+    address: String,
+    explicit_root: bool,
+) -> Result<Json<ProofResponse>, ApiError> {
+    metrics::spawn_blocking(move || {
+        campaign_proof_response(&proofs, &campaign, &address, explicit_root)
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("spawn_blocking error: {e}")))?
+    .map(Json)
+}
+
+/// Answers `If-None-Match` once the address is known to be in the tree,
+/// without building the proof, else builds the proof and tags it with its
+/// `ETag` and `Cache-Control` (see [`http_cache`]).
+async fn cached_proof_for(
+    state: &AppState,
+    campaign: Arc<Campaign>,
+    address: String,
+    explicit_root: bool,
+    headers: &HeaderMap,
+) -> Result<Response, ApiError> {
+    let etag = http_cache::proof_etag(&campaign, &address);
+    if let Some(not_modified) = etag
+        .as_ref()
+        .and_then(|etag| state.cache.not_modified(headers, etag, explicit_root))
+    {
+        let proofs = Arc::clone(state.campaigns.proofs());
+        let (member, lookup) = (Arc::clone(&campaign), address.clone());
+        let is_member = metrics::spawn_blocking(move || proofs.is_member(&member, &lookup))
+            .await
+            .unwrap_or(false);
+        if is_member {
+            return Ok(not_modified);
+        }
+    }
+    let proofs = Arc::clone(state.campaigns.proofs());
+    let mut response = proof_for(proofs, campaign, address, explicit_root)
+        .await?
+        .into_response();
+    if let Some(etag) = etag {
+        state
+            .cache
+            .apply(response.headers_mut(), etag, explicit_root);
+    }
+    Ok(response)
+}
+
+/// Builds every proof of a batch in one blocking task.
+#[instrument(skip_all, fields(campaign = %campaign.id, batch_size = addresses.len()))]
+async fn batch_proofs_for(
+    proofs: Arc<ProofCache>,
+    campaign: Arc<Campaign>,
+    addresses: Vec<String>,
+    explicit_root: bool,
+    max_batch_size: usize,
+) -> Result<(Extension<NotFoundCount>, Json<BatchProofResponse>), ApiError> {
+    check_batch_size(addresses.len(), max_batch_size)?;
+    let results: Vec<_> = metrics::spawn_blocking(move || {
+        addresses
+            .into_iter()
+            .map(|address| {
+                let result = campaign_proof_response(&proofs, &campaign, &address, explicit_root);
+                BatchProofResult::new(address, result)
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("spawn_blocking error: {e}")))?;
+    let not_found = NotFoundCount(count_not_found(&results));
+    Ok((Extension(not_found), Json(BatchProofResponse { results })))
+}
+
+fn count_not_found(results: &[BatchProofResult]) -> usize {
+    results
+        .iter()
+        .filter(|result| result.status == "not_found")
+        .count()
+}
+
+fn campaign_proof_response(
+    proofs: &ProofCache,
+    campaign: &Campaign,
+    address: &str,
+    explicit_root: bool,
+) -> Result<ProofResponse, ApiError> {
+    let proof = build_campaign_proof(proofs, campaign, address, explicit_root)?;
+    Ok(signed_proof_response(campaign, proof))
+}
+
+fn check_batch_size(len: usize, max_batch_size: usize) -> Result<(), ApiError> {
+    if len == 0 {
+        return Err(ApiError::BadRequest("batch holds no addresses".to_string()));
+    }
+    if len > max_batch_size {
+        return Err(ApiError::BadRequest(format!(
+            "batch holds {len} addresses, the maximum is {max_batch_size}"
+        )));
+    }
+    Ok(())
+}
+
+/// Builds a proof from the campaign's files, through the proof cache unless
+/// a root was given explicitly. Performs blocking I/O.
+fn build_campaign_proof(
+    proofs: &ProofCache,
+    campaign: &Campaign,
+    address: &str,
+    explicit_root: bool,
+) -> Result<ProofResult, ApiError> {
+    let proof = if explicit_root {
+        metrics::time_proof(|| build_proof(&campaign.db_dir, address))
+    } else {
+        proofs.proof(campaign, address)
+    }
+    .map_err(|e| classify_error(&e))?;
+    check_root(campaign, &proof)?;
+    Ok(proof)
+}
+
+/// The proof must lead to the campaign's loaded root: files that were
+/// rewritten in place, even below the pinned top layers, no longer serve it.
+fn check_root(campaign: &Campaign, proof: &ProofResult) -> Result<(), ApiError> {
+    if proof.root != campaign.root || proof.computed_root() != campaign.root {
+        return Err(ApiError::NotFound(format!(
+            "root {} is no longer available: the files of campaign '{}' changed on disk",
+            to_hex32(&campaign.root),
+            campaign.id
+        )));
+    }
+    Ok(())
+}
+
+fn signed_proof_response(campaign: &Campaign, proof: ProofResult) -> ProofResponse {
+    let mut response = ProofResponse::from(proof);
+    if let Some(signed) = &campaign.signed_root {
+        response.signature = Some(signed.signature.clone());
+        response.signer = Some(signed.signer.clone());
+    }
+    response
+}
+
+#[utoipa::path(
+    get,
+    path = "/proof/index/{index}",
+    tag = "proofs",
+    params(("index" = usize, Path, description = "Leaf position in the tree"), ProofQuery),
+    responses(
+        (status = 200, description = "Merkle proof", body = ProofResponse),
+        (status = 400, description = "Malformed input", body = ErrorBody),
+        (status = 404, description = "Index outside the tree or root not found", body = ErrorBody),
+        (status = 500, description = "Database error", body = ErrorBody),
+    )
+)]
+async fn proof_at_index(
+    Path(index): Path<String>,
+    Query(query): Query<ProofQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<ProofResponse>, ApiError> {
+    let registry = state.campaigns.current();
+    let campaign = select_root(&registry, registry.default_id(), query.root.as_deref())?;
+    proof_at_index_for(campaign, &index).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/campaigns/{id}/proof/index/{index}",
+    tag = "proofs",
+    params(("id" = String, Path, description = "Campaign ID"), ("index" = usize, Path, description = "Leaf position in the tree"), ProofQuery),
+    responses(
+        (status = 200, description = "Merkle proof", body = ProofResponse),
+        (status = 400, description = "Malformed input", body = ErrorBody),
+        (status = 404, description = "Campaign, index or root not found", body = ErrorBody),
+        (status = 500, description = "Database error", body = ErrorBody),
+    )
+)]
+async fn campaign_proof_at_index(
+    Path((id, index)): Path<(String, String)>,
+    Query(query): Query<ProofQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<ProofResponse>, ApiError> {
+    let campaign = select_root(&state.campaigns.current(), &id, query.root.as_deref())?;
+    proof_at_index_for(campaign, &index).await
+}
+
+#[instrument(skip_all, fields(campaign = %campaign.id, index))]
+async fn proof_at_index_for(
+    campaign: Arc<Campaign>,
+    index: &str,
+) -> Result<Json<ProofResponse>, ApiError> {
+    let index = parse_index("leaf index", index)?;
+    metrics::spawn_blocking(move || {
+        let proof = metrics::time_proof(|| build_proof_at_index(&campaign.db_dir, index))
+            .map_err(|e| classify_index_error(&e))?;
+        check_root(&campaign, &proof)?;
+        Ok(signed_proof_response(&campaign, proof))
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("spawn_blocking error: {e}")))?
+    .map(Json)
+}
+
+#[utoipa::path(
+    get,
+    path = "/leaf/{index}",
+    tag = "tree",
+    params(("index" = usize, Path, description = "Leaf position in the tree")),
+    responses(
+        (status = 200, description = "Address and leaf hash", body = LeafResponse),
+        (status = 400, description = "Malformed input", body = ErrorBody),
+        (status = 404, description = "Index outside the tree", body = ErrorBody),
+        (status = 500, description = "Database error", body = ErrorBody),
+    )
+)]
+async fn leaf(
+    Path(index): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<LeafResponse>, ApiError> {
+    leaf_for(state.campaigns.current().default_campaign(), &index).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/campaigns/{id}/leaf/{index}",
+    tag = "tree",
+    params(("id" = String, Path, description = "Campaign ID"), ("index" = usize, Path, description = "Leaf position in the tree")),
+    responses(
+        (status = 200, description = "Address and leaf hash", body = LeafResponse),
+        (status = 400, description = "Malformed input", body = ErrorBody),
+        (status = 404, description = "Campaign or index not found", body = ErrorBody),
+        (status = 500, description = "Database error", body = ErrorBody),
+    )
+)]
+async fn campaign_leaf(
+    Path((id, index)): Path<(String, String)>,
+    State(state): State<AppState>,
+) -> Result<Json<LeafResponse>, ApiError> {
+    leaf_for(find_campaign(&state, &id)?, &index).await
+}
+
+#[instrument(skip_all, fields(campaign = %campaign.id, index))]
+async fn leaf_for(campaign: Arc<Campaign>, index: &str) -> Result<Json<LeafResponse>, ApiError> {
+    let index = parse_index("leaf index", index)?;
+    metrics::spawn_blocking(move || {
+        let address = read_address(&campaign.db_dir, index)?;
+        let leaf = read_layer_node(&campaign.db_dir, 0, index)?;
+        Ok(LeafResponse {
+            index,
+            address: to_hex20(&address),
+            leaf: to_hex32(&leaf),
+        })
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("spawn_blocking error: {e}")))?
+    .map(Json)
+    .map_err(|e: MerkleError| classify_index_error(&e))
+}
+
+#[utoipa::path(
+    get,
+    path = "/node/{level}/{index}",
+    tag = "tree",
+    params(("level" = usize, Path, description = "Tree level, 0 for the leaves"), ("index" = usize, Path, description = "Node position within the level")),
+    responses(
+        (status = 200, description = "Node hash", body = NodeResponse),
+        (status = 400, description = "Malformed input", body = ErrorBody),
+        (status = 404, description = "Level or index outside the tree", body = ErrorBody),
+        (status = 500, description = "Database error", body = ErrorBody),
+    )
+)]
+async fn node(
+    Path((level, index)): Path<(String, String)>,
+    State(state): State<AppState>,
+) -> Result<Json<NodeResponse>, ApiError> {
+    node_for(state.campaigns.current().default_campaign(), &level, &index).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/campaigns/{id}/node/{level}/{index}",
+    tag = "tree",
+    params(("id" = String, Path, description = "Campaign ID"), ("level" = usize, Path, description = "Tree level, 0 for the leaves"), ("index" = usize, Path, description = "Node position within the level")),
+    responses(
+        (status = 200, description = "Node hash", body = NodeResponse),
+        (status = 400, description = "Malformed input", body = ErrorBody),
+        (status = 404, description = "Campaign, level or index not found", body = ErrorBody),
+        (status = 500, description = "Database error", body = ErrorBody),
+    )
+)]
+async fn campaign_node(
+    Path((id, level, index)): Path<(String, String, String)>,
+    State(state): State<AppState>,
+) -> Result<Json<NodeResponse>, ApiError> {
+    node_for(find_campaign(&state, &id)?, &level, &index).await
+}
+
+#[instrument(skip_all, fields(campaign = %campaign.id, level, index))]
+async fn node_for(
+    campaign: Arc<Campaign>,
+    level: &str,
+    index: &str,
+) -> Result<Json<NodeResponse>, ApiError> {
+    let level = parse_index("level", level)?;
+    let index = parse_index("node index", index)?;
+    metrics::spawn_blocking(move || read_layer_node(&campaign.db_dir, level, index))
+        .await
+        .map_err(|e| ApiError::Internal(format!("spawn_blocking error: {e}")))?
+        .map(|hash| {
+            Json(NodeResponse {
+                level,
+                index,
+                hash: to_hex32(&hash),
+            })
+        })
+        .map_err(|e| classify_index_error(&e))
+}
+
+fn parse_index(what: &str, raw: &str) -> Result<usize, ApiError> {
+    raw.parse()
+        .map_err(|_| ApiError::BadRequest(format!("invalid {what} '{raw}'")))
+}
+
+#[utoipa::path(
+    get,
+    path = "/roots",
+    tag = "metadata",
+    responses(
+        (status = 200, description = "Retained roots, newest first", body = RootsResponse),
+    )
+)]
+async fn roots(State(state): State<AppState>) -> Json<RootsResponse> {
+    let registry = state.campaigns.current();
+    Json(roots_for(&registry, registry.default_id()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/campaigns/{id}/roots",
+    tag = "metadata",
+    params(("id" = String, Path, description = "Campaign ID")),
+    responses(
+        (status = 200, description = "Retained roots, newest first", body = RootsResponse),
+        (status = 404, description = "Campaign not found", body = ErrorBody),
+    )
+)]
+async fn campaign_roots(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<RootsResponse>, ApiError> {
+    let registry = state.campaigns.current();
+    if registry.get(&id).is_none() {
+        return Err(ApiError::NotFound(format!("campaign '{id}'")));
+    }
+    Ok(Json(roots_for(&registry, &id)))
+}
+
+fn roots_for(registry: &CampaignRegistry, id: &str) -> RootsResponse {
+    let versions = registry.versions(id);
+    RootsResponse {
+        campaign: id.to_string(),
+        current: versions
+            .first()
+            .map(|version| to_hex32(&version.campaign.root))
+            .unwrap_or_default(),
+        roots: versions
+            .iter()
+            .enumerate()
+            .map(|(position, version)| RootEntry {
+                root: to_hex32(&version.campaign.root),
+                leaf_count: version.campaign.leaf_count,
+                activated_at: version.activated_at,
+                current: position == 0,
+            })
+            .collect(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/root",
+    tag = "metadata",
+    responses(
+        (status = 200, description = "Current root", body = RootResponse),
+    )
+)]
+async fn root(State(state): State<AppState>) -> Json<RootResponse> {
+    Json(RootResponse::from(
+        &*state.campaigns.current().default_campaign(),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/campaigns/{id}/root",
+    tag = "metadata",
+    params(("id" = String, Path, description = "Campaign ID")),
+    responses(
+        (status = 200, description = "Current root", body = RootResponse),
+        (status = 404, description = "Campaign not found", body = ErrorBody),
+    )
+)]
+async fn campaign_root(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<RootResponse>, ApiError> {
+    Ok(Json(RootResponse::from(&*find_campaign(&state, &id)?)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/info",
+    tag = "metadata",
+    responses(
+        (status = 200, description = "Root and build details", body = InfoResponse),
+        (status = 500, description = "Database error", body = ErrorBody),
+    )
+)]
+async fn info(State(state): State<AppState>) -> Result<Json<InfoResponse>, ApiError> {
+    info_for(state.campaigns.current().default_campaign()).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/campaigns/{id}/info",
+    tag = "metadata",
+    params(("id" = String, Path, description = "Campaign ID")),
+    responses(
+        (status = 200, description = "Root and build details", body = InfoResponse),
+        (status = 404, description = "Campaign not found", body = ErrorBody),
+        (status = 500, description = "Database error", body = ErrorBody),
+    )
+)]
+async fn campaign_info(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<InfoResponse>, ApiError> {
+    info_for(find_campaign(&state, &id)?).await
+}
+
+async fn info_for(campaign: Arc<Campaign>) -> Result<Json<InfoResponse>, ApiError> {
+    metrics::spawn_blocking(move || InfoResponse::new(&campaign))
+        .await
+        .map(Json)
+        .map_err(|e| ApiError::Internal(format!("spawn_blocking error: {e}")))
+}
+
+#[utoipa::path(
+    get,
+    path = "/stats",
+    tag = "metadata",
+    responses(
+        (status = 200, description = "Database file sizes and proof cache counters", body = StatsResponse),
+        (status = 500, description = "Database error", body = ErrorBody),
+    )
+)]
+async fn stats(State(state): State<AppState>) -> Result<Json<StatsResponse>, ApiError> {
+    stats_for(&state, state.campaigns.current().default_campaign()).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/campaigns/{id}/stats",
+    tag = "metadata",
+    params(("id" = String, Path, description = "Campaign ID")),
+    responses(
+        (status = 200, description = "Database file sizes and proof cache counters", body = StatsResponse),
+        (status = 404, description = "Campaign not found", body = ErrorBody),
+        (status = 500, description = "Database error", body = ErrorBody),
+    )
+)]
+async fn campaign_stats(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<StatsResponse>, ApiError> {
+    stats_for(&state, find_campaign(&state, &id)?).await
+}
+
+async fn stats_for(
+    state: &AppState,
+    campaign: Arc<Campaign>,
+) -> Result<Json<StatsResponse>, ApiError> {
+    let proof_cache = state.campaigns.proofs().stats(&campaign);
+    metrics::spawn_blocking(move || StatsResponse::new(&campaign, proof_cache))
+        .await
+        .map_err(|e| ApiError::Internal(format!("spawn_blocking error: {e}")))?
+        .map(Json)
+        .map_err(ApiError::Internal)
+}
+
+#[utoipa::path(
+    get,
+    path = "/signature",
+    tag = "metadata",
+    responses(
+        (status = 200, description = "EIP-191 signature over the root", body = openapi::SignedRootSchema),
+        (status = 404, description = "Database is not signed", body = ErrorBody),
+    )
+)]
+async fn signature(State(state): State<AppState>) -> Result<Json<SignedRoot>, ApiError> {
+    signature_for(&state.campaigns.current().default_campaign())
+}
+
+#[utoipa::path(
+    get,
+    path = "/campaigns/{id}/signature",
+    tag = "metadata",
+    params(("id" = String, Path, description = "Campaign ID")),
+    responses(
+        (status = 200, description = "EIP-191 signature over the root", body = openapi::SignedRootSchema),
+        (status = 404, description = "Campaign not found or not signed", body = ErrorBody),
+    )
+)]
+async fn campaign_signature(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<SignedRoot>, ApiError> {
+    let campaign = find_campaign(&state, &id)?;
+    signature_for(&campaign)
+}
+
+fn signature_for(campaign: &Campaign) -> Result<Json<SignedRoot>, ApiError> {
+    campaign
+        .signed_root
+        .clone()
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("campaign '{}' is not signed", campaign.id)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/campaigns",
+    tag = "campaigns",
+    responses(
+        (status = 200, description = "Campaigns with their health", body = CampaignsResponse),
+        (status = 500, description = "Database error", body = ErrorBody),
+    )
+)]
+async fn list_campaigns(
+    State(state): State<AppState>,
+) -> Result<Json<CampaignsResponse>, ApiError> {
+    let registry = state.campaigns.current();
+    let response = metrics::spawn_blocking(move || CampaignsResponse {
+        default: registry.default_id().to_string(),
+        campaigns: registry.iter().map(|campaign| campaign.health()).collect(),
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("spawn_blocking error: {e}")))?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    get,
+    path = "/campaigns/{id}/health",
+    tag = "campaigns",
+    params(("id" = String, Path, description = "Campaign ID")),
+    responses(
+        (status = 200, description = "Campaign is healthy", body = CampaignHealth),
+        (status = 503, description = "Campaign files are damaged", body = CampaignHealth),
+        (status = 404, description = "Campaign not found", body = ErrorBody),
+    )
+)]
+async fn campaign_health(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+) -> Result<(StatusCode, Json<CampaignHealth>), ApiError> {
+    let campaign = find_campaign(&state, &id)?;
+    let health = metrics::spawn_blocking(move || campaign.health())
+        .await
+        .map_err(|e| ApiError::Internal(format!("spawn_blocking error: {e}")))?;
+    let status = if health.is_ok() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    Ok((status, Json(health)))
+}
+
+fn find_campaign(state: &AppState, id: &str) -> Result<Arc<Campaign>, ApiError> {
+    state
+        .campaigns
+        .current()
+        .get(id)
+        .ok_or_else(|| ApiError::NotFound(format!("campaign '{id}'")))
+}
+
+#[utoipa::path(
+    get,
+    path = "/voucher/{address}",
+    tag = "vouchers",
+    params(("address" = String, Path, description = "Ethereum address, 0x-prefixed hex")),
+    responses(
+        (status = 200, description = "Signed EIP-712 claim voucher", body = VoucherResponse),
+        (status = 400, description = "Malformed input", body = ErrorBody),
+        (status = 404, description = "Vouchers disabled or address not found", body = ErrorBody),
+        (status = 500, description = "Database error", body = ErrorBody),
+    )
+)]
+async fn voucher(
+    Path(address): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<VoucherResponse>, ApiError> {
+    let issuer = state
+        .vouchers
+        .clone()
+        .ok_or_else(|| ApiError::NotFound("vouchers are not enabled".to_string()))?;
+    let account = parse_address(&address).map_err(|e| classify_error(&e))?;
+    let campaign = state.campaigns.current().default_campaign();
+    let found = metrics::spawn_blocking(move || {
+        find_address_index(&campaign.db_dir.join("addresses.bin"), &account)
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("spawn_blocking error: {e}")))?
+    .map_err(|e| classify_error(&e))?;
+    if found.is_none() {
+        return Err(classify_error(&MerkleError::AddressNotFound));
+    }
+    issuer
+        .issue(account)
+        .map(Json)
+        .map_err(|e| ApiError::Internal(e.to_string()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/reload",
+    tag = "admin",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Campaigns reloaded", body = ReloadResponse),
+        (status = 401, description = "Missing or invalid token", body = ErrorBody),
+        (status = 404, description = "Admin endpoints are disabled", body = ErrorBody),
+        (status = 500, description = "Reload failed, previous data still served", body = ErrorBody),
+    )
+)]
+async fn admin_reload(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ReloadResponse>, ApiError> {
+    authorize_admin(state.admin_token.as_deref(), &headers)?;
+    let result = state.campaigns.reload().await;
+    report_reload("admin endpoint", &result);
+    let registry = result.map_err(|e| {
+        ApiError::Internal(format!(
+            "reload failed, still serving the previous data: {e}"
+        ))
+    })?;
+    Ok(Json(ReloadResponse::from(&*registry)))
+}
+
+fn authorize_admin(expected: Option<&str>, headers: &HeaderMap) -> Result<(), ApiError> {
+    let expected =
+        expected.ok_or_else(|| ApiError::NotFound("admin endpoints are disabled".to_string()))?;
+    let provided = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or_else(|| ApiError::Unauthorized("missing bearer token".to_string()))?;
+    if !constant_time_eq(provided.as_bytes(), expected.as_bytes()) {
+        return Err(ApiError::Unauthorized("invalid token".to_string()));
+    }
+    Ok(())
+}
+
+fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    left.len() == right.len()
+        && left
+            .iter()
+            .zip(right)
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+/// Like [`classify_error`], but a position outside the tree is not found.
+fn classify_index_error(err: &MerkleError) -> ApiError {
+    match err {
+        MerkleError::IndexOutOfBounds { .. } | MerkleError::MissingLayer(_) => {
+            metrics::record_merkle_error(err);
+            ApiError::NotFound(err.to_string())
+        }
+        _ => classify_error(err),
+    }
+}
+
+fn classify_error(err: &MerkleError) -> ApiError {
+    metrics::record_merkle_error(err);
+    match err {
+        MerkleError::InvalidAddress(_) | MerkleError::InvalidHex(_) => {
+            ApiError::BadRequest(err.to_string())
+        }
+        MerkleError::AddressNotFound => ApiError::NotFound(err.to_string()),
+        _ => ApiError::Internal(err.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use limits::Limits;
+    use merklebuilder::merkle::build_proof;
+    use sha3::{Digest, Keccak256};
+    use std::fs::File;
+    use std::io::Write;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    fn hash_address(addr: &[u8]) -> [u8; 32] {
+        let mut hasher = Keccak256::new();
+        hasher.update(addr);
+        hasher.finalize().into()
+    }
+
+    fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        let mut hasher = Keccak256::new();
+        hasher.update(left);
+        hasher.update(right);
+        hasher.finalize().into()
+    }
+
+    fn create_test_db() -> (TempDir, PathBuf) {
+        let temp_dir = TempDir::new().unwrap();
+        let db_dir: PathBuf = temp_dir.path().to_path_buf();
+        write_test_db(&db_dir);
+        (temp_dir, db_dir)
+    }
+
+    pub(crate) fn write_test_db(db_dir: &std::path::Path) {
+        let test_address = [0x01u8; 20];
+
+        let addresses_path = db_dir.join("addresses.bin");
+        let mut addresses_file = File::create(&addresses_path).unwrap();
+        addresses_file.write_all(&test_address).unwrap();
+        addresses_file.write_all(&[0x02u8; 20]).unwrap();
+        addresses_file.write_all(&[0x03u8; 20]).unwrap();
+
+        let addr1_hash = hash_address(&test_address);
+        let addr2_hash = hash_address(&[0x02u8; 20]);
+        let addr3_hash = hash_address(&[0x03u8; 20]);
+
+        let layer00_path = db_dir.join("layer00.bin");
+        let mut layer00_file = File::create(&layer00_path).unwrap();
+        layer00_file.write_all(&addr1_hash).unwrap();
+        layer00_file.write_all(&addr2_hash).unwrap();
+        layer00_file.write_all(&addr3_hash).unwrap();
+
+        let hash01 = hash_pair(&addr1_hash, &addr2_hash);
+        let hash23 = hash_pair(&addr3_hash, &addr3_hash);
+
+        let layer01_path = db_dir.join("layer01.bin");
+        let mut layer01_file = File::create(&layer01_path).unwrap();
+        layer01_file.write_all(&hash01).unwrap();
+        layer01_file.write_all(&hash23).unwrap();
+
+        let root_hash = hash_pair(&hash01, &hash23);
+
+        let layer02_path = db_dir.join("layer02.bin");
+        let mut layer02_file = File::create(&layer02_path).unwrap();
+        layer02_file.write_all(&root_hash).unwrap();
+    }
+
+    async fn json_body(response: Response) -> serde_json::Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn test_state(db_dir: &std::path::Path) -> AppState {
+        test_state_for(CampaignSource::Single(db_dir.to_path_buf()))
+    }
+
+    fn test_state_for(source: CampaignSource) -> AppState {
+        AppState {
+            campaigns: Arc::new(
+                CampaignStore::load(
+                    source,
+                    None,
+                    config::DEFAULT_RETAIN_ROOTS,
+                    ProofCache::default(),
+                )
+                .unwrap(),
+            ),
+            vouchers: None,
+            admin_token: None,
+            max_batch_size: config::DEFAULT_MAX_BATCH_SIZE,
+            access: Arc::new(Access::open(auth::quota(1000, 1000).unwrap())),
+            cache: Arc::new(CachePolicy::default()),
+            allowed_origins: Arc::from([HeaderValue::from_static(config::DEFAULT_ALLOWED_ORIGINS)]),
+        }
+    }
+
+    #[test]
+    fn test_build_proof_success() {
+        let (_temp, db_dir) = create_test_db();
+        let address_str = "0x0101010101010101010101010101010101010101";
+
+        let result = build_proof(&db_dir, address_str);
+        assert!(result.is_ok());
+
+        let proof = result.unwrap();
+        assert_eq!(proof.index, 0);
+        assert_eq!(proof.total, 3);
+        assert_eq!(proof.root_level, 2);
+        assert!(!proof.steps.is_empty());
+    }
+
+    #[test]
+    fn test_build_proof_address_not_found() {
+        let (_temp, db_dir) = create_test_db();
+        let address_str = "0xffffffffffffffffffffffffffffffffffffffff";
+
+        let result = build_proof(&db_dir, address_str);
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("not found"));
+    }
+
+    #[test]
+    fn test_proof_result_structure() {
+        let (_temp, db_dir) = create_test_db();
+        let address_str = "0x0101010101010101010101010101010101010101";
+
+        let proof = build_proof(&db_dir, address_str).unwrap();
+        assert_eq!(proof.steps.len(), 2);
+        assert_eq!(proof.steps[0].level, 0);
+        assert_eq!(proof.steps[1].level, 1);
+    }
+
+    #[tokio::test]
+    async fn test_voucher_route() {
+        use merklebuilder::eip712::{recover_voucher_signer, Eip712Domain, Voucher};
+        use merklebuilder::signing::{parse_secret_key, parse_signature};
+
+        let (_temp, db_dir) = create_test_db();
+        let key =
+            parse_secret_key("0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318")
+                .unwrap();
+        let domain = Eip712Domain {
+            name: "Demo Airdrop".to_string(),
+            version: "1".to_string(),
+            chain_id: 11_155_111,
+            verifying_contract: [0xaau8; 20],
+        };
+        let issuer = VoucherIssuer::new(key, domain.clone(), 5, 60, 3).unwrap();
+        let signer = parse_address(issuer.signer()).unwrap();
+        let state = AppState {
+            vouchers: Some(Arc::new(issuer)),
+            ..test_state(&db_dir)
+        };
+
+        let address = "0x0101010101010101010101010101010101010101".to_string();
+        let Json(response) = voucher(Path(address), State(state.clone())).await.unwrap();
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["voucher"]["amount"], "5");
+        assert_eq!(json["voucher"]["nonce"], 3);
+        assert_eq!(json["domain"]["chainId"], 11_155_111);
+
+        let voucher_fields = Voucher {
+            account: [0x01u8; 20],
+            amount: 5,
+            deadline: json["voucher"]["deadline"].as_u64().unwrap(),
+            nonce: 3,
+        };
+        let signature = parse_signature(json["signature"].as_str().unwrap()).unwrap();
+        assert_eq!(
+            recover_voucher_signer(&domain, &voucher_fields, &signature).unwrap(),
+            signer
+        );
+
+        let missing = "0xffffffffffffffffffffffffffffffffffffffff".to_string();
+        let err = voucher(Path(missing), State(state)).await.unwrap_err();
+        assert!(matches!(err, ApiError::NotFound(_)));
+    }
+
+    #[test]
+    fn test_classify_error_invalid_input() {
+        use merklebuilder::merkle::MerkleError;
+        let err = MerkleError::InvalidAddress("Address must be 40 hex characters".to_string());
+        let api_error = classify_error(&err);
+        assert!(matches!(api_error, ApiError::BadRequest(_)));
+    }
+
+    #[test]
+    fn test_classify_error_not_found() {
+        use merklebuilder::merkle::MerkleError;
+        let err = MerkleError::AddressNotFound;
+        let api_error = classify_error(&err);
+        assert!(matches!(api_error, ApiError::NotFound(_)));
+    }
+
+    #[test]
+    fn test_classify_error_internal() {
+        use merklebuilder::merkle::MerkleError;
+        let err = MerkleError::Internal("Some internal error".to_string());
+        let api_error = classify_error(&err);
+        assert!(matches!(api_error, ApiError::Internal(_)));
+    }
+
+    #[test]
+    fn test_load_signed_root() {
+        use campaigns::load_signed_root;
+        use merklebuilder::signing::parse_secret_key;
+        let (_temp, db_dir) = create_test_db();
+        assert!(load_signed_root(&db_dir, None).unwrap().is_none());
+        assert!(load_signed_root(&db_dir, Some(&[0x01u8; 20])).is_err());
+
+        let key =
+            parse_secret_key("0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318")
+                .unwrap();
+        let signed = SignedRoot::sign_database(&db_dir, &key).unwrap();
+        signed.write(&db_dir).unwrap();
+        let recovered = parse_address(&signed.signer).unwrap();
+        assert_eq!(
+            load_signed_root(&db_dir, Some(&recovered)).unwrap(),
+            Some(signed)
+        );
+        assert!(load_signed_root(&db_dir, Some(&[0x01u8; 20]))
+            .unwrap_err()
+            .contains("expected"));
+
+        std::fs::write(db_dir.join("layer02.bin"), [0u8; 32]).unwrap();
+        assert!(load_signed_root(&db_dir, None).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_campaign_routes() {
+        let temp = TempDir::new().unwrap();
+        for id in ["spring", "summer"] {
+            std::fs::create_dir(temp.path().join(id)).unwrap();
+            write_test_db(&temp.path().join(id));
+        }
+        std::fs::create_dir(temp.path().join("notes")).unwrap();
+
+        assert!(CampaignRegistry::load_dir(temp.path(), None, None)
+            .unwrap_err()
+            .contains("--default-campaign"));
+        assert!(CampaignRegistry::load_dir(temp.path(), Some("autumn"), None).is_err());
+        let source = CampaignSource::Directory {
+            dir: temp.path().to_path_buf(),
+            default_id: Some("summer".to_string()),
+        };
+        let state = test_state_for(source);
+        assert_eq!(state.campaigns.current().len(), 2);
+
+        let Json(listing) = list_campaigns(State(state.clone())).await.unwrap();
+        assert_eq!(listing.default, "summer");
+        let ids: Vec<&str> = listing.campaigns.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, vec!["spring", "summer"]);
+        assert!(listing.campaigns.iter().all(|c| c.leaf_count == Some(3)));
+
+        let address = "0x0202020202020202020202020202020202020202";
+        let proof = campaign_proof(
+            Path(("spring".to_string(), address.to_string())),
+            Query(ProofQuery::default()),
+            State(state.clone()),
+            HeaderMap::new(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(json_body(proof).await["index"], 1);
+        let missing = campaign_proof(
+            Path(("autumn".to_string(), address.to_string())),
+            Query(ProofQuery::default()),
+            State(state.clone()),
+            HeaderMap::new(),
+        )
+        .await;
+        assert!(matches!(missing, Err(ApiError::NotFound(_))));
+
+        let (status, Json(health)) =
+            campaign_health(Path("spring".to_string()), State(state.clone()))
+                .await
+                .unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(health.status, "ok");
+
+        std::fs::remove_file(temp.path().join("spring/layer02.bin")).unwrap();
+        let (status, Json(health)) = campaign_health(Path("spring".to_string()), State(state))
+            .await
+            .unwrap();
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(health.error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_metadata_routes() {
+        let (_temp, db_dir) = create_test_db();
+        let state = test_state(&db_dir);
+        let expected_root = to_hex32(&state.campaigns.current().default_campaign().root);
+
+        let Json(root) = root(State(state.clone())).await;
+        let root = serde_json::to_value(root).unwrap();
+        assert_eq!(root["root"], expected_root.as_str());
+        assert_eq!(root["root_level"], 2);
+        assert_eq!(root["leaf_count"], 3);
+        assert_eq!(root["layer_count"], 3);
+
+        let Json(info) = info(State(state.clone())).await.unwrap();
+        let info = serde_json::to_value(info).unwrap();
+        assert_eq!(info["campaign"], "default");
+        assert_eq!(info["root"], expected_root.as_str());
+        assert!(info["built_at"].is_u64());
+        assert!(info.get("manifest_hash").is_none());
+
+        let Json(usage) = stats(State(state.clone())).await.unwrap();
+        let usage = serde_json::to_value(usage).unwrap();
+        let names: Vec<&str> = usage["files"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|file| file["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            vec!["addresses.bin", "layer00.bin", "layer01.bin", "layer02.bin"]
+        );
+        assert_eq!(usage["files"][1]["entries"], 3);
+        assert_eq!(usage["total_size"], 60 + 96 + 64 + 32);
+
+        let missing = campaign_info(Path("autumn".to_string()), State(state)).await;
+        assert!(matches!(missing, Err(ApiError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_proof_cache() {
+        let (_temp, db_dir) = create_test_db();
+        let state = test_state(&db_dir);
+        let cache_stats = |state: &AppState| {
+            let state = state.clone();
+            async move {
+                let Json(usage) = stats(State(state)).await.unwrap();
+                serde_json::to_value(usage).unwrap()["proof_cache"].clone()
+            }
+        };
+        let counters = cache_stats(&state).await;
+        assert_eq!(counters["capacity"], proof_cache::DEFAULT_PROOF_CACHE_SIZE);
+        assert_eq!(counters["pinned_layers"], 3);
+        assert_eq!(counters["pinned_bytes"], 96 + 64 + 32);
+        assert_eq!(counters["hit_rate"], 0.0);
+
+        let address = "0x0202020202020202020202020202020202020202";
+        for _ in 0..4 {
+            let Json(response) = proof_for(
+                Arc::clone(state.campaigns.proofs()),
+                state.campaigns.current().default_campaign(),
+                address.to_string(),
+                false,
+            )
+            .await
+            .unwrap();
+            assert_eq!(response.index, 1);
+        }
+        let counters = cache_stats(&state).await;
+        assert_eq!(counters["entries"], 1);
+        assert_eq!(counters["hits"], 3);
+        assert_eq!(counters["misses"], 1);
+        assert_eq!(counters["hit_rate"], 0.75);
+
+        // Unknown addresses are not cached; explicit roots bypass the cache.
+        let missing = proof_for(
+            Arc::clone(state.campaigns.proofs()),
+            state.campaigns.current().default_campaign(),
+            format!("0x{}", "09".repeat(20)),
+            false,
+        )
+        .await;
+        assert!(matches!(missing, Err(ApiError::NotFound(_))));
+        let explicit_root = proof_for(
+            Arc::clone(state.campaigns.proofs()),
+            state.campaigns.current().default_campaign(),
+            address.to_string(),
+            true,
+        )
+        .await;
+        assert!(explicit_root.is_ok());
+        let counters = cache_stats(&state).await;
+        assert_eq!(counters["entries"], 1);
+        assert_eq!(counters["misses"], 2);
+
+        state.campaigns.reload().await.unwrap();
+        let counters = cache_stats(&state).await;
+        assert_eq!(counters["entries"], 0);
+        assert_eq!(counters["pinned_layers"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_proof_cache_rejects_files_rewritten_below_pinned_layers() {
+        use tower::Service;
+
+        let (_temp, db_dir) = create_test_db();
+        let mut state = test_state(&db_dir);
+        state.campaigns = Arc::new(
+            CampaignStore::load(
+                CampaignSource::Single(db_dir.clone()),
+                None,
+                config::DEFAULT_RETAIN_ROOTS,
+                ProofCache::new(proof_cache::DEFAULT_PROOF_CACHE_SIZE, 1),
+            )
+            .unwrap(),
+        );
+        let mut app = build_router(state.clone());
+
+        // Only the root is pinned; the lower layers change before a reload.
+        let leaf = hash_address(&[0x04u8; 20]);
+        let leaf2 = hash_address(&[0x02u8; 20]);
+        let leaf3 = hash_address(&[0x03u8; 20]);
+        std::fs::write(db_dir.join("layer00.bin"), [leaf, leaf2, leaf3].concat()).unwrap();
+        let branches = [hash_pair(&leaf, &leaf2), hash_pair(&leaf3, &leaf3)];
+        std::fs::write(db_dir.join("layer01.bin"), branches.concat()).unwrap();
+
+        let request = axum::http::Request::builder()
+            .uri(format!("/proof/0x{}", "02".repeat(20)))
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(!response.headers().contains_key("etag"));
+        let Json(usage) = stats(State(state)).await.unwrap();
+        assert_eq!(
+            serde_json::to_value(usage).unwrap()["proof_cache"]["entries"],
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_positional_routes() {
+        let (_temp, db_dir) = create_test_db();
+        let state = test_state(&db_dir);
+        let _router = build_router(state.clone());
+
+        let Json(leaf_response) = leaf(Path("2".to_string()), State(state.clone()))
+            .await
+            .unwrap();
+        assert_eq!(
+            leaf_response.address,
+            "0x0303030303030303030303030303030303030303"
+        );
+        assert_eq!(leaf_response.leaf, to_hex32(&hash_address(&[0x03u8; 20])));
+
+        let Json(root_node) = node(
+            Path(("2".to_string(), "0".to_string())),
+            State(state.clone()),
+        )
+        .await
+        .unwrap();
+        let expected_root = to_hex32(&state.campaigns.current().default_campaign().root);
+        assert_eq!(root_node.hash, expected_root);
+
+        let Json(by_index) = proof_at_index(
+            Path("1".to_string()),
+            Query(ProofQuery::default()),
+            State(state.clone()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            by_index.address,
+            "0x0202020202020202020202020202020202020202"
+        );
+        assert_eq!(by_index.root, expected_root);
+        assert_eq!(by_index.proof_flags, vec![true, false]);
+
+        let past_end = leaf(Path("3".to_string()), State(state.clone())).await;
+        assert!(matches!(past_end, Err(ApiError::NotFound(_))));
+        let past_layer = node(
+            Path(("1".to_string(), "2".to_string())),
+            State(state.clone()),
+        )
+        .await;
+        assert!(matches!(past_layer, Err(ApiError::NotFound(_))));
+        let above_root = node(
+            Path(("3".to_string(), "0".to_string())),
+            State(state.clone()),
+        )
+        .await;
+        assert!(matches!(above_root, Err(ApiError::NotFound(_))));
+        let not_a_number = proof_at_index(
+            Path("first".to_string()),
+            Query(ProofQuery::default()),
+            State(state),
+        )
+        .await;
+        assert!(matches!(not_a_number, Err(ApiError::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn test_metrics() {
+        use tower::Service;
+
+        let (_temp, db_dir) = create_test_db();
+        let state = test_state(&db_dir);
+        let mut app = Router::new()
+            .route("/proof/:address", get(proof))
+            .layer(middleware::from_fn(metrics::track_requests))
+            .with_state(state);
+        let request = axum::http::Request::builder()
+            .uri("/proof/0x0909090909090909090909090909090909090909")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let rendered = metrics::render().await;
+        assert_eq!(rendered.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(rendered.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains(
+            r#"merkle_api_http_requests_total{method="GET",route="/proof/:address",status="404"}"#
+        ));
+        assert!(body.contains(r#"merkle_api_merkle_errors_total{kind="address_not_found"}"#));
+        assert!(body.contains("merkle_api_proof_build_seconds_count"));
+        assert!(body.contains("merkle_api_blocking_queue_seconds_count"));
+        assert!(body.contains(r#"merkle_api_campaign_leaf_count{campaign="default"}"#));
+    }
+
+    #[tokio::test]
+    async fn test_grpc_service() {
+        use grpc::MerkleProofs;
+        use tokio_stream::StreamExt;
+
+        let (_temp, db_dir) = create_test_db();
+        let state = test_state(&db_dir);
+        let service = grpc::MerkleService::new(Arc::clone(&state.campaigns), 2);
+
+        let proof = service
+            .get_proof(tonic::Request::new(grpc::GetProofRequest {
+                address: "0x0202020202020202020202020202020202020202".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let expected = build_proof(&db_dir, "0x0202020202020202020202020202020202020202").unwrap();
+        assert_eq!(proof.index, 1);
+        assert_eq!(proof.root, expected.root.to_vec());
+        assert_eq!(proof.proof.len(), expected.steps.len());
+        assert_eq!(proof.proof_flags.len(), expected.steps.len());
+
+        let missing = service
+            .get_proof(tonic::Request::new(grpc::GetProofRequest {
+                address: "0x0909090909090909090909090909090909090909".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(missing.code(), tonic::Code::NotFound);
+        let unknown = service
+            .get_root(tonic::Request::new(grpc::GetRootRequest {
+                campaign: "nope".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(unknown.code(), tonic::Code::NotFound);
+
+        let batch = service
+            .batch_get_proof(tonic::Request::new(grpc::BatchGetProofRequest {
+                addresses: vec![
+                    "0x0101010101010101010101010101010101010101".to_string(),
+                    "0xzz".to_string(),
+                ],
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let statuses: Vec<&str> = batch.results.iter().map(|r| r.status.as_str()).collect();
+        assert_eq!(statuses, ["ok", "invalid"]);
+        let missing = service
+            .batch_get_proof(tonic::Request::new(grpc::BatchGetProofRequest {
+                addresses: vec!["0x0909090909090909090909090909090909090909".to_string(); 2],
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+        assert_eq!(missing.extensions().get::<NotFoundCount>().unwrap().0, 2);
+        let too_many = service
+            .batch_get_proof(tonic::Request::new(grpc::BatchGetProofRequest {
+                addresses: vec![String::new(); 3],
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(too_many.code(), tonic::Code::InvalidArgument);
+
+        let root = service
+            .get_root(tonic::Request::new(grpc::GetRootRequest::default()))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(root.campaign, "default");
+        assert_eq!(root.leaf_count, 3);
+        assert_eq!(root.root_level, 2);
+
+        let stream = service
+            .export_proofs(tonic::Request::new(grpc::ExportProofsRequest {
+                start: 1,
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let exported: Vec<grpc::Proof> = stream.map(Result::unwrap).collect().await;
+        let indexes: Vec<u64> = exported.iter().map(|proof| proof.index).collect();
+        assert_eq!(indexes, [1, 2]);
+        assert!(exported.iter().all(|proof| proof.root == root.root));
+    }
+
+    #[tokio::test]
+    async fn test_request_ids() {
+        use tower::Service;
+
+        let (_temp, db_dir) = create_test_db();
+        let mut app = telemetry::with_request_tracing(Router::new().route("/health", get(health)))
+            .with_state(test_state(&db_dir));
+
+        let request = axum::http::Request::builder()
+            .uri("/health")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app.call(request).await.unwrap();
+        let generated = response.headers()["x-request-id"].to_str().unwrap();
+        assert_eq!(generated.len(), 36);
+
+        let request = axum::http::Request::builder()
+            .uri("/health")
+            .header("x-request-id", "client-chosen")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.headers()["x-request-id"], "client-chosen");
+    }
+
+    #[tokio::test]
+    async fn test_api_key_tiers() {
+        use hmac::{Hmac, Mac};
+        use sha2::Sha256;
+        use tower::Service;
+
+        let (temp, db_dir) = create_test_db();
+        let keys_path = temp.path().join("keys.toml");
+        std::fs::write(
+            &keys_path,
+            r#"
+[tiers.anonymous]
+requests_per_second = 1
+burst = 2
+routes = ["proof"]
+
+[tiers.partner]
+requests_per_second = 100
+burst = 100
+routes = ["proof", "batch"]
+
+[[keys]]
+name = "acme"
+key = "acme-key"
+tier = "partner"
+
+[[keys]]
+name = "signed"
+key = "signed-key"
+tier = "partner"
+secret = "s3cret"
+"#,
+        )
+        .unwrap();
+        let quota = auth::quota(10, 10).unwrap();
+        let state = AppState {
+            access: Arc::new(Access::load(&keys_path, quota).unwrap()),
+            ..test_state(&db_dir)
+        };
+        let mut app = build_router(state);
+        let address = format!("0x{}", "01".repeat(20));
+        let batch = format!(r#"["{address}"]"#);
+        let post = |key: Option<&str>| {
+            let mut builder = axum::http::Request::builder()
+                .method("POST")
+                .uri("/proofs")
+                .header("content-type", "application/json");
+            if let Some(key) = key {
+                builder = builder.header("x-api-key", key);
+            }
+            builder.body(axum::body::Body::from(batch.clone())).unwrap()
+        };
+
+        let response = app.call(post(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = app.call(post(Some("acme-key"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.call(post(Some("nope"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = app.call(post(Some("signed-key"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let sign = |timestamp: u64| {
+            let mut mac = Hmac::<Sha256>::new_from_slice(b"s3cret").unwrap();
+            let body_hash = hex::encode(<Sha256 as sha2::Digest>::digest(batch.as_bytes()));
+            mac.update(format!("{timestamp}\nPOST\n/proofs\n{body_hash}").as_bytes());
+            let mut request = post(Some("signed-key"));
+            let headers = request.headers_mut();
+            headers.insert("x-timestamp", timestamp.to_string().parse().unwrap());
+            let signature = hex::encode(mac.finalize().into_bytes());
+            headers.insert("x-signature", signature.parse().unwrap());
+            request
+        };
+        let now = campaigns::unix_now();
+        let response = app.call(sign(now)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.call(sign(now - 3600)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // Forbidden calls spend no tokens, so the anonymous burst of 2 is intact.
+        let get = || {
+            axum::http::Request::builder()
+                .uri(format!("/proof/{address}"))
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+        for _ in 0..2 {
+            let response = app.call(get()).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        let response = app.call(get()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key("retry-after"));
+
+        std::fs::write(
+            &keys_path,
+            "[[keys]]\nname = \"x\"\nkey = \"k\"\ntier = \"gold\"\n",
+        )
+        .unwrap();
+        let err = Access::load(&keys_path, quota).err().unwrap();
+        assert!(err.contains("unknown tier 'gold'"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_route_and_not_found_limits() {
+        use axum::extract::ConnectInfo;
+        use tower::Service;
+
+        let (_temp, db_dir) = create_test_db();
+        let limits = Limits::default()
+            .with_trusted_proxies(limits::parse_networks("10.0.0.0/8").unwrap())
+            .with_unlimited(limits::parse_networks("192.0.2.0/24").unwrap())
+            .with_route_limit(auth::RouteGroup::Batch, auth::quota(1, 1).unwrap())
+            .with_not_found_limit(auth::quota(1, 2).unwrap());
+        let access = Access::open(auth::quota(1000, 1000).unwrap()).with_limits(limits);
+        let state = AppState {
+            access: Arc::new(access),
+            ..test_state(&db_dir)
+        };
+        let mut app = build_router(state);
+        let member = format!("0x{}", "01".repeat(20));
+        let stranger = format!("0x{}", "09".repeat(20));
+        let request = |client: &str, method: &str, uri: &str, body: &str| {
+            let mut request = axum::http::Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("content-type", "application/json")
+                .header("x-forwarded-for", client)
+                .body(axum::body::Body::from(body.to_string()))
+                .unwrap();
+            request
+                .extensions_mut()
+                .insert(ConnectInfo(std::net::SocketAddr::from((
+                    [10, 0, 0, 1],
+                    4000,
+                ))));
+            request
+        };
+        let get =
+            |client: &str, address: &str| request(client, "GET", &format!("/proof/{address}"), "");
+
+        for _ in 0..2 {
+            let response = app.call(get("198.51.100.1", &stranger)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        }
+        // The third miss empties the bucket; later calls are refused, hits included.
+        let response = app.call(get("198.51.100.1", &stranger)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = app.call(get("198.51.100.1", &member)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key("retry-after"));
+        let response = app.call(get("198.51.100.2", &member)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        for _ in 0..5 {
+            let response = app.call(get("192.0.2.7", &stranger)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        }
+
+        let batch = format!(r#"["{member}"]"#);
+        let response = app
+            .call(request("198.51.100.3", "POST", "/proofs", &batch))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app
+            .call(request("198.51.100.3", "POST", "/proofs", &batch))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let response = app.call(get("198.51.100.3", &member)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Each `not_found` entry of a batch spends a 404 token.
+        let strangers = format!(r#"["{member}", "{stranger}", "{stranger}", "{stranger}"]"#);
+        let response = app
+            .call(request("198.51.100.4", "POST", "/proofs", &strangers))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.call(get("198.51.100.4", &member)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test]
+    async fn test_batch_proofs() {
+        let (_temp, db_dir) = create_test_db();
+        let state = AppState {
+            max_batch_size: 3,
+            ..test_state(&db_dir)
+        };
+        let batch = vec![
+            "0x0303030303030303030303030303030303030303".to_string(),
+            "0x0909090909090909090909090909090909090909".to_string(),
+            "0x12".to_string(),
+        ];
+        let (Extension(not_found), Json(response)) = proofs(
+            Query(ProofQuery::default()),
+            State(state.clone()),
+            Json(batch.clone()),
+        )
+        .await
+        .unwrap();
+        let statuses: Vec<&str> = response.results.iter().map(|r| r.status).collect();
+        assert_eq!(statuses, vec!["ok", "not_found", "invalid"]);
+        assert_eq!(not_found.0, 1);
+        assert_eq!(response.results[0].proof.as_ref().unwrap().index, 2);
+        assert!(response.results[1].error.is_some());
+        assert_eq!(response.results[2].address, "0x12");
+
+        let empty = proofs(
+            Query(ProofQuery::default()),
+            State(state.clone()),
+            Json(vec![]),
+        )
+        .await;
+        assert!(matches!(empty, Err(ApiError::BadRequest(_))));
+        let mut too_many = batch;
+        too_many.push("0x0101010101010101010101010101010101010101".to_string());
+        let oversized = proofs(Query(ProofQuery::default()), State(state), Json(too_many)).await;
+        assert!(matches!(oversized, Err(ApiError::BadRequest(_))));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_admin_reload_swaps_campaigns() {
+        let temp = TempDir::new().unwrap();
+        let v1 = temp.path().join("v1");
+        let v2 = temp.path().join("v2");
+        std::fs::create_dir(&v1).unwrap();
+        std::fs::create_dir(&v2).unwrap();
+        write_test_db(&v1);
+        std::fs::write(v2.join("addresses.bin"), [0x04u8; 20]).unwrap();
+        std::fs::write(v2.join("layer00.bin"), hash_address(&[0x04u8; 20])).unwrap();
+        let link = temp.path().join("current");
+        std::os::unix::fs::symlink(&v1, &link).unwrap();
+
+        let state = AppState {
+            admin_token: Some(Arc::from("secret")),
+            ..test_state(&link)
+        };
+        let in_flight = state.campaigns.current().default_campaign();
+
+        let unauthorized = admin_reload(State(state.clone()), HeaderMap::new()).await;
+        assert!(matches!(unauthorized, Err(ApiError::Unauthorized(_))));
+
+        std::fs::remove_file(&link).unwrap();
+        std::os::unix::fs::symlink(&v2, &link).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, "Bearer secret".parse().unwrap());
+        let Json(reloaded) = admin_reload(State(state.clone()), headers.clone())
+            .await
+            .unwrap();
+        assert_eq!(reloaded.campaigns[0].leaf_count, 1);
+        assert_eq!(state.campaigns.current().default_campaign().leaf_count, 1);
+
+        // A request that started before the swap still reads the old files.
+        let address = "0x0101010101010101010101010101010101010101";
+        assert_eq!(build_proof(&in_flight.db_dir, address).unwrap().total, 3);
+
+        std::fs::write(v2.join("layer00.bin"), [0u8; 32]).unwrap();
+        let failed = admin_reload(State(state.clone()), headers).await;
+        assert!(matches!(failed, Err(ApiError::Internal(_))));
+        assert_eq!(state.campaigns.current().default_campaign().leaf_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_proof_etags() {
+        use tower::Service;
+
+        let (_temp, db_dir) = create_test_db();
+        let state = test_state(&db_dir);
+        let root = to_hex32(&state.campaigns.current().default_campaign().root);
+        let mut app = build_router(state);
+        let get = |uri: String, etag: Option<&str>| {
+            let mut builder = axum::http::Request::builder().uri(uri);
+            if let Some(etag) = etag {
+                builder = builder.header("if-none-match", etag);
+            }
+            builder.body(axum::body::Body::empty()).unwrap()
+        };
+        let member = format!("/proof/0x{}", "01".repeat(20));
+
+        let response = app.call(get(member.clone(), None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()["cache-control"],
+            http_cache::DEFAULT_CACHE_CONTROL
+        );
+        let etag = response.headers()["etag"].to_str().unwrap().to_string();
+        assert!(etag.starts_with('"') && etag.ends_with('"'));
+
+        let response = app.call(get(member.clone(), Some(&etag))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()["etag"], etag.as_str());
+        let weak = format!("\"other\", W/{etag}");
+        let response = app.call(get(member.clone(), Some(&weak))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        let other = format!("/proof/0x{}", "02".repeat(20));
+        let response = app.call(get(other, Some(&etag))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(response.headers()["etag"], etag.as_str());
+
+        let with_root = format!("{member}?root={root}");
+        let response = app.call(get(with_root, Some(&etag))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(
+            response.headers()["cache-control"],
+            http_cache::DEFAULT_ROOT_CACHE_CONTROL
+        );
+
+        let missing = format!("/proof/0x{}", "09".repeat(20));
+        let response = app.call(get(missing, None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(!response.headers().contains_key("etag"));
+        assert!(!response.headers().contains_key("cache-control"));
+    }
+
+    #[tokio::test]
+    async fn test_if_none_match_for_non_members() {
+        use axum::extract::ConnectInfo;
+        use tower::Service;
+
+        let (_temp, db_dir) = create_test_db();
+        let limits = Limits::default().with_not_found_limit(auth::quota(1, 2).unwrap());
+        let access = Access::open(auth::quota(1000, 1000).unwrap()).with_limits(limits);
+        let state = AppState {
+            access: Arc::new(access),
+            ..test_state(&db_dir)
+        };
+        let campaign = state.campaigns.current().default_campaign();
+        let stranger = format!("0x{}", "09".repeat(20));
+        let forged = http_cache::proof_etag(&campaign, &stranger).unwrap();
+        let mut app = build_router(state);
+        let get = |address: &str, if_none_match: &str| {
+            let mut request = axum::http::Request::builder()
+                .uri(format!("/proof/{address}"))
+                .header("if-none-match", if_none_match)
+                .body(axum::body::Body::empty())
+                .unwrap();
+            request
+                .extensions_mut()
+                .insert(ConnectInfo(std::net::SocketAddr::from((
+                    [198, 51, 100, 1],
+                    4000,
+                ))));
+            request
+        };
+
+        let member = format!("0x{}", "01".repeat(20));
+        let response = app.call(get(&member, "*")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        // Neither `*` nor a computed ETag reveals anything about a stranger,
+        // and both count against the 404 limit.
+        for if_none_match in ["*", forged.to_str().unwrap(), "*"] {
+            let response = app.call(get(&stranger, if_none_match)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+            assert!(!response.headers().contains_key("etag"));
+        }
+        let response = app.call(get(&member, "*")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_proof_against_retained_root() {
+        let temp = TempDir::new().unwrap();
+        let v1 = temp.path().join("v1");
+        let v2 = temp.path().join("v2");
+        std::fs::create_dir(&v1).unwrap();
+        std::fs::create_dir(&v2).unwrap();
+        write_test_db(&v1);
+        let new_leaf = hash_address(&[0x04u8; 20]);
+        std::fs::write(v2.join("addresses.bin"), [0x04u8; 20]).unwrap();
+        std::fs::write(v2.join("layer00.bin"), new_leaf).unwrap();
+        let link = temp.path().join("current");
+        std::os::unix::fs::symlink(&v1, &link).unwrap();
+
+        let state = test_state(&link);
+        let old_root = to_hex32(&state.campaigns.current().default_campaign().root);
+        std::fs::remove_file(&link).unwrap();
+        std::os::unix::fs::symlink(&v2, &link).unwrap();
+        state.campaigns.reload().await.unwrap();
+
+        let Json(listing) = roots(State(state.clone())).await;
+        assert_eq!(listing.current, to_hex32(&new_leaf));
+        let listed: Vec<&str> = listing.roots.iter().map(|r| r.root.as_str()).collect();
+        assert_eq!(listed, vec![listing.current.as_str(), old_root.as_str()]);
+        assert!(listing.roots[0].current && !listing.roots[1].current);
+
+        let address = "0x0101010101010101010101010101010101010101";
+        let current = proof(
+            Path(address.to_string()),
+            Query(ProofQuery::default()),
+            State(state.clone()),
+            HeaderMap::new(),
+        )
+        .await;
+        assert!(matches!(current, Err(ApiError::NotFound(_))));
+        let old = proof(
+            Path(address.to_string()),
+            Query(ProofQuery {
+                root: Some(old_root.clone()),
+            }),
+            State(state.clone()),
+            HeaderMap::new(),
+        )
+        .await
+        .unwrap();
+        let old = json_body(old).await;
+        assert_eq!(old["root"], old_root);
+        assert_eq!(old["total"], 3);
+
+        let unknown = proof(
+            Path(address.to_string()),
+            Query(ProofQuery {
+                root: Some(format!("0x{}", "00".repeat(32))),
+            }),
+            State(state.clone()),
+            HeaderMap::new(),
+        )
+        .await;
+        assert!(matches!(unknown, Err(ApiError::NotFound(_))));
+        let invalid = proof(
+            Path(address.to_string()),
+            Query(ProofQuery {
+                root: Some("0x1234".to_string()),
+            }),
+            State(state.clone()),
+            HeaderMap::new(),
+        )
+        .await;
+        assert!(matches!(invalid, Err(ApiError::BadRequest(_))));
+
+        // Rewriting the old files in place retires their root.
+        std::fs::write(v1.join("layer02.bin"), [0u8; 32]).unwrap();
+        let rewritten = proof(
+            Path(address.to_string()),
+            Query(ProofQuery {
+                root: Some(old_root),
+            }),
+            State(state),
+            HeaderMap::new(),
+        )
+        .await;
+        assert!(matches!(rewritten, Err(ApiError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_in_place_drops_old_root() {
+        let temp = TempDir::new().unwrap();
+        write_test_db(temp.path());
+        let state = test_state(temp.path());
+
+        let new_leaf = hash_address(&[0x04u8; 20]);
+        for layer in merklebuilder::merkle::available_layers(temp.path()) {
+            std::fs::remove_file(layer).unwrap();
+        }
+        std::fs::write(temp.path().join("addresses.bin"), [0x04u8; 20]).unwrap();
+        std::fs::write(temp.path().join("layer00.bin"), new_leaf).unwrap();
+        state.campaigns.reload().await.unwrap();
+
+        let Json(listing) = roots(State(state)).await;
+        assert_eq!(listing.current, to_hex32(&new_leaf));
+        assert_eq!(listing.roots.len(), 1);
+    }
+
+    #[test]
+    fn test_odd_node_proof() {
+        let (_temp, db_dir) = create_test_db();
+        let address_str = "0x0303030303030303030303030303030303030303";
+
+        let result = build_proof(&db_dir, address_str);
+        assert!(result.is_ok(), "Odd node proof should succeed");
+
+        let proof = result.unwrap();
+        assert_eq!(proof.index, 2, "Third address should be at index 2");
+        assert_eq!(proof.total, 3);
+        assert_eq!(proof.root_level, 2);
+        assert_eq!(proof.steps.len(), 2, "Should have 2 proof steps");
+
+        assert_eq!(proof.steps[0].level, 0);
+        assert_eq!(
+            proof.steps[0].sibling_index, 2,
+            "Odd node should reference itself as sibling at layer 0"
+        );
+
+        assert_eq!(proof.steps[1].level, 1);
+    }
+}
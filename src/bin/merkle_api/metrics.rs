@@ -0,0 +1,230 @@
+//! Prometheus metrics served at `GET /metrics`.
+//!
+//! Every request is counted by matched route, method and status, and timed by
+//! route. Blocking work records how long it waited for a thread of the
+//! blocking pool, proof builds record their own duration, proof cache lookups
+//! are counted as hits or misses, and Merkle errors are counted by variant.
+//! Requests rejected by the rate limiter show up as status `429`. Gauges
+//! describe the root and leaf count of each campaign and are refreshed on
+//! every load.
+
+use std::sync::LazyLock;
+use std::time::Instant;
+
+use axum::extract::{MatchedPath, Request};
+use axum::http::{header, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use merklebuilder::merkle::{to_hex32, MerkleError};
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+use tokio::task::JoinError;
+use tracing::Span;
+
+use crate::campaigns::CampaignRegistry;
+
+/// Buckets for work measured in microseconds to a few seconds.
+const FAST_BUCKETS: &[f64] = &[
+    0.000_01, 0.000_05, 0.000_1, 0.000_5, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0,
+];
+
+struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    request_duration: HistogramVec,
+    blocking_queue: Histogram,
+    proof_build: Histogram,
+    proof_cache: IntCounterVec,
+    merkle_errors: IntCounterVec,
+    rate_limited: IntCounter,
+    root: IntGaugeVec,
+    leaf_count: IntGaugeVec,
+}
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("merkle_api".to_string()), None)
+            .expect("valid metrics prefix");
+        let metrics = Metrics {
+            requests: IntCounterVec::new(
+                Opts::new(
+                    "http_requests_total",
+                    "HTTP requests by route, method and status",
+                ),
+                &["route", "method", "status"],
+            )
+            .expect("valid metric"),
+            request_duration: HistogramVec::new(
+                HistogramOpts::new("http_request_duration_seconds", "HTTP request latency")
+                    .buckets(FAST_BUCKETS.to_vec()),
+                &["route", "method"],
+            )
+            .expect("valid metric"),
+            blocking_queue: Histogram::with_opts(
+                HistogramOpts::new(
+                    "blocking_queue_seconds",
+                    "Time spawn_blocking work waited for a thread",
+                )
+                .buckets(FAST_BUCKETS.to_vec()),
+            )
+            .expect("valid metric"),
+            proof_build: Histogram::with_opts(
+                HistogramOpts::new("proof_build_seconds", "Time to build one Merkle proof")
+                    .buckets(FAST_BUCKETS.to_vec()),
+            )
+            .expect("valid metric"),
+            proof_cache: IntCounterVec::new(
+                Opts::new(
+                    "proof_cache_lookups_total",
+                    "Proof cache lookups by result (hit or miss)",
+                ),
+                &["result"],
+            )
+            .expect("valid metric"),
+            merkle_errors: IntCounterVec::new(
+                Opts::new("merkle_errors_total", "Merkle errors by variant"),
+                &["kind"],
+            )
+            .expect("valid metric"),
+            rate_limited: IntCounter::new(
+                "rate_limited_total",
+                "Requests rejected by the rate limiter",
+            )
+            .expect("valid metric"),
+            root: IntGaugeVec::new(
+                Opts::new("campaign_root", "Loaded root of each campaign (always 1)"),
+                &["campaign", "root"],
+            )
+            .expect("valid metric"),
+            leaf_count: IntGaugeVec::new(
+                Opts::new("campaign_leaf_count", "Leaves in each campaign"),
+                &["campaign"],
+            )
+            .expect("valid metric"),
+            registry,
+        };
+        for collector in [
+            Box::new(metrics.requests.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(metrics.request_duration.clone()),
+            Box::new(metrics.blocking_queue.clone()),
+            Box::new(metrics.proof_build.clone()),
+            Box::new(metrics.proof_cache.clone()),
+            Box::new(metrics.merkle_errors.clone()),
+            Box::new(metrics.rate_limited.clone()),
+            Box::new(metrics.root.clone()),
+            Box::new(metrics.leaf_count.clone()),
+        ] {
+            metrics
+                .registry
+                .register(collector)
+                .expect("metric registered once");
+        }
+        metrics
+    }
+}
+
+/// Middleware counting and timing every request.
+pub async fn track_requests(
+    matched: Option<MatchedPath>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let route = matched.map_or_else(|| "unmatched".to_string(), |path| path.as_str().to_string());
+    let method = request.method().to_string();
+    let started = Instant::now();
+    let response = next.run(request).await;
+
+    let status = response.status();
+    METRICS
+        .request_duration
+        .with_label_values(&[&route, &method])
+        .observe(started.elapsed().as_secs_f64());
+    METRICS
+        .requests
+        .with_label_values(&[&route, &method, status.as_str()])
+        .inc();
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        METRICS.rate_limited.inc();
+    }
+    response
+}
+
+/// Renders every metric in the Prometheus text format.
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "health",
+    responses(
+        (status = 200, description = "Prometheus text format", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn render() -> Response {
+    let mut body = Vec::new();
+    match TextEncoder::new().encode(&METRICS.registry.gather(), &mut body) {
+        Ok(()) => ([(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)], body).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("failed to encode metrics: {e}"),
+        )
+            .into_response(),
+    }
+}
+
+/// Runs blocking work on the blocking pool, recording how long it queued.
+///
+/// The work runs inside the caller's tracing span.
+pub async fn spawn_blocking<F, T>(work: F) -> Result<T, JoinError>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let queued = Instant::now();
+    let span = Span::current();
+    tokio::task::spawn_blocking(move || {
+        let _entered = span.enter();
+        METRICS
+            .blocking_queue
+            .observe(queued.elapsed().as_secs_f64());
+        work()
+    })
+    .await
+}
+
+/// Times one proof build. Performs blocking I/O when `build` does.
+pub fn time_proof<T>(build: impl FnOnce() -> T) -> T {
+    let started = Instant::now();
+    let result = build();
+    METRICS.proof_build.observe(started.elapsed().as_secs_f64());
+    result
+}
+
+pub fn record_proof_cache(hit: bool) {
+    METRICS
+        .proof_cache
+        .with_label_values(&[if hit { "hit" } else { "miss" }])
+        .inc();
+}
+
+pub fn record_merkle_error(err: &MerkleError) {
+    METRICS.merkle_errors.with_label_values(&[err.kind()]).inc();
+}
+
+/// Points the root and leaf count gauges at a newly loaded registry.
+pub fn record_campaigns(registry: &CampaignRegistry) {
+    METRICS.root.reset();
+    METRICS.leaf_count.reset();
+    for campaign in registry.iter() {
+        METRICS
+            .root
+            .with_label_values(&[&campaign.id, &to_hex32(&campaign.root)])
+            .set(1);
+        METRICS
+            .leaf_count
+            .with_label_values(&[&campaign.id])
+            .set(i64::try_from(campaign.leaf_count).unwrap_or(i64::MAX));
+    }
+}
@@ -0,0 +1,249 @@
+//! `OpenAPI` 3 document served at `GET /openapi.json`.
+//!
+//! The document is derived from the handlers' `#[utoipa::path]` attributes and
+//! the response types, so clients can be generated from it. Built with the
+//! `docs-ui` feature, the server also embeds a Swagger UI page at `/docs`.
+//!
+//! Library types returned as-is (`SignedRoot`, `ToolInfo`, `TreeParameters`)
+//! do not depend on `utoipa`; their schemas are declared here by mirror
+//! structs that must keep the same fields.
+
+use std::sync::LazyLock;
+
+use axum::routing::get;
+use axum::{Json, Router};
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+use utoipa::openapi::OpenApi as Document;
+use utoipa::{Modify, OpenApi, ToSchema};
+
+use crate::ErrorBody;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "merkle_api",
+        description = "Merkle proofs, tree metadata and claim vouchers for airdrop campaigns."
+    ),
+    paths(
+        crate::health,
+        crate::metrics::render,
+        crate::proof,
+        crate::proof_at_index,
+        crate::proofs,
+        crate::leaf,
+        crate::node,
+        crate::roots,
+        crate::root,
+        crate::info,
+        crate::stats,
+        crate::signature,
+        crate::voucher,
+        crate::list_campaigns,
+        crate::campaign_health,
+        crate::campaign_proof,
+        crate::campaign_proof_at_index,
+        crate::campaign_proofs,
+        crate::campaign_leaf,
+        crate::campaign_node,
+        crate::campaign_roots,
+        crate::campaign_root,
+        crate::campaign_info,
+        crate::campaign_stats,
+        crate::campaign_signature,
+        crate::admin_reload,
+    ),
+    components(schemas(ErrorBody)),
+    modifiers(&AdminToken),
+    tags(
+        (name = "proofs", description = "Merkle proofs by address or position"),
+        (name = "tree", description = "Single leaves and nodes"),
+        (name = "metadata", description = "Roots, build details and signatures"),
+        (name = "campaigns", description = "Campaign listing and health"),
+        (name = "vouchers", description = "EIP-712 claim vouchers"),
+        (name = "admin", description = "Operations behind `ADMIN_TOKEN`"),
+        (name = "health", description = "Liveness and metrics"),
+    )
+)]
+struct ApiDoc;
+
+/// Declares the bearer token required by the admin endpoints.
+struct AdminToken;
+
+impl Modify for AdminToken {
+    fn modify(&self, openapi: &mut Document) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "admin_token",
+                SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+            );
+        }
+    }
+}
+
+/// Schema of `merklebuilder::signing::SignedRoot`.
+#[derive(ToSchema)]
+#[schema(as = SignedRoot)]
+#[allow(dead_code)]
+pub struct SignedRootSchema {
+    root: String,
+    leaf_count: usize,
+    /// Keccak-256 hash of `manifest.json`, when the database has one.
+    manifest_hash: Option<String>,
+    /// The exact message passed to `personal_sign`.
+    message: String,
+    signature: String,
+    signer: String,
+}
+
+/// Schema of `merklebuilder::manifest::ToolInfo`.
+#[derive(ToSchema)]
+#[schema(as = ToolInfo)]
+#[allow(dead_code)]
+pub struct ToolInfoSchema {
+    name: String,
+    version: String,
+}
+
+/// Schema of `merklebuilder::manifest::TreeParameters`.
+#[derive(ToSchema)]
+#[schema(as = TreeParameters)]
+#[allow(dead_code)]
+pub struct TreeParametersSchema {
+    /// Input format forced on the command line; absent when detected per input.
+    format: Option<String>,
+    selector: Option<String>,
+    allow_reserved: bool,
+    lenient: bool,
+    max_reject_ratio: Option<f64>,
+    leaf_hash: String,
+    node_hash: String,
+    odd_node: String,
+}
+
+static DOCUMENT: LazyLock<Document> = LazyLock::new(ApiDoc::openapi);
+
+pub fn document() -> &'static Document {
+    &DOCUMENT
+}
+
+/// Routes serving the document, plus the docs page with `docs-ui`.
+pub fn routes<S>() -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let router = Router::new().route("/openapi.json", get(|| async { Json(document()) }));
+    #[cfg(feature = "docs-ui")]
+    let router = router.merge(
+        utoipa_swagger_ui::SwaggerUi::new("/docs")
+            .config(utoipa_swagger_ui::Config::new(["/openapi.json"])),
+    );
+    router
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use merklebuilder::manifest::{ToolInfo, TreeParameters};
+    use merklebuilder::signing::SignedRoot;
+    use serde::Serialize;
+
+    fn assert_mirrors(schema: &str, value: &impl Serialize) {
+        let document = serde_json::to_value(document()).unwrap();
+        let mut declared: Vec<String> = document["components"]["schemas"][schema]["properties"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        let mut serialized: Vec<String> = serde_json::to_value(value)
+            .unwrap()
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        declared.sort();
+        serialized.sort();
+        assert_eq!(declared, serialized, "schema {schema} is out of date");
+    }
+
+    #[test]
+    fn test_openapi_document() {
+        let document = serde_json::to_value(document()).unwrap();
+        let paths = document["paths"].as_object().unwrap();
+        for path in [
+            "/health",
+            "/metrics",
+            "/proof/{address}",
+            "/proof/index/{index}",
+            "/leaf/{index}",
+            "/node/{level}/{index}",
+            "/roots",
+            "/voucher/{address}",
+            "/campaigns/{id}/proof/{address}",
+            "/campaigns/{id}/signature",
+        ] {
+            assert!(paths.contains_key(path), "missing {path}");
+        }
+        assert!(paths["/proofs"]["post"]["requestBody"].is_object());
+        assert!(paths["/admin/reload"]["post"]["security"].is_array());
+
+        let proof = &paths["/proof/{address}"]["get"];
+        let params: Vec<&str> = proof["parameters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|param| param["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(params, ["address", "root"]);
+        assert_eq!(
+            proof["responses"]["200"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/ProofResponse"
+        );
+
+        let schemas = &document["components"]["schemas"];
+        for schema in [
+            "ProofResponse",
+            "ProofNode",
+            "ErrorBody",
+            "HealthResponse",
+            "BatchProofResponse",
+            "SignedRoot",
+            "InfoResponse",
+            "ProofCacheStats",
+        ] {
+            assert!(schemas[schema].is_object(), "missing schema {schema}");
+        }
+        assert_eq!(
+            schemas["ProofNode"]["required"],
+            serde_json::json!(["level", "sibling_index", "side", "hash"])
+        );
+    }
+
+    #[test]
+    fn test_library_schemas_match_types() {
+        assert_mirrors(
+            "SignedRoot",
+            &SignedRoot {
+                root: String::new(),
+                leaf_count: 0,
+                manifest_hash: Some(String::new()),
+                message: String::new(),
+                signature: String::new(),
+                signer: String::new(),
+            },
+        );
+        assert_mirrors("ToolInfo", &ToolInfo::current());
+        assert_mirrors(
+            "TreeParameters",
+            &TreeParameters::new(
+                Some("csv".to_string()),
+                Some("address".to_string()),
+                false,
+                true,
+                Some(0.1),
+            ),
+        );
+    }
+}
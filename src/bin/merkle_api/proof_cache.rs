@@ -0,0 +1,219 @@
+//! Cache of built proofs and of the top layers of every campaign.
+//!
+//! During a claim rush the same addresses are requested over and over, so
+//! proofs against the current root of a campaign are kept in a bounded LRU
+//! cache (`PROOF_CACHE_SIZE` entries, default 10000; `0` disables it). Every
+//! proof also reads one node per layer, so the top `PINNED_LAYERS` layers of
+//! each campaign (default 16) stay in memory and proofs only read the lower
+//! layers from disk. Both can also be set in the `[cache]` section of the
+//! config file.
+//!
+//! A reload empties the cache and pins the layers of the new databases.
+//! Proofs for an explicit `?root=` bypass both. A proof is hashed from
+//! its leaf up before it is cached or served, so files rewritten in place
+//! below the pinned layers are detected instead of mixed with them.
+
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+
+use lru::LruCache;
+use merklebuilder::merkle::{
+    build_proof_with_layers, find_address_index, parse_address, MerkleError, ProofResult, TopLayers,
+};
+use merklebuilder::{ADDRESS_SIZE, HASH_SIZE};
+use serde::Serialize;
+use tracing::warn;
+use utoipa::ToSchema;
+
+use crate::campaigns::{Campaign, CampaignRegistry};
+use crate::metrics;
+
+pub const DEFAULT_PROOF_CACHE_SIZE: usize = 10_000;
+pub const DEFAULT_PINNED_LAYERS: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ProofKey {
+    campaign: String,
+    root: [u8; HASH_SIZE],
+    address: [u8; ADDRESS_SIZE],
+}
+
+pub struct ProofCache {
+    capacity: usize,
+    pinned_layers: usize,
+    proofs: Option<Mutex<LruCache<ProofKey, ProofResult>>>,
+    /// Top layers of the current version of each campaign, by campaign ID.
+    layers: RwLock<HashMap<String, Arc<TopLayers>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+/// Proof cache counters, reported by `/stats`.
+///
+/// One cache serves every campaign, so `capacity`, `entries`, `hits`,
+/// `misses` and `hit_rate` are process-wide and the same in the stats of
+/// every campaign. Only `pinned_layers` and `pinned_bytes` are per campaign.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ProofCacheStats {
+    /// Maximum number of cached proofs across all campaigns; 0 when the cache
+    /// is disabled.
+    capacity: usize,
+    /// Proofs cached across all campaigns.
+    entries: usize,
+    /// Lookups served from the cache, across all campaigns.
+    hits: u64,
+    /// Lookups that built a proof, across all campaigns.
+    misses: u64,
+    /// `hits / (hits + misses)`, or 0 before the first lookup.
+    hit_rate: f64,
+    /// Layers of this campaign held in memory.
+    pinned_layers: usize,
+    /// Memory used by the pinned layers of this campaign.
+    pinned_bytes: usize,
+}
+
+impl Default for ProofCache {
+    fn default() -> Self {
+        ProofCache::new(DEFAULT_PROOF_CACHE_SIZE, DEFAULT_PINNED_LAYERS)
+    }
+}
+
+impl ProofCache {
+    pub fn new(capacity: usize, pinned_layers: usize) -> Self {
+        ProofCache {
+            capacity,
+            pinned_layers,
+            proofs: NonZeroUsize::new(capacity).map(|size| Mutex::new(LruCache::new(size))),
+            layers: RwLock::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Empties the cache and pins the top layers of every campaign in
+    /// `registry`. A campaign whose layers cannot be read is served from disk.
+    /// Performs blocking I/O.
+    pub fn reset(&self, registry: &CampaignRegistry) {
+        let mut layers = HashMap::new();
+        if self.pinned_layers > 0 {
+            for campaign in registry.iter() {
+                match TopLayers::load(&campaign.db_dir, self.pinned_layers) {
+                    Ok(top) if top.root() == Some(campaign.root) => {
+                        layers.insert(campaign.id.clone(), Arc::new(top));
+                    }
+                    Ok(_) => warn!(
+                        "Campaign {}: files changed since loading, not pinning its layers",
+                        campaign.id
+                    ),
+                    Err(e) => warn!("Campaign {}: cannot pin its layers: {e}", campaign.id),
+                }
+            }
+        }
+        *self.layers.write().unwrap_or_else(PoisonError::into_inner) = layers;
+        if let Some(proofs) = &self.proofs {
+            proofs
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clear();
+        }
+    }
+
+    /// Top layers pinned for this version of the campaign, if any.
+    pub fn layers(&self, campaign: &Campaign) -> Option<Arc<TopLayers>> {
+        self.layers
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&campaign.id)
+            .filter(|top| top.root() == Some(campaign.root))
+            .cloned()
+    }
+
+    /// Returns the cached proof of `address`, or builds it with the pinned
+    /// layers and caches it. Performs blocking I/O on a miss.
+    pub fn proof(&self, campaign: &Campaign, address: &str) -> Result<ProofResult, MerkleError> {
+        let key = ProofKey {
+            campaign: campaign.id.clone(),
+            root: campaign.root,
+            address: parse_address(address)?,
+        };
+        if let Some(proofs) = &self.proofs {
+            let cached = proofs
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .get(&key)
+                .cloned();
+            metrics::record_proof_cache(cached.is_some());
+            if let Some(proof) = cached {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(proof);
+            }
+            self.misses.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let top = self.layers(campaign).unwrap_or_default();
+        let proof =
+            metrics::time_proof(|| build_proof_with_layers(&campaign.db_dir, address, &top))?;
+        if let Some(proofs) = &self.proofs {
+            // A proof that does not lead to the loaded root means the files
+            // changed on disk, below the pinned layers or above them.
+            if proof.root == campaign.root && proof.computed_root() == campaign.root {
+                proofs
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .put(key, proof.clone());
+            }
+        }
+        Ok(proof)
+    }
+
+    /// Whether `address` is a leaf of `campaign`: a cached proof, else a
+    /// search of `addresses.bin`. Performs blocking I/O on a miss.
+    pub fn is_member(&self, campaign: &Campaign, address: &str) -> bool {
+        let Ok(address) = parse_address(address) else {
+            return false;
+        };
+        let key = ProofKey {
+            campaign: campaign.id.clone(),
+            root: campaign.root,
+            address,
+        };
+        let cached = self.proofs.as_ref().is_some_and(|proofs| {
+            proofs
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .contains(&key)
+        });
+        cached
+            || matches!(
+                find_address_index(&campaign.db_dir.join("addresses.bin"), &address),
+                Ok(Some(_))
+            )
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    pub fn stats(&self, campaign: &Campaign) -> ProofCacheStats {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let lookups = hits + misses;
+        let pinned = self.layers(campaign);
+        ProofCacheStats {
+            capacity: self.capacity,
+            entries: self.proofs.as_ref().map_or(0, |proofs| {
+                proofs.lock().unwrap_or_else(PoisonError::into_inner).len()
+            }),
+            hits,
+            misses,
+            hit_rate: if lookups == 0 {
+                0.0
+            } else {
+                hits as f64 / lookups as f64
+            },
+            pinned_layers: pinned.as_ref().map_or(0, |top| top.len()),
+            pinned_bytes: pinned
+                .as_ref()
+                .map_or(0, |top| top.node_count() * HASH_SIZE),
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! Hot reload of the served campaigns.
+//!
+//! A reload loads and fully validates every campaign from its source, then
+//! swaps the whole registry in one atomic store. Requests that already hold
+//! the previous registry keep using it until they finish; if validation fails
+//! the previous registry stays in place. The new registry retains up to
+//! `--retain-roots` previous roots of each campaign. Once swapped in, the
+//! proof cache is emptied and the top layers of the new databases are pinned
+//! (see [`crate::proof_cache`]).
+//!
+//! Reloads are triggered by `SIGHUP`, by `POST /admin/reload`, or by polling
+//! the campaign directories for changes (`--watch-interval`).
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use arc_swap::ArcSwap;
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+
+use crate::campaigns::{CampaignRegistry, CampaignSource};
+use crate::metrics;
+use crate::proof_cache::ProofCache;
+
+pub struct CampaignStore {
+    source: CampaignSource,
+    signer: Option<[u8; 20]>,
+    retain_roots: usize,
+    current: ArcSwap<CampaignRegistry>,
+    proofs: Arc<ProofCache>,
+    reload_lock: Mutex<()>,
+}
+
+/// Modification time and size of every file in the campaign directories.
+type Fingerprint = Vec<(PathBuf, Option<SystemTime>, u64)>;
+
+impl CampaignStore {
+    /// Performs the initial load. Performs blocking I/O.
+    pub fn load(
+        source: CampaignSource,
+        signer: Option<[u8; 20]>,
+        retain_roots: usize,
+        proofs: ProofCache,
+    ) -> Result<Self, String> {
+        let registry = source.load(signer.as_ref())?;
+        metrics::record_campaigns(&registry);
+        proofs.reset(&registry);
+        Ok(CampaignStore {
+            source,
+            signer,
+            retain_roots,
+            current: ArcSwap::from_pointee(registry),
+            proofs: Arc::new(proofs),
+            reload_lock: Mutex::new(()),
+        })
+    }
+
+    pub fn current(&self) -> Arc<CampaignRegistry> {
+        self.current.load_full()
+    }
+
+    pub fn proofs(&self) -> &Arc<ProofCache> {
+        &self.proofs
+    }
+
+    /// Loads the campaigns again and swaps them in when all of them are valid.
+    pub async fn reload(&self) -> Result<Arc<CampaignRegistry>, String> {
+        let _guard = self.reload_lock.lock().await;
+        let source = self.source.clone();
+        let signer = self.signer;
+        let mut registry = metrics::spawn_blocking(move || source.load(signer.as_ref()))
+            .await
+            .map_err(|e| format!("spawn_blocking error: {e}"))??;
+        registry.retain_history(&self.current(), self.retain_roots);
+        let registry = Arc::new(registry);
+        self.current.store(Arc::clone(&registry));
+        metrics::record_campaigns(&registry);
+        let proofs = Arc::clone(&self.proofs);
+        let pinned = Arc::clone(&registry);
+        metrics::spawn_blocking(move || proofs.reset(&pinned))
+            .await
+            .map_err(|e| format!("spawn_blocking error: {e}"))?;
+        Ok(registry)
+    }
+
+    fn fingerprint(&self) -> Fingerprint {
+        let mut files = Vec::new();
+        for dir in self.source.db_dirs() {
+            let resolved = dir.canonicalize().unwrap_or(dir);
+            let Ok(entries) = std::fs::read_dir(&resolved) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let metadata = entry.metadata().ok();
+                files.push((
+                    entry.path(),
+                    metadata.as_ref().and_then(|m| m.modified().ok()),
+                    metadata.map_or(0, |m| m.len()),
+                ));
+            }
+        }
+        files.sort();
+        files
+    }
+}
+
+/// Logs the outcome of a reload.
+pub fn report_reload(trigger: &str, result: &Result<Arc<CampaignRegistry>, String>) {
+    match result {
+        Ok(registry) => {
+            for campaign in registry.iter() {
+                info!(
+                    "Reloaded campaign {} ({trigger}): root 0x{}, {} leaves",
+                    campaign.id,
+                    hex::encode(campaign.root),
+                    campaign.leaf_count
+                );
+            }
+        }
+        Err(e) => error!("Reload ({trigger}) failed, still serving the previous data: {e}"),
+    }
+}
+
+/// Reloads the campaigns whenever the process receives `SIGHUP`.
+#[cfg(unix)]
+pub fn spawn_sighup_handler(store: Arc<CampaignStore>) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    tokio::spawn(async move {
+        let mut hangup = match signal(SignalKind::hangup()) {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!("Cannot listen for SIGHUP: {e}");
+                return;
+            }
+        };
+        while hangup.recv().await.is_some() {
+            report_reload("SIGHUP", &store.reload().await);
+        }
+    });
+}
+
+#[cfg(not(unix))]
+pub fn spawn_sighup_handler(_store: Arc<CampaignStore>) {}
+
+/// Polls the campaign directories and reloads after files change.
+///
+/// A change is only acted on once two consecutive polls agree, so a database
+/// that is still being written is not picked up half-way.
+pub fn spawn_watcher(store: Arc<CampaignStore>, interval: Duration) {
+    tokio::spawn(async move {
+        let snapshot = Arc::clone(&store);
+        let mut loaded = metrics::spawn_blocking(move || snapshot.fingerprint())
+            .await
+            .unwrap_or_default();
+        let mut pending: Option<Fingerprint> = None;
+        loop {
+            tokio::time::sleep(interval).await;
+            let snapshot = Arc::clone(&store);
+            let Ok(current) = metrics::spawn_blocking(move || snapshot.fingerprint()).await else {
+                continue;
+            };
+            if current == loaded {
+                pending = None;
+                continue;
+            }
+            if pending.as_ref() != Some(&current) {
+                pending = Some(current);
+                continue;
+            }
+            report_reload("watcher", &store.reload().await);
+            loaded = current;
+            pending = None;
+        }
+    });
+}
@@ -0,0 +1,131 @@
+//! Logging, tracing spans and request IDs.
+//!
+//! Every request gets an `x-request-id` (kept when the client sends one,
+//! otherwise a new UUID) that is echoed in the response and recorded on the
+//! request span. Blocking work and the library's `build_proof` and
+//! `find_address_index` spans nest under that span.
+//!
+//! Logs go to stdout, filtered by `RUST_LOG` (default `info`). Set
+//! `LOG_FORMAT=json` for one JSON object per line. When
+//! `OTEL_EXPORTER_OTLP_ENDPOINT` is set, spans are also exported over OTLP/HTTP
+//! to that collector (for example `http://localhost:4318`). The `[logging]`
+//! section of the config file sets the same options.
+
+use axum::extract::MatchedPath;
+use axum::http::{HeaderName, Request};
+use axum::Router;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tower_http::trace::TraceLayer;
+use tracing::{info_span, Span};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+const SERVICE_NAME: &str = "merkle_api";
+
+/// Log filter, format and OTLP collector, resolved by [`crate::config`].
+#[derive(Debug, Clone)]
+pub struct LogSettings {
+    /// `RUST_LOG` syntax, for example `info,merkle_api=debug`.
+    pub filter: String,
+    pub json: bool,
+    pub otlp_endpoint: Option<String>,
+}
+
+/// Keeps the OTLP exporter alive; call [`Telemetry::shutdown`] before exiting
+/// so buffered spans are flushed.
+pub struct Telemetry {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl Telemetry {
+    /// Installs the global subscriber.
+    pub fn init(settings: &LogSettings) -> Result<Self, String> {
+        let fmt = if settings.json {
+            tracing_subscriber::fmt::layer()
+                .json()
+                .with_current_span(true)
+                .with_span_list(false)
+                .boxed()
+        } else {
+            tracing_subscriber::fmt::layer().boxed()
+        };
+
+        let provider = settings
+            .otlp_endpoint
+            .as_deref()
+            .map(otlp_provider)
+            .transpose()?;
+        let otel = provider.as_ref().map(|provider| {
+            tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME))
+        });
+
+        tracing_subscriber::registry()
+            .with(
+                EnvFilter::try_new(&settings.filter)
+                    .map_err(|e| format!("Invalid log filter '{}': {e}", settings.filter))?,
+            )
+            .with(fmt)
+            .with(otel)
+            .try_init()
+            .map_err(|e| format!("Failed to install the tracing subscriber: {e}"))?;
+        Ok(Telemetry { provider })
+    }
+
+    /// Flushes and stops the OTLP exporter, if any.
+    pub async fn shutdown(self) {
+        let Some(provider) = self.provider else {
+            return;
+        };
+        let result = tokio::task::spawn_blocking(move || provider.shutdown()).await;
+        if let Ok(Err(e)) = result {
+            tracing::warn!("Failed to flush OTLP spans: {e}");
+        }
+    }
+}
+
+fn otlp_provider(endpoint: &str) -> Result<SdkTracerProvider, String> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+        .build()
+        .map_err(|e| format!("Failed to create the OTLP exporter for {endpoint}: {e}"))?;
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(SERVICE_NAME).build())
+        .build())
+}
+
+/// Assigns request IDs and wraps every request in a span carrying it.
+pub fn with_request_tracing<S>(router: Router<S>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router
+        .layer(PropagateRequestIdLayer::new(REQUEST_ID_HEADER))
+        .layer(TraceLayer::new_for_http().make_span_with(request_span))
+        .layer(SetRequestIdLayer::new(REQUEST_ID_HEADER, MakeRequestUuid))
+}
+
+fn request_span<B>(request: &Request<B>) -> Span {
+    let request_id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(|| request.uri().path(), MatchedPath::as_str);
+    info_span!(
+        "request",
+        method = %request.method(),
+        route,
+        request_id,
+    )
+}
@@ -0,0 +1,189 @@
+//! Native TLS for the API port.
+//!
+//! `--tls-cert` and `--tls-key` name PEM files holding the certificate chain
+//! and its private key. With `--tls-client-ca`, every client must present a
+//! certificate issued by one of the CAs in that PEM file (mutual TLS). The
+//! files are read again on `SIGHUP`; connections that are already open keep
+//! their session, and a failed reload keeps the previous certificate.
+//!
+//! ALPN offers `h2` and `http/1.1`, so gRPC works over TLS as well.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use axum_server::tls_rustls::RustlsConfig;
+use rustls::crypto::ring;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig};
+use tracing::{error, info};
+
+use crate::metrics;
+
+#[derive(Debug, Clone)]
+pub struct TlsSettings {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+    pub client_ca: Option<PathBuf>,
+}
+
+impl TlsSettings {
+    /// Builds the rustls configuration from the PEM files. Performs blocking I/O.
+    pub fn server_config(&self) -> Result<ServerConfig, String> {
+        let provider = Arc::new(ring::default_provider());
+        let builder = ServerConfig::builder_with_provider(Arc::clone(&provider))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| format!("Invalid TLS protocol versions: {e}"))?;
+        let builder = match &self.client_ca {
+            Some(path) => {
+                let mut roots = RootCertStore::empty();
+                for cert in read_certs(path)? {
+                    roots
+                        .add(cert)
+                        .map_err(|e| format!("Invalid client CA in {}: {e}", path.display()))?;
+                }
+                let verifier =
+                    WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                        .build()
+                        .map_err(|e| format!("Invalid client CA in {}: {e}", path.display()))?;
+                builder.with_client_cert_verifier(verifier)
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let mut config = builder
+            .with_single_cert(read_certs(&self.cert)?, read_key(&self.key)?)
+            .map_err(|e| {
+                format!(
+                    "Certificate {} does not match key {}: {e}",
+                    self.cert.display(),
+                    self.key.display()
+                )
+            })?;
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(config)
+    }
+
+    /// Performs the initial load. Performs blocking I/O.
+    pub fn load(&self) -> Result<RustlsConfig, String> {
+        Ok(RustlsConfig::from_config(Arc::new(self.server_config()?)))
+    }
+
+    /// Reads the PEM files again and swaps them in when they are valid.
+    pub async fn reload(&self, config: &RustlsConfig) -> Result<(), String> {
+        let settings = self.clone();
+        let server_config = metrics::spawn_blocking(move || settings.server_config())
+            .await
+            .map_err(|e| format!("spawn_blocking error: {e}"))??;
+        config.reload_from_config(Arc::new(server_config));
+        Ok(())
+    }
+}
+
+fn read_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {e}", path.display()))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to parse {}: {e}", path.display()))?;
+    if certs.is_empty() {
+        return Err(format!("No certificates found in {}", path.display()));
+    }
+    Ok(certs)
+}
+
+fn read_key(path: &Path) -> Result<PrivateKeyDer<'static>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {e}", path.display()))?;
+    rustls_pemfile::private_key(&mut BufReader::new(file))
+        .map_err(|e| format!("Failed to parse {}: {e}", path.display()))?
+        .ok_or_else(|| format!("No private key found in {}", path.display()))
+}
+
+/// Reloads the certificate whenever the process receives `SIGHUP`.
+#[cfg(unix)]
+pub fn spawn_sighup_handler(settings: TlsSettings, config: RustlsConfig) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    tokio::spawn(async move {
+        let Ok(mut hangup) = signal(SignalKind::hangup()) else {
+            return;
+        };
+        while hangup.recv().await.is_some() {
+            match settings.reload(&config).await {
+                Ok(()) => info!("Reloaded TLS certificate {}", settings.cert.display()),
+                Err(e) => error!("TLS reload failed, still serving the previous certificate: {e}"),
+            }
+        }
+    });
+}
+
+#[cfg(not(unix))]
+pub fn spawn_sighup_handler(_settings: TlsSettings, _config: RustlsConfig) {}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_self_signed(dir: &std::path::Path, name: &str) -> (PathBuf, PathBuf) {
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let cert = dir.join(format!("{name}.crt"));
+        let key = dir.join(format!("{name}.key"));
+        std::fs::write(&cert, certified.cert.pem()).unwrap();
+        std::fs::write(&key, certified.key_pair.serialize_pem()).unwrap();
+        (cert, key)
+    }
+
+    #[tokio::test]
+    async fn test_tls_settings() {
+        let temp = TempDir::new().unwrap();
+        let (cert, key) = write_self_signed(temp.path(), "server");
+        let (client_ca, other_key) = write_self_signed(temp.path(), "client");
+
+        let mut settings = TlsSettings {
+            cert: cert.clone(),
+            key: key.clone(),
+            client_ca: None,
+        };
+        let config = settings.server_config().unwrap();
+        assert_eq!(
+            config.alpn_protocols,
+            [b"h2".to_vec(), b"http/1.1".to_vec()]
+        );
+
+        settings.client_ca = Some(client_ca.clone());
+        assert!(settings.server_config().is_ok());
+        settings.client_ca = Some(key.clone());
+        assert!(settings
+            .server_config()
+            .unwrap_err()
+            .contains("No certificates"));
+
+        let mismatched = TlsSettings {
+            cert: cert.clone(),
+            key: other_key.clone(),
+            client_ca: None,
+        };
+        assert!(mismatched
+            .server_config()
+            .unwrap_err()
+            .contains("does not match"));
+
+        let settings = TlsSettings {
+            cert,
+            key,
+            client_ca: None,
+        };
+        let loaded = settings.load().unwrap();
+        let before = loaded.get_inner();
+        write_self_signed(temp.path(), "server");
+        settings.reload(&loaded).await.unwrap();
+        assert!(!Arc::ptr_eq(&before, &loaded.get_inner()));
+
+        std::fs::write(&settings.key, "not a key").unwrap();
+        let current = loaded.get_inner();
+        assert!(settings.reload(&loaded).await.is_err());
+        assert!(Arc::ptr_eq(&current, &loaded.get_inner()));
+    }
+}
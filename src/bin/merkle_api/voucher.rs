@@ -0,0 +1,169 @@
+//! EIP-712 claim vouchers issued by the API.
+//!
+//! Vouchers are enabled when `VOUCHER_SIGNING_KEY` is set. They are bound to
+//! the domain given by `VOUCHER_DOMAIN_NAME`, `VOUCHER_DOMAIN_VERSION`,
+//! `VOUCHER_CHAIN_ID` and `VOUCHER_CONTRACT`, grant `VOUCHER_AMOUNT` (in base
+//! units) and expire `VOUCHER_TTL_SECONDS` after issuance. Every voucher of a
+//! campaign carries the same `VOUCHER_NONCE`, so the contract can mark
+//! `(account, nonce)` as used and re-issued vouchers cannot be claimed twice.
+
+use std::env;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use k256::SecretKey;
+use merklebuilder::eip712::{sign_voucher, Eip712Domain, Voucher};
+use merklebuilder::merkle::parse_address;
+use merklebuilder::signing::{parse_secret_key, SignatureError};
+use merklebuilder::{public_key_address, to_checksum_address};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+const DEFAULT_DOMAIN_NAME: &str = "Demo Airdrop";
+const DEFAULT_DOMAIN_VERSION: &str = "1";
+const DEFAULT_CHAIN_ID: u64 = 11_155_111;
+const DEFAULT_AMOUNT: u128 = 1_000_000_000_000_000_000;
+const DEFAULT_TTL_SECONDS: u64 = 3600;
+
+pub struct VoucherIssuer {
+    secret_key: SecretKey,
+    signer: String,
+    domain: Eip712Domain,
+    amount: u128,
+    ttl_seconds: u64,
+    nonce: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct VoucherDomain {
+    name: String,
+    version: String,
+    #[serde(rename = "chainId")]
+    chain_id: u64,
+    #[serde(rename = "verifyingContract")]
+    verifying_contract: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct VoucherMessage {
+    account: String,
+    /// Decimal string, since amounts routinely exceed JavaScript's safe integers.
+    amount: String,
+    deadline: u64,
+    nonce: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct VoucherResponse {
+    domain: VoucherDomain,
+    primary_type: &'static str,
+    voucher: VoucherMessage,
+    digest: String,
+    signature: String,
+    signer: String,
+}
+
+impl VoucherIssuer {
+    /// Reads the voucher configuration from the environment.
+    ///
+    /// Returns `Ok(None)` when `VOUCHER_SIGNING_KEY` is unset.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let Ok(raw_key) = env::var("VOUCHER_SIGNING_KEY") else {
+            return Ok(None);
+        };
+        let secret_key = parse_secret_key(&raw_key).map_err(|e| e.to_string())?;
+        let contract = env::var("VOUCHER_CONTRACT")
+            .map_err(|_| "VOUCHER_CONTRACT is required when vouchers are enabled".to_string())?;
+        let verifying_contract = parse_address(&contract)
+            .map_err(|e| format!("Invalid VOUCHER_CONTRACT '{contract}': {e}"))?;
+
+        let domain = Eip712Domain {
+            name: env::var("VOUCHER_DOMAIN_NAME")
+                .unwrap_or_else(|_| DEFAULT_DOMAIN_NAME.to_string()),
+            version: env::var("VOUCHER_DOMAIN_VERSION")
+                .unwrap_or_else(|_| DEFAULT_DOMAIN_VERSION.to_string()),
+            chain_id: env_number("VOUCHER_CHAIN_ID", DEFAULT_CHAIN_ID)?,
+            verifying_contract,
+        };
+        Self::new(
+            secret_key,
+            domain,
+            env_number("VOUCHER_AMOUNT", DEFAULT_AMOUNT)?,
+            env_number("VOUCHER_TTL_SECONDS", DEFAULT_TTL_SECONDS)?,
+            env_number("VOUCHER_NONCE", 0)?,
+        )
+        .map(Some)
+    }
+
+    pub fn new(
+        secret_key: SecretKey,
+        domain: Eip712Domain,
+        amount: u128,
+        ttl_seconds: u64,
+        nonce: u64,
+    ) -> Result<Self, String> {
+        let signer = public_key_address(&secret_key.public_key()).map_err(|e| e.to_string())?;
+        Ok(VoucherIssuer {
+            secret_key,
+            signer: to_checksum_address(&signer),
+            domain,
+            amount,
+            ttl_seconds,
+            nonce,
+        })
+    }
+
+    pub fn signer(&self) -> &str {
+        &self.signer
+    }
+
+    pub fn domain(&self) -> &Eip712Domain {
+        &self.domain
+    }
+
+    /// Signs a voucher for `account` that expires `ttl_seconds` from now.
+    pub fn issue(&self, account: [u8; 20]) -> Result<VoucherResponse, SignatureError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        self.issue_at(account, now)
+    }
+
+    pub fn issue_at(&self, account: [u8; 20], now: u64) -> Result<VoucherResponse, SignatureError> {
+        let voucher = Voucher {
+            account,
+            amount: self.amount,
+            deadline: now.saturating_add(self.ttl_seconds),
+            nonce: self.nonce,
+        };
+        let signature = sign_voucher(&self.secret_key, &self.domain, &voucher)?;
+        Ok(VoucherResponse {
+            domain: VoucherDomain {
+                name: self.domain.name.clone(),
+                version: self.domain.version.clone(),
+                chain_id: self.domain.chain_id,
+                verifying_contract: to_checksum_address(&self.domain.verifying_contract),
+            },
+            primary_type: "Voucher",
+            voucher: VoucherMessage {
+                account: to_checksum_address(&voucher.account),
+                amount: voucher.amount.to_string(),
+                deadline: voucher.deadline,
+                nonce: voucher.nonce,
+            },
+            digest: format!("0x{}", hex::encode(voucher.digest(&self.domain))),
+            signature: format!("0x{}", hex::encode(signature)),
+            signer: self.signer.clone(),
+        })
+    }
+}
+
+fn env_number<T: std::str::FromStr>(name: &str, default: T) -> Result<T, String> {
+    match env::var(name) {
+        Ok(raw) => raw
+            .trim()
+            .parse()
+            .map_err(|_| format!("{name} must be a non-negative integer, got '{raw}'")),
+        Err(_) => Ok(default),
+    }
+}
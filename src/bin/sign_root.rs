@@ -0,0 +1,133 @@
+//! CLI tool to sign and verify the root of a Merkle database.
+//!
+//! Usage:
+//! - `sign_root <db_dir>` signs the root, leaf count and `manifest.json` hash
+//!   with EIP-191 and writes `signature.json` into the database directory.
+//! - `sign_root --verify <db_dir> [--signer <address>]` checks `signature.json`
+//!   against the database and optionally pins the expected signer.
+//!
+//! The signing key is read from the file named by `--key-file`, or from the
+//! `MERKLE_SIGNING_KEY` environment variable (hex, with or without `0x`).
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use merklebuilder::merkle::parse_address;
+use merklebuilder::signing::{parse_secret_key, SignedRoot, SIGNATURE_FILE};
+use merklebuilder::to_checksum_address;
+
+const KEY_ENV: &str = "MERKLE_SIGNING_KEY";
+const USAGE: &str = "Usage: sign_root <db_dir> [--key-file <path>]\n       sign_root --verify <db_dir> [--signer <address>]";
+
+enum Command {
+    Sign {
+        db_dir: String,
+        key_file: Option<String>,
+    },
+    Verify {
+        db_dir: String,
+        signer: Option<String>,
+    },
+}
+
+fn main() {
+    let command = match parse_args(env::args().skip(1).collect()) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("{e}");
+            eprintln!("{USAGE}");
+            process::exit(1);
+        }
+    };
+
+    let result = match command {
+        Command::Sign { db_dir, key_file } => sign(Path::new(&db_dir), key_file.as_deref()),
+        Command::Verify { db_dir, signer } => verify(Path::new(&db_dir), signer.as_deref()),
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {e}");
+        process::exit(1);
+    }
+}
+
+fn sign(db_dir: &Path, key_file: Option<&str>) -> Result<(), String> {
+    let raw_key = match key_file {
+        Some(path) => {
+            fs::read_to_string(path).map_err(|e| format!("Failed to read key file {path}: {e}"))?
+        }
+        None => env::var(KEY_ENV)
+            .map_err(|_| format!("Pass --key-file or set {KEY_ENV} to a hex secret key"))?,
+    };
+    let secret_key = parse_secret_key(&raw_key).map_err(|e| e.to_string())?;
+    let signed = SignedRoot::sign_database(db_dir, &secret_key).map_err(|e| e.to_string())?;
+    signed.write(db_dir).map_err(|e| e.to_string())?;
+
+    println!("Root: {}", signed.root);
+    println!("Leaves: {}", signed.leaf_count);
+    if let Some(hash) = &signed.manifest_hash {
+        println!("Manifest: {hash}");
+    }
+    println!("Signer: {}", signed.signer);
+    println!("Signature: {}", signed.signature);
+    println!("Wrote {}", db_dir.join(SIGNATURE_FILE).display());
+    Ok(())
+}
+
+fn verify(db_dir: &Path, expected: Option<&str>) -> Result<(), String> {
+    let signed = SignedRoot::read(db_dir)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("{} not found in {}", SIGNATURE_FILE, db_dir.display()))?;
+    let recovered = signed.verify_database(db_dir).map_err(|e| e.to_string())?;
+    if let Some(expected) = expected {
+        let expected = parse_address(expected).map_err(|e| e.to_string())?;
+        if expected != recovered {
+            return Err(format!(
+                "Signed by {}, expected {}",
+                to_checksum_address(&recovered),
+                to_checksum_address(&expected)
+            ));
+        }
+    }
+    println!("Root: {}", signed.root);
+    println!("Signer: {}", to_checksum_address(&recovered));
+    println!("Signature OK");
+    Ok(())
+}
+
+fn parse_args(args: Vec<String>) -> Result<Command, String> {
+    let mut args = args.into_iter();
+    let mut verify = false;
+    let mut db_dir = None;
+    let mut key_file = None;
+    let mut signer = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--verify" => verify = true,
+            "--key-file" | "-k" => {
+                key_file = Some(args.next().ok_or("Missing value for --key-file")?);
+            }
+            "--signer" => {
+                signer = Some(args.next().ok_or("Missing value for --signer")?);
+            }
+            flag if flag.starts_with('-') => return Err(format!("Unknown option: {flag}")),
+            _ if db_dir.is_some() => return Err("Too many arguments provided".to_string()),
+            _ => db_dir = Some(arg),
+        }
+    }
+
+    let db_dir = db_dir.ok_or("Missing required argument: db_dir")?;
+    if verify {
+        if key_file.is_some() {
+            return Err("--key-file cannot be used with --verify".to_string());
+        }
+        Ok(Command::Verify { db_dir, signer })
+    } else {
+        if signer.is_some() {
+            return Err("--signer requires --verify".to_string());
+        }
+        Ok(Command::Sign { db_dir, key_file })
+    }
+}
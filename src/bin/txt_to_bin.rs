@@ -1,27 +1,104 @@
-//! Convert a text file of Ethereum addresses to binary Merkle tree format.
+//! Convert a list of Ethereum addresses to binary Merkle tree format.
 //!
-//! Usage: `txt_to_bin <input.txt> [output_dir]`
+//! Usage: `txt_to_bin <input> [output_dir] [options]`
 //!
-//! Reads addresses from a text file (one per line), sorts them, removes duplicates,
-//! and builds a Merkle tree with layers stored as binary files.
+//! Reads addresses from a text file (one per line), a CSV file with a header
+//! row, a JSON array or NDJSON, sorts them, removes duplicates, and builds a
+//! Merkle tree with layers stored as binary files. An existing Merkle database
+//! directory can be used wherever an input file is expected.
+//!
+//! # Options
+//! - `--format <auto|text|csv|json|ndjson>` - Input format (default: detected
+//!   from the file extension and contents)
+//! - `--field <selector>` / `--column <selector>` - CSV column (header name or
+//!   0-based index) or JSON field path (e.g. `holder.address`) holding the
+//!   address (default: `address`)
+//! - `--exclude <file>` - Denylist of addresses to keep out of the tree
+//!   (repeatable; any supported input format)
+//! - `--allow-reserved` - Do not exclude the zero address and precompiles
+//!   (0x01-0x0a)
+//! - `--report <file.json>` - Write a JSON ingestion report listing every
+//!   rejected line, every duplicate with its line numbers, excluded addresses,
+//!   counts, stage timings and the resulting root
+//! - `--union <input>` / `--intersect <input>` / `--subtract <input>` - Combine
+//!   the set built so far with another list or database (repeatable, applied
+//!   left to right)
+//! - `--lenient` - Skip lines that do not hold a valid address instead of
+//!   aborting; skipped lines are written to `rejects.csv` in the output
+//!   directory
+//! - `--rejects <file>` - Write skipped lines here instead (requires `--lenient`)
+//! - `--max-reject-ratio <ratio>` - Fail a lenient build when more than this
+//!   fraction of records is rejected (default: 0.01; requires `--lenient`)
+//!
+//! Excluded addresses are listed with their source in `excluded.csv` next to
+//! the layer files. Every build also writes `manifest.json` recording the
+//! input hashes, tool version, tree parameters, counts, per-file hashes and
+//! the root.
+//!
+//! `txt_to_bin --verify <db_dir>` rebuilds a database from the inputs named in
+//! its manifest and confirms the result is byte-identical.
 
 use std::env;
-use std::fs::{create_dir_all, File};
-use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::fs::{create_dir_all, remove_dir_all, remove_file, File};
+use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
 use std::process;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
-use merklebuilder::merkle::{hash_leaf, hash_pair, parse_address};
+use merklebuilder::exclude::{ExcludedAddress, Exclusions};
+use merklebuilder::input::{detect_format, read_records, InputError, InputFormat};
+use merklebuilder::manifest::{
+    compare_digests, digest_database, hash_file, Manifest, ManifestExclusion, ManifestInput,
+    ToolInfo, TreeParameters, HASH_ALGORITHM, MANIFEST_VERSION,
+};
+use merklebuilder::merkle::{
+    available_layers, hash_leaf, hash_pair, parse_address, to_hex20, MerkleError,
+};
 use merklebuilder::progress::{build_progress, progress_update_interval};
+use merklebuilder::report::{
+    collect_duplicates, duration_ms, ExcludedEntry, IngestReport, InputSummary, LineEntry,
+    RejectedLine,
+};
+use merklebuilder::setops::{read_address_set, SetOp};
 use merklebuilder::{ADDRESS_SIZE, HASH_SIZE, MAX_ADDRESSES};
 
+const DEFAULT_MAX_REJECT_RATIO: f64 = 0.01;
+const MERKLEDB_FORMAT: &str = "merkledb";
+
+const USAGE: &str = "--verify <db_dir> | <input> [output_dir (default: merkledb)] \
+[--format <auto|text|csv|json|ndjson>] [--field <column|field>] \
+[--union|--intersect|--subtract <input>]... \
+[--exclude <file>]... [--allow-reserved] [--report <file.json>] \
+[--lenient [--rejects <file>] [--max-reject-ratio <ratio>]]";
+
+#[derive(Debug, Clone)]
+struct Config {
+    input: String,
+    output_dir: String,
+    format: Option<InputFormat>,
+    selector: Option<String>,
+    exclude: Vec<String>,
+    allow_reserved: bool,
+    report: Option<String>,
+    lenient: bool,
+    rejects: Option<String>,
+    max_reject_ratio: Option<f64>,
+    operations: Vec<(SetOp, String)>,
+}
+
+#[derive(Debug, Clone)]
+enum Command {
+    Build(Config),
+    Verify(String),
+}
+
 fn main() {
-    let (input, output_dir) = match parse_args() {
+    let command = match parse_args() {
         Ok(v) => v,
         Err(e) => {
             eprintln!("{e}");
             eprintln!(
-                "Usage: {} <input_txt> [output_dir (default: merkledb)]",
+                "Usage: {} {USAGE}",
                 env::args()
                     .next()
                     .unwrap_or_else(|| "txt_to_bin".to_string())
@@ -30,34 +107,512 @@ fn main() {
         }
     };
 
-    if let Err(e) = convert_file(&input, &output_dir) {
+    let result = match command {
+        Command::Build(config) => convert_file(&config),
+        Command::Verify(db_dir) => verify_build(Path::new(&db_dir)),
+    };
+    if let Err(e) = result {
         eprintln!("Error: {e}");
         process::exit(1);
     }
 }
 
-fn parse_args() -> Result<(String, String), String> {
-    let mut args = env::args().skip(1);
-    let input = args
+fn parse_args() -> Result<Command, String> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "--verify") {
+        return match args.as_slice() {
+            [_, db_dir] => Ok(Command::Verify(db_dir.clone())),
+            [_] => Err("--verify requires a database directory".to_string()),
+            _ => Err("Too many arguments provided".to_string()),
+        };
+    }
+    parse_args_from(args).map(Command::Build)
+}
+
+fn parse_args_from(args: impl IntoIterator<Item = String>) -> Result<Config, String> {
+    let mut args = args.into_iter();
+    let mut positional = Vec::new();
+    let mut format = None;
+    let mut selector = None;
+    let mut exclude = Vec::new();
+    let mut allow_reserved = false;
+    let mut report = None;
+    let mut lenient = false;
+    let mut rejects = None;
+    let mut max_reject_ratio = None;
+    let mut operations = Vec::new();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" | "-f" => {
+                let raw = args.next().ok_or_else(|| {
+                    "--format requires one of auto, text, csv, json, ndjson".to_string()
+                })?;
+                format = if raw == "auto" {
+                    None
+                } else {
+                    Some(
+                        raw.parse::<InputFormat>()
+                            .map_err(|e: InputError| e.to_string())?,
+                    )
+                };
+            }
+            "--field" | "--column" => {
+                let raw = args.next().ok_or_else(|| {
+                    format!("{arg} requires a column name, column index or field path")
+                })?;
+                selector = Some(raw);
+            }
+            "--exclude" | "-x" => {
+                let raw = args
+                    .next()
+                    .ok_or_else(|| "--exclude requires a file path".to_string())?;
+                exclude.push(raw);
+            }
+            "--allow-reserved" => allow_reserved = true,
+            "--report" | "-r" => {
+                let raw = args
+                    .next()
+                    .ok_or_else(|| "--report requires a file path".to_string())?;
+                report = Some(raw);
+            }
+            "--union" | "--intersect" | "--subtract" => {
+                let raw = args
+                    .next()
+                    .ok_or_else(|| format!("{arg} requires an input file or database directory"))?;
+                operations.push((arg.trim_start_matches("--").parse::<SetOp>()?, raw));
+            }
+            "--lenient" => lenient = true,
+            "--rejects" => {
+                let raw = args
+                    .next()
+                    .ok_or_else(|| "--rejects requires a file path".to_string())?;
+                rejects = Some(raw);
+            }
+            "--max-reject-ratio" => {
+                let raw = args.next().ok_or_else(|| {
+                    "--max-reject-ratio requires a value between 0 and 1".to_string()
+                })?;
+                let ratio: f64 = raw
+                    .parse()
+                    .map_err(|_| format!("Invalid reject ratio '{raw}'"))?;
+                if !(0.0..=1.0).contains(&ratio) {
+                    return Err(format!("Reject ratio must be between 0 and 1, got {raw}"));
+                }
+                max_reject_ratio = Some(ratio);
+            }
+            other if other.starts_with("--") => {
+                return Err(format!("Unknown argument: {other}"));
+            }
+            _ => positional.push(arg),
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    let input = positional
         .next()
         .ok_or_else(|| "Missing required argument: input file".to_string())?;
-    let output_dir = args.next().unwrap_or_else(|| "merkledb".to_string());
+    let output_dir = positional.next().unwrap_or_else(|| "merkledb".to_string());
 
-    if args.next().is_some() {
+    if positional.next().is_some() {
         return Err("Too many arguments provided".to_string());
     }
 
-    Ok((input, output_dir))
+    if !lenient && (rejects.is_some() || max_reject_ratio.is_some()) {
+        return Err("--rejects and --max-reject-ratio require --lenient".to_string());
+    }
+
+    Ok(Config {
+        input,
+        output_dir,
+        format,
+        selector,
+        exclude,
+        allow_reserved,
+        report,
+        lenient,
+        rejects,
+        max_reject_ratio,
+        operations,
+    })
+}
+
+fn convert_file(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let mut report = IngestReport::new(&config.input, &config.output_dir);
+    let result = build_database(config, &mut report);
+    if let Some(report_path) = &config.report {
+        if let Err(e) = &result {
+            report.fail(e.to_string());
+        }
+        report.write_json(Path::new(report_path))?;
+        println!("Wrote ingestion report to {report_path}");
+    }
+    result
+}
+
+fn build_database(
+    config: &Config,
+    report: &mut IngestReport,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let started = Instant::now();
+    let exclusions = load_exclusions(config)?;
+    let mut addresses = load_input(&config.input, None, config, report)?;
+    let mut operands = Vec::with_capacity(config.operations.len());
+    for (op, input) in &config.operations {
+        operands.push((*op, load_input(input, Some(*op), config, report)?));
+    }
+
+    if config.lenient {
+        skip_rejects(config, report)?;
+    } else if let Some(first) = report.rejected.first() {
+        let mut message = format!("Line {}: {}", first.line, first.reason);
+        if !config.operations.is_empty() {
+            message = format!("{}: {message}", first.input);
+        }
+        if report.rejected.len() > 1 {
+            message = format!(
+                "{message} ({} more invalid lines)",
+                report.rejected.len() - 1
+            );
+        }
+        return Err(message.into());
+    }
+
+    if addresses.is_empty() {
+        return Err("Input file contained no addresses".into());
+    }
+
+    let stage = Instant::now();
+    for (idx, (op, operand)) in operands.iter().enumerate() {
+        addresses = op.apply(&addresses, operand);
+        report.inputs[idx + 1].result = addresses.len();
+    }
+    if addresses.len() > MAX_ADDRESSES {
+        return Err(format!(
+            "Set operations produced {} addresses, which exceeds the maximum of {MAX_ADDRESSES}",
+            addresses.len()
+        )
+        .into());
+    }
+    if addresses.is_empty() {
+        return Err("Set operations left no addresses to build".into());
+    }
+
+    let excluded = exclusions.apply(&mut addresses);
+    report.counts.excluded = excluded.len();
+    report.excluded = excluded
+        .iter()
+        .map(|e| ExcludedEntry {
+            address: to_hex20(&e.address),
+            source: e.source.clone(),
+        })
+        .collect();
+    report.timings.sort_ms += duration_ms(stage.elapsed());
+    if addresses.is_empty() {
+        return Err(format!(
+            "All {} unique addresses were excluded; nothing left to build",
+            excluded.len()
+        )
+        .into());
+    }
+
+    let stage = Instant::now();
+    let leaves: Vec<[u8; HASH_SIZE]> = addresses.iter().map(hash_leaf).collect();
+    let layers = build_layers(leaves)?;
+    let root_hex = layers
+        .last()
+        .and_then(|l| l.first())
+        .map(hex::encode)
+        .ok_or("Failed to get root hash")?;
+    report.timings.build_ms = duration_ms(stage.elapsed());
+
+    let stage = Instant::now();
+    let out_dir = PathBuf::from(&config.output_dir);
+    create_dir_all(&out_dir)?;
+    if !exclusions.sources().is_empty() {
+        write_excluded(&out_dir.join("excluded.csv"), &excluded)?;
+    }
+
+    write_addresses(&out_dir.join("addresses.bin"), &addresses)?;
+    write_layers(&out_dir, &layers)?;
+    report.counts.leaves = addresses.len();
+    report.layers = layers.len();
+    report.root = Some(format!("0x{root_hex}"));
+    build_manifest(config, report, &out_dir)?.write(&out_dir)?;
+    report.timings.write_ms = duration_ms(stage.elapsed());
+    report.timings.total_ms = duration_ms(started.elapsed());
+
+    print_summary(report, &exclusions, &out_dir);
+    Ok(())
+}
+
+/// Records the inputs, parameters and file hashes of a finished build.
+///
+/// # Errors
+/// Returns an error if an input or output file cannot be hashed.
+fn build_manifest(
+    config: &Config,
+    report: &IngestReport,
+    out_dir: &Path,
+) -> Result<Manifest, Box<dyn std::error::Error>> {
+    let inputs = report
+        .inputs
+        .iter()
+        .map(|input| {
+            let (hash, size) = hash_file(&input_data_path(&input.input))?;
+            Ok(ManifestInput {
+                path: input.input.clone(),
+                format: input.format.clone(),
+                operation: input.operation.clone(),
+                size,
+                hash,
+            })
+        })
+        .collect::<Result<Vec<_>, MerkleError>>()?;
+    let exclusions = config
+        .exclude
+        .iter()
+        .map(|path| {
+            let (hash, size) = hash_file(Path::new(path))?;
+            Ok(ManifestExclusion {
+                path: path.clone(),
+                size,
+                hash,
+            })
+        })
+        .collect::<Result<Vec<_>, MerkleError>>()?;
+    let created_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+
+    Ok(Manifest {
+        manifest_version: MANIFEST_VERSION,
+        tool: ToolInfo::current(),
+        created_at,
+        hash_algorithm: HASH_ALGORITHM.to_string(),
+        inputs,
+        exclusions,
+        parameters: TreeParameters::new(
+            config.format.map(|f| f.to_string()),
+            config.selector.clone(),
+            config.allow_reserved,
+            config.lenient,
+            config.max_reject_ratio,
+        ),
+        leaf_count: report.counts.leaves,
+        duplicates_removed: report.counts.duplicates_removed,
+        excluded: report.counts.excluded,
+        rejected: report.counts.rejected,
+        layer_count: report.layers,
+        root: report.root.clone().unwrap_or_default(),
+        files: digest_database(out_dir, report.layers)?,
+    })
+}
+
+/// Returns the file holding an input's data: the list itself, or the
+/// `addresses.bin` of a database directory.
+fn input_data_path(input: &str) -> PathBuf {
+    let path = Path::new(input);
+    if path.is_dir() {
+        path.join("addresses.bin")
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// Rebuilds a database from the inputs recorded in its manifest and checks
+/// that the result is byte-identical.
+///
+/// # Errors
+/// Returns an error if the database no longer matches its manifest, if an
+/// input changed since the build, or if the rebuild differs.
+fn verify_build(db_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let manifest = Manifest::read(db_dir)?;
+    let current = digest_database(db_dir, manifest.layer_count)?;
+    let mismatches = compare_digests(&manifest.files, &current);
+    if !mismatches.is_empty() {
+        return Err(format!(
+            "{} does not match its manifest:\n  {}",
+            db_dir.display(),
+            mismatches.join("\n  ")
+        )
+        .into());
+    }
+
+    let recorded = manifest
+        .inputs
+        .iter()
+        .map(|input| (input_data_path(&input.path), &input.hash))
+        .chain(
+            manifest
+                .exclusions
+                .iter()
+                .map(|list| (PathBuf::from(&list.path), &list.hash)),
+        );
+    for (path, expected) in recorded {
+        let (hash, _) = hash_file(&path)?;
+        if &hash != expected {
+            return Err(format!(
+                "Input {} changed since the build (expected {expected}, got {hash})",
+                path.display()
+            )
+            .into());
+        }
+    }
+
+    let nonce = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.subsec_nanos());
+    let scratch = env::temp_dir().join(format!("merklebuilder-verify-{}-{nonce}", process::id()));
+    let config = config_from_manifest(&manifest, &scratch)?;
+    println!("Rebuilding {} into {}", db_dir.display(), scratch.display());
+    let rebuilt = build_database(
+        &config,
+        &mut IngestReport::new(&config.input, &config.output_dir),
+    )
+    .and_then(|()| Ok(digest_database(&scratch, manifest.layer_count)?));
+    let _ = remove_dir_all(&scratch);
+    let mismatches = compare_digests(&manifest.files, &rebuilt?);
+    if !mismatches.is_empty() {
+        return Err(format!(
+            "Rebuild of {} is not byte-identical:\n  {}",
+            db_dir.display(),
+            mismatches.join("\n  ")
+        )
+        .into());
+    }
+
+    println!(
+        "Verified {}: rebuild is byte-identical ({} files, root {})",
+        db_dir.display(),
+        manifest.files.len(),
+        manifest.root
+    );
+    Ok(())
+}
+
+/// Reconstructs the build options recorded in a manifest.
+///
+/// # Errors
+/// Returns an error if the manifest has no inputs or names an unknown format
+/// or set operation.
+fn config_from_manifest(manifest: &Manifest, output_dir: &Path) -> Result<Config, String> {
+    let (base, operands) = manifest
+        .inputs
+        .split_first()
+        .ok_or_else(|| "Manifest lists no inputs".to_string())?;
+    let operations = operands
+        .iter()
+        .map(|input| {
+            let op = input
+                .operation
+                .as_deref()
+                .ok_or_else(|| format!("Manifest input {} has no set operation", input.path))?
+                .parse::<SetOp>()?;
+            Ok((op, input.path.clone()))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let params = &manifest.parameters;
+    Ok(Config {
+        input: base.path.clone(),
+        output_dir: output_dir.to_string_lossy().into_owned(),
+        format: params
+            .format
+            .as_deref()
+            .map(str::parse::<InputFormat>)
+            .transpose()
+            .map_err(|e| e.to_string())?,
+        selector: params.selector.clone(),
+        exclude: manifest.exclusions.iter().map(|e| e.path.clone()).collect(),
+        allow_reserved: params.allow_reserved,
+        report: None,
+        lenient: params.lenient,
+        rejects: None,
+        max_reject_ratio: params.max_reject_ratio,
+        operations,
+    })
 }
 
-fn convert_file(input_path: &str, output_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let file = File::open(input_path)?;
-    let metadata = file.metadata()?;
+/// Loads one input as a sorted, deduplicated address list.
+///
+/// Directories are read as existing Merkle databases through their
+/// `addresses.bin`; anything else is parsed as an address list.
+///
+/// # Errors
+/// Returns an error if the input cannot be read or holds too many addresses.
+fn load_input(
+    input: &str,
+    operation: Option<SetOp>,
+    config: &Config,
+    report: &mut IngestReport,
+) -> Result<Vec<[u8; ADDRESS_SIZE]>, Box<dyn std::error::Error>> {
+    let path = Path::new(input);
+    let stage = Instant::now();
+    let (format, addresses) = if path.is_dir() {
+        let addresses = read_address_set(&path.join("addresses.bin"))?;
+        report.timings.read_ms += duration_ms(stage.elapsed());
+        (MERKLEDB_FORMAT.to_string(), addresses)
+    } else {
+        let format = match config.format {
+            Some(format) => format,
+            None => detect_format(path)?,
+        };
+        let mut entries = read_entries(input, format, config.selector.as_deref(), report)?;
+        report.timings.read_ms += duration_ms(stage.elapsed());
+        if entries.len() > MAX_ADDRESSES {
+            return Err(format!(
+                "{input} contains {} addresses, which exceeds the maximum of {MAX_ADDRESSES}",
+                entries.len()
+            )
+            .into());
+        }
+
+        let stage = Instant::now();
+        let valid = entries.len();
+        entries.sort_unstable();
+        report
+            .duplicates
+            .extend(collect_duplicates(input, &entries));
+        let mut addresses: Vec<[u8; ADDRESS_SIZE]> =
+            entries.iter().map(|(addr, _)| *addr).collect();
+        drop(entries);
+        addresses.dedup();
+        report.counts.duplicates_removed += valid - addresses.len();
+        report.timings.sort_ms += duration_ms(stage.elapsed());
+        (format.to_string(), addresses)
+    };
+
+    if operation.is_none() {
+        report.format = Some(format.clone());
+    }
+    report.inputs.push(InputSummary {
+        input: input.to_string(),
+        format,
+        operation: operation.map(|op| op.to_string()),
+        addresses: addresses.len(),
+        result: addresses.len(),
+    });
+    Ok(addresses)
+}
+
+/// Reads every record from the input, keeping valid addresses with their line
+/// numbers and recording the rest in the report.
+///
+/// # Errors
+/// Returns an error if the input cannot be read at all.
+fn read_entries(
+    input: &str,
+    format: InputFormat,
+    selector: Option<&str>,
+    report: &mut IngestReport,
+) -> Result<Vec<LineEntry>, Box<dyn std::error::Error>> {
+    let input_path = Path::new(input);
+    let metadata = std::fs::metadata(input_path)?;
     #[allow(clippy::cast_possible_truncation)]
     let estimated_capacity = (metadata.len() as usize / 43).max(1024);
-    let reader = BufReader::new(file);
+    let records = read_records(input_path, format, selector)?;
 
-    let mut addresses = Vec::with_capacity(estimated_capacity);
+    let mut entries = Vec::with_capacity(estimated_capacity);
     let mut total = 0usize;
 
     let update_every;
@@ -68,70 +623,182 @@ fn convert_file(input_path: &str, output_dir: &str) -> Result<(), Box<dyn std::e
         progress = build_progress(estimated_total as u64);
     }
 
-    for (idx, line) in reader.lines().enumerate() {
-        let line = line?;
-        let trimmed = line.trim();
-        if trimmed.is_empty() {
-            continue;
+    for record in records {
+        match record {
+            Ok(record) => match parse_address(&record.value) {
+                Ok(addr) => entries.push((addr, record.line)),
+                Err(e) => report.rejected.push(RejectedLine {
+                    input: input.to_string(),
+                    line: record.line,
+                    value: Some(record.value),
+                    reason: e.to_string(),
+                }),
+            },
+            Err(InputError::Record { line, message }) => report.rejected.push(RejectedLine {
+                input: input.to_string(),
+                line,
+                value: None,
+                reason: message,
+            }),
+            Err(e) => return Err(e.into()),
         }
-        let addr = parse_address(trimmed).map_err(|e| format!("Line {}: {}", idx + 1, e))?;
-        addresses.push(addr);
         total += 1;
 
         if total.is_multiple_of(update_every) {
             progress.set_position(total as u64);
         }
     }
+    progress.finish_and_clear();
+    report.counts.records += total;
+    report.counts.valid += entries.len();
+    report.counts.rejected = report.rejected.len();
+    Ok(entries)
+}
 
-    if total == 0 {
-        return Err("Input file contained no addresses".into());
+/// Writes the rejected lines of a lenient build and enforces the reject ratio.
+///
+/// # Errors
+/// Returns an error if the rejects file cannot be written or if the share of
+/// rejected records exceeds the configured maximum.
+fn skip_rejects(config: &Config, report: &IngestReport) -> Result<(), Box<dyn std::error::Error>> {
+    let rejects_path = if let Some(path) = &config.rejects {
+        PathBuf::from(path)
+    } else {
+        let out_dir = PathBuf::from(&config.output_dir);
+        create_dir_all(&out_dir)?;
+        out_dir.join("rejects.csv")
+    };
+    write_rejects(&rejects_path, &report.rejected)?;
+    if !report.rejected.is_empty() {
+        println!(
+            "Skipped {} invalid lines (listed in {})",
+            report.rejected.len(),
+            rejects_path.display()
+        );
     }
 
-    if total > MAX_ADDRESSES {
+    let max_ratio = config.max_reject_ratio.unwrap_or(DEFAULT_MAX_REJECT_RATIO);
+    let ratio = reject_ratio(report.counts.rejected, report.counts.records);
+    if ratio > max_ratio {
         return Err(format!(
-            "Input file contains {total} addresses, which exceeds the maximum of {MAX_ADDRESSES}"
+            "Rejected {} of {} records ({:.2}%), above the maximum reject ratio of {:.2}%",
+            report.counts.rejected,
+            report.counts.records,
+            ratio * 100.0,
+            max_ratio * 100.0
         )
         .into());
     }
+    Ok(())
+}
 
-    progress.set_length(total as u64);
-    progress.set_position(total as u64);
-
-    addresses.sort_unstable();
-    let original_count = addresses.len();
-    addresses.dedup();
-    let duplicates_removed = original_count - addresses.len();
-
-    let out_dir = PathBuf::from(output_dir);
-    create_dir_all(&out_dir)?;
-
-    let addresses_path = out_dir.join("addresses.bin");
-    write_addresses(&addresses_path, &addresses)?;
+#[allow(clippy::cast_precision_loss)]
+fn reject_ratio(rejected: usize, records: usize) -> f64 {
+    if records == 0 {
+        return 0.0;
+    }
+    rejected as f64 / records as f64
+}
 
-    let leaves: Vec<[u8; HASH_SIZE]> = addresses.iter().map(hash_leaf).collect();
-    let layers = build_layers(leaves)?;
-    write_layers(&out_dir, &layers)?;
+/// Writes skipped input lines as CSV.
+///
+/// # Errors
+/// Returns an error if file creation or writing fails.
+fn write_rejects(path: &Path, rejected: &[RejectedLine]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(["input", "line", "reason", "value"])?;
+    for reject in rejected {
+        writer.write_record([
+            reject.input.as_str(),
+            reject.line.to_string().as_str(),
+            reject.reason.as_str(),
+            reject.value.as_deref().unwrap_or_default(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
 
-    progress.finish_and_clear();
-    let root_hex = layers
-        .last()
-        .and_then(|l| l.first())
-        .map(hex::encode)
-        .ok_or("Failed to get root hash")?;
+fn print_summary(report: &IngestReport, exclusions: &Exclusions, out_dir: &Path) {
+    for input in &report.inputs {
+        match &input.operation {
+            None => println!(
+                "Read {} unique addresses from {} ({} input)",
+                input.addresses, input.input, input.format
+            ),
+            Some(op) => println!(
+                "Applied {op} with {} ({} unique addresses, {} input): {} addresses",
+                input.input, input.addresses, input.format, input.result
+            ),
+        }
+    }
     println!(
         "Wrote {} unique addresses ({}-byte each) to {}",
-        addresses.len(),
+        report.counts.leaves,
         ADDRESS_SIZE,
-        addresses_path.display()
+        out_dir.join("addresses.bin").display()
     );
-    if duplicates_removed > 0 {
-        println!("Removed {duplicates_removed} duplicate addresses");
+    if report.counts.duplicates_removed > 0 {
+        println!(
+            "Removed {} duplicate addresses",
+            report.counts.duplicates_removed
+        );
+    }
+    if !report.excluded.is_empty() {
+        println!(
+            "Excluded {} addresses (listed in {}):",
+            report.excluded.len(),
+            out_dir.join("excluded.csv").display()
+        );
+        for source in exclusions.sources() {
+            let count = report
+                .excluded
+                .iter()
+                .filter(|e| e.source == source.name)
+                .count();
+            if count > 0 {
+                println!("  {count} from {}", source.name);
+            }
+        }
     }
     println!(
-        "Built {} Merkle layers (root: 0x{root_hex}) into {}",
-        layers.len(),
+        "Built {} Merkle layers (root: {}) into {}",
+        report.layers,
+        report.root.as_deref().unwrap_or_default(),
         out_dir.display()
     );
+}
+
+/// Builds the exclusion set from the built-in reserved addresses and denylists.
+///
+/// # Errors
+/// Returns an error if a denylist cannot be read or contains an invalid address.
+fn load_exclusions(config: &Config) -> Result<Exclusions, Box<dyn std::error::Error>> {
+    let mut exclusions = if config.allow_reserved {
+        Exclusions::default()
+    } else {
+        Exclusions::with_reserved()
+    };
+    for path in &config.exclude {
+        exclusions.load_list(Path::new(path))?;
+    }
+    Ok(exclusions)
+}
+
+/// Writes excluded addresses and their sources as CSV.
+///
+/// # Errors
+/// Returns an error if file creation or writing fails.
+fn write_excluded(
+    path: &Path,
+    excluded: &[ExcludedAddress],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(["address", "source"])?;
+    for entry in excluded {
+        writer.write_record([to_hex20(&entry.address).as_str(), entry.source.as_str()])?;
+    }
+    writer.flush()?;
     Ok(())
 }
 
@@ -152,6 +819,11 @@ fn write_addresses(
     Ok(())
 }
 
+/// Writes one file per layer and removes stale higher layers left by a
+/// previous, taller build in the same directory.
+///
+/// # Errors
+/// Returns an error if file creation, writing or removal fails.
 fn write_layers(
     dir: &Path,
     layers: &[Vec<[u8; HASH_SIZE]>],
@@ -165,6 +837,9 @@ fn write_layers(
         }
         writer.flush()?;
     }
+    for stale in available_layers(dir).iter().skip(layers.len()) {
+        remove_file(stale)?;
+    }
     Ok(())
 }
 
@@ -243,7 +918,7 @@ fn total_hash_ops(mut count: usize) -> usize {
 mod tests {
     use super::*;
     use std::io::Write;
-    use tempfile::NamedTempFile;
+    use tempfile::{NamedTempFile, TempDir};
 
     #[test]
     fn test_total_hash_ops_single() {
@@ -274,7 +949,7 @@ mod tests {
     fn test_convert_file_empty() {
         let mut temp = NamedTempFile::new().unwrap();
         temp.write_all(b"").unwrap();
-        let result = convert_file(temp.path().to_str().unwrap(), "test_out");
+        let result = convert_file(&config_for(temp.path(), Path::new("test_out")));
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("no addresses"));
     }
@@ -283,7 +958,7 @@ mod tests {
     fn test_convert_file_only_whitespace() {
         let mut temp = NamedTempFile::new().unwrap();
         temp.write_all(b"   \n\n\t\n   ").unwrap();
-        let result = convert_file(temp.path().to_str().unwrap(), "test_out");
+        let result = convert_file(&config_for(temp.path(), Path::new("test_out")));
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("no addresses"));
     }
@@ -302,14 +977,435 @@ mod tests {
         assert!(result.unwrap_err().contains("Too many arguments"));
     }
 
-    fn parse_args_with(args: Vec<&str>) -> Result<(String, String), String> {
-        let mut iter = args.into_iter();
-        iter.next();
-        let input = iter.next().ok_or("Missing required argument: input file")?;
-        let output_dir = iter.next().unwrap_or("merkledb");
-        if iter.next().is_some() {
-            return Err("Too many arguments provided".to_string());
+    #[test]
+    fn test_parse_args_format_and_field() {
+        let config = parse_args_with(vec![
+            "txt_to_bin",
+            "holders.csv",
+            "--format",
+            "csv",
+            "outdir",
+            "--column",
+            "wallet",
+        ])
+        .unwrap();
+        assert_eq!(config.input, "holders.csv");
+        assert_eq!(config.output_dir, "outdir");
+        assert_eq!(config.format, Some(InputFormat::Csv));
+        assert_eq!(config.selector.as_deref(), Some("wallet"));
+    }
+
+    #[test]
+    fn test_parse_args_unknown_format() {
+        let result = parse_args_with(vec!["txt_to_bin", "in.txt", "--format", "xml"]);
+        assert!(result.unwrap_err().contains("Unknown input format"));
+    }
+
+    #[test]
+    fn test_convert_file_csv_with_extra_columns() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("holders.csv");
+        std::fs::write(
+            &input,
+            "balance,address\n5,0x2222222222222222222222222222222222222222\n7,0x1111111111111111111111111111111111111111\n",
+        )
+        .unwrap();
+        let out = dir.path().join("db");
+        convert_file(&config_for(&input, &out)).unwrap();
+
+        let bytes = std::fs::read(out.join("addresses.bin")).unwrap();
+        assert_eq!(bytes.len(), 2 * ADDRESS_SIZE);
+        assert_eq!(bytes[0], 0x11);
+        assert!(out.join("layer01.bin").exists());
+    }
+
+    #[test]
+    fn test_convert_file_account_dump() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("accounts.json");
+        std::fs::write(
+            &input,
+            r#"[{"address":"0x1111111111111111111111111111111111111111","private_key":"0x01"}]"#,
+        )
+        .unwrap();
+        let out = dir.path().join("db");
+        convert_file(&config_for(&input, &out)).unwrap();
+        assert_eq!(
+            std::fs::read(out.join("addresses.bin")).unwrap(),
+            [0x11u8; ADDRESS_SIZE]
+        );
+    }
+
+    #[test]
+    fn test_parse_args_exclusions() {
+        let config = parse_args_with(vec![
+            "txt_to_bin",
+            "in.txt",
+            "--exclude",
+            "contracts.txt",
+            "-x",
+            "sanctioned.csv",
+            "--allow-reserved",
+        ])
+        .unwrap();
+        assert_eq!(config.exclude, vec!["contracts.txt", "sanctioned.csv"]);
+        assert!(config.allow_reserved);
+    }
+
+    #[test]
+    fn test_convert_file_reports_excluded_addresses() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("addresses.txt");
+        std::fs::write(
+            &input,
+            "0x0000000000000000000000000000000000000000\n\
+             0x0000000000000000000000000000000000000001\n\
+             0x2222222222222222222222222222222222222222\n\
+             0x1111111111111111111111111111111111111111\n",
+        )
+        .unwrap();
+        let denylist = dir.path().join("denylist.txt");
+        std::fs::write(&denylist, "0x2222222222222222222222222222222222222222\n").unwrap();
+        let out = dir.path().join("db");
+        let mut config = config_for(&input, &out);
+        config.exclude.push(denylist.to_string_lossy().into_owned());
+        convert_file(&config).unwrap();
+
+        assert_eq!(
+            std::fs::read(out.join("addresses.bin")).unwrap(),
+            [0x11u8; ADDRESS_SIZE]
+        );
+        let report = std::fs::read_to_string(out.join("excluded.csv")).unwrap();
+        assert_eq!(report.lines().count(), 4);
+        assert!(report.contains("0x0000000000000000000000000000000000000001,reserved"));
+        assert!(report.contains(&format!(
+            "0x2222222222222222222222222222222222222222,{}",
+            denylist.display()
+        )));
+    }
+
+    #[test]
+    fn test_write_excluded_quotes_sources() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("excluded.csv");
+        let excluded = [ExcludedAddress {
+            address: [0x22; ADDRESS_SIZE],
+            source: "lists/deny, \"old\".txt".to_string(),
+        }];
+        write_excluded(&path, &excluded).unwrap();
+
+        let mut reader = csv::Reader::from_path(&path).unwrap();
+        let rows: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(&rows[0][0], "0x2222222222222222222222222222222222222222");
+        assert_eq!(&rows[0][1], "lists/deny, \"old\".txt");
+    }
+
+    #[test]
+    fn test_convert_file_all_excluded() {
+        let mut temp = NamedTempFile::new().unwrap();
+        temp.write_all(b"0x0000000000000000000000000000000000000000\n")
+            .unwrap();
+        let result = convert_file(&config_for(temp.path(), Path::new("test_out")));
+        assert!(result.unwrap_err().to_string().contains("were excluded"));
+    }
+
+    #[test]
+    fn test_convert_file_report_lists_all_rejects() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("addresses.txt");
+        std::fs::write(
+            &input,
+            "0x1111111111111111111111111111111111111111\n\
+             not-an-address\n\
+             0x1111111111111111111111111111111111111111\n\
+             0x12\n",
+        )
+        .unwrap();
+        let report_path = dir.path().join("report.json");
+        let mut config = config_for(&input, &dir.path().join("db"));
+        config.report = Some(report_path.to_string_lossy().into_owned());
+
+        let err = convert_file(&config).unwrap_err().to_string();
+        assert!(err.contains("Line 2"));
+        assert!(err.contains("1 more invalid lines"));
+
+        let report: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&report_path).unwrap()).unwrap();
+        assert_eq!(report["status"], "failed");
+        assert_eq!(report["counts"]["records"], 4);
+        assert_eq!(report["rejected"][0]["line"], 2);
+        assert_eq!(report["rejected"][0]["value"], "not-an-address");
+        assert_eq!(report["rejected"][1]["line"], 4);
+    }
+
+    #[test]
+    fn test_convert_file_report_success() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("addresses.txt");
+        std::fs::write(
+            &input,
+            "0x2222222222222222222222222222222222222222\n\
+             0x1111111111111111111111111111111111111111\n\
+             0x2222222222222222222222222222222222222222\n\
+             0x0000000000000000000000000000000000000000\n\
+             0x2222222222222222222222222222222222222222\n",
+        )
+        .unwrap();
+        let report_path = dir.path().join("report.json");
+        let mut config = config_for(&input, &dir.path().join("db"));
+        config.report = Some(report_path.to_string_lossy().into_owned());
+        convert_file(&config).unwrap();
+
+        let report: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&report_path).unwrap()).unwrap();
+        assert_eq!(report["status"], "ok");
+        assert_eq!(report["format"], "text");
+        assert_eq!(report["counts"]["duplicates_removed"], 2);
+        assert_eq!(report["counts"]["excluded"], 1);
+        assert_eq!(report["counts"]["leaves"], 2);
+        assert_eq!(
+            report["duplicates"][0]["lines"],
+            serde_json::json!([1, 3, 5])
+        );
+        assert_eq!(report["layers"], 2);
+        assert!(report["root"].as_str().unwrap().starts_with("0x"));
+    }
+
+    #[test]
+    fn test_parse_args_lenient() {
+        let config = parse_args_with(vec![
+            "txt_to_bin",
+            "in.txt",
+            "--lenient",
+            "--max-reject-ratio",
+            "0.25",
+        ])
+        .unwrap();
+        assert!(config.lenient);
+        assert_eq!(config.max_reject_ratio, Some(0.25));
+
+        let result = parse_args_with(vec!["txt_to_bin", "in.txt", "--max-reject-ratio", "0.2"]);
+        assert!(result.unwrap_err().contains("require --lenient"));
+        let result = parse_args_with(vec![
+            "txt_to_bin",
+            "in.txt",
+            "--lenient",
+            "--max-reject-ratio",
+            "2",
+        ]);
+        assert!(result.unwrap_err().contains("between 0 and 1"));
+    }
+
+    #[test]
+    fn test_reject_ratio() {
+        assert!((reject_ratio(0, 0) - 0.0).abs() < f64::EPSILON);
+        assert!((reject_ratio(1, 4) - 0.25).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_convert_file_lenient_skips_invalid_lines() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("addresses.txt");
+        std::fs::write(
+            &input,
+            "0x1111111111111111111111111111111111111111\n\
+             bogus\n\
+             0x2222222222222222222222222222222222222222\n\
+             0x3333333333333333333333333333333333333333\n",
+        )
+        .unwrap();
+        let out = dir.path().join("db");
+        let mut config = config_for(&input, &out);
+        config.lenient = true;
+        config.max_reject_ratio = Some(0.25);
+        convert_file(&config).unwrap();
+
+        let bytes = std::fs::read(out.join("addresses.bin")).unwrap();
+        assert_eq!(bytes.len(), 3 * ADDRESS_SIZE);
+        let rejects = std::fs::read_to_string(out.join("rejects.csv")).unwrap();
+        assert!(rejects.starts_with("input,line,reason,value\n"));
+        assert!(rejects.contains(&format!("{},2,", input.display())));
+        assert!(rejects.trim_end().ends_with(",bogus"));
+    }
+
+    #[test]
+    fn test_convert_file_lenient_ratio_exceeded() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("addresses.txt");
+        std::fs::write(
+            &input,
+            "0x1111111111111111111111111111111111111111\nbogus\n",
+        )
+        .unwrap();
+        let out = dir.path().join("db");
+        let mut config = config_for(&input, &out);
+        config.lenient = true;
+        config.rejects = Some(dir.path().join("bad.csv").to_string_lossy().into_owned());
+
+        let err = convert_file(&config).unwrap_err().to_string();
+        assert!(err.contains("Rejected 1 of 2 records (50.00%)"));
+        assert!(dir.path().join("bad.csv").exists());
+        assert!(!out.join("addresses.bin").exists());
+    }
+
+    #[test]
+    fn test_parse_args_set_operations() {
+        let config = parse_args_with(vec![
+            "txt_to_bin",
+            "a.txt",
+            "--union",
+            "b.csv",
+            "out",
+            "--subtract",
+            "bots.txt",
+        ])
+        .unwrap();
+        assert_eq!(config.output_dir, "out");
+        assert_eq!(
+            config.operations,
+            vec![
+                (SetOp::Union, "b.csv".to_string()),
+                (SetOp::Subtract, "bots.txt".to_string())
+            ]
+        );
+        let result = parse_args_with(vec!["txt_to_bin", "a.txt", "--union"]);
+        assert!(result.unwrap_err().contains("requires an input"));
+    }
+
+    #[test]
+    fn test_convert_file_set_operations_on_lists_and_databases() {
+        let dir = TempDir::new().unwrap();
+        let holders_a = dir.path().join("a.txt");
+        std::fs::write(
+            &holders_a,
+            "0x1111111111111111111111111111111111111111\n\
+             0x3333333333333333333333333333333333333333\n",
+        )
+        .unwrap();
+        let holders_b = dir.path().join("b.csv");
+        std::fs::write(
+            &holders_b,
+            "address,balance\n0x2222222222222222222222222222222222222222,1\n\
+             0x3333333333333333333333333333333333333333,2\n",
+        )
+        .unwrap();
+        let bots = dir.path().join("bots.txt");
+        std::fs::write(&bots, "0x3333333333333333333333333333333333333333\n").unwrap();
+
+        let first = dir.path().join("db1");
+        let mut config = config_for(&holders_a, &first);
+        config.operations = vec![(SetOp::Union, holders_b.to_string_lossy().into_owned())];
+        convert_file(&config).unwrap();
+        assert_eq!(
+            std::fs::read(first.join("addresses.bin")).unwrap().len(),
+            3 * ADDRESS_SIZE
+        );
+
+        let second = dir.path().join("db2");
+        let mut config = config_for(&first, &second);
+        config.operations = vec![(SetOp::Subtract, bots.to_string_lossy().into_owned())];
+        convert_file(&config).unwrap();
+        let mut expected = vec![0x11u8; ADDRESS_SIZE];
+        expected.extend_from_slice(&[0x22u8; ADDRESS_SIZE]);
+        assert_eq!(
+            std::fs::read(second.join("addresses.bin")).unwrap(),
+            expected
+        );
+
+        let third = dir.path().join("db3");
+        let mut config = config_for(&second, &third);
+        config.operations = vec![(SetOp::Intersect, bots.to_string_lossy().into_owned())];
+        let err = convert_file(&config).unwrap_err().to_string();
+        assert!(err.contains("left no addresses"));
+    }
+
+    #[test]
+    fn test_convert_file_writes_manifest() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("addresses.txt");
+        std::fs::write(
+            &input,
+            "0x1111111111111111111111111111111111111111\n\
+             0x2222222222222222222222222222222222222222\n\
+             0x3333333333333333333333333333333333333333\n",
+        )
+        .unwrap();
+        let out = dir.path().join("db");
+        convert_file(&config_for(&input, &out)).unwrap();
+
+        let manifest = Manifest::read(&out).unwrap();
+        assert_eq!(manifest.leaf_count, 3);
+        assert_eq!(manifest.layer_count, 3);
+        assert_eq!(manifest.inputs[0].hash, hash_file(&input).unwrap().0);
+        assert_eq!(manifest.files.len(), 4);
+        assert_eq!(manifest.files[0].name, "addresses.bin");
+        assert_eq!(
+            manifest.root,
+            format!(
+                "0x{}",
+                hex::encode(std::fs::read(out.join("layer02.bin")).unwrap())
+            )
+        );
+    }
+
+    #[test]
+    fn test_write_layers_removes_stale_layers() {
+        let dir = TempDir::new().unwrap();
+        let tall = build_layers(vec![[1u8; HASH_SIZE]; 4]).unwrap();
+        write_layers(dir.path(), &tall).unwrap();
+        assert!(dir.path().join("layer02.bin").exists());
+
+        let short = build_layers(vec![[1u8; HASH_SIZE]; 2]).unwrap();
+        write_layers(dir.path(), &short).unwrap();
+        assert_eq!(available_layers(dir.path()).len(), 2);
+    }
+
+    #[test]
+    fn test_verify_build() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("addresses.csv");
+        std::fs::write(
+            &input,
+            "address\n0x1111111111111111111111111111111111111111\n\
+             0x2222222222222222222222222222222222222222\n",
+        )
+        .unwrap();
+        let denylist = dir.path().join("denylist.txt");
+        std::fs::write(&denylist, "0x2222222222222222222222222222222222222222\n").unwrap();
+        let out = dir.path().join("db");
+        let mut config = config_for(&input, &out);
+        config.exclude.push(denylist.to_string_lossy().into_owned());
+        convert_file(&config).unwrap();
+        verify_build(&out).unwrap();
+
+        std::fs::write(out.join("layer00.bin"), [0u8; HASH_SIZE]).unwrap();
+        let err = verify_build(&out).unwrap_err().to_string();
+        assert!(err.contains("does not match its manifest"));
+
+        convert_file(&config).unwrap();
+        std::fs::write(&denylist, "0x1111111111111111111111111111111111111111\n").unwrap();
+        let err = verify_build(&out).unwrap_err().to_string();
+        assert!(err.contains("changed since the build"));
+    }
+
+    fn config_for(input: &Path, output_dir: &Path) -> Config {
+        Config {
+            input: input.to_string_lossy().into_owned(),
+            output_dir: output_dir.to_string_lossy().into_owned(),
+            format: None,
+            selector: None,
+            exclude: Vec::new(),
+            allow_reserved: false,
+            report: None,
+            lenient: false,
+            rejects: None,
+            max_reject_ratio: None,
+            operations: Vec::new(),
         }
-        Ok((input.to_string(), output_dir.to_string()))
+    }
+
+    fn parse_args_with(args: Vec<&str>) -> Result<Config, String> {
+        parse_args_from(args.into_iter().skip(1).map(String::from))
     }
 }
@@ -0,0 +1,202 @@
+//! Build-time address exclusion.
+//!
+//! This module provides functions for:
+//! - The built-in set of reserved addresses (zero address and precompiles)
+//! - Loading denylists such as contract or sanctioned addresses
+//! - Removing excluded addresses from a sorted leaf set while recording why
+
+use std::path::Path;
+
+use crate::input::{detect_format, read_records, InputError};
+use crate::merkle::parse_address;
+use crate::ADDRESS_SIZE;
+
+/// Source name reported for addresses in the built-in reserved set.
+pub const RESERVED_SOURCE: &str = "reserved";
+
+/// Highest precompile address covered by the reserved set (0x01 through 0x0a).
+pub const LAST_PRECOMPILE: u8 = 0x0a;
+
+/// A named set of addresses that must not enter the tree.
+#[derive(Debug, Clone)]
+pub struct ExclusionSource {
+    pub name: String,
+    addresses: Vec<[u8; ADDRESS_SIZE]>,
+}
+
+impl ExclusionSource {
+    #[must_use]
+    pub fn new(name: impl Into<String>, mut addresses: Vec<[u8; ADDRESS_SIZE]>) -> Self {
+        addresses.sort_unstable();
+        addresses.dedup();
+        ExclusionSource {
+            name: name.into(),
+            addresses,
+        }
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.addresses.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty()
+    }
+
+    #[must_use]
+    pub fn contains(&self, address: &[u8; ADDRESS_SIZE]) -> bool {
+        self.addresses.binary_search(address).is_ok()
+    }
+}
+
+/// An address removed from the leaf set, with the source that excluded it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExcludedAddress {
+    pub address: [u8; ADDRESS_SIZE],
+    pub source: String,
+}
+
+/// The exclusion sources applied to a build, checked in insertion order.
+#[derive(Debug, Clone, Default)]
+pub struct Exclusions {
+    sources: Vec<ExclusionSource>,
+}
+
+impl Exclusions {
+    /// Creates an exclusion set containing only the built-in reserved addresses.
+    #[must_use]
+    pub fn with_reserved() -> Self {
+        let mut exclusions = Exclusions::default();
+        exclusions.add_source(ExclusionSource::new(RESERVED_SOURCE, reserved_addresses()));
+        exclusions
+    }
+
+    pub fn add_source(&mut self, source: ExclusionSource) {
+        self.sources.push(source);
+    }
+
+    /// Loads a denylist file and adds it as a source named after the path.
+    ///
+    /// Any input format understood by [`crate::input`] is accepted.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or contains an invalid address.
+    pub fn load_list(&mut self, path: &Path) -> Result<(), InputError> {
+        let format = detect_format(path)?;
+        let mut addresses = Vec::new();
+        for record in read_records(path, format, None)? {
+            let record = record.map_err(|e| {
+                InputError::Malformed(format!("Exclusion list {}: {e}", path.display()))
+            })?;
+            let address = parse_address(&record.value).map_err(|e| {
+                InputError::Malformed(format!(
+                    "Exclusion list {}: Line {}: {e}",
+                    path.display(),
+                    record.line
+                ))
+            })?;
+            addresses.push(address);
+        }
+        self.add_source(ExclusionSource::new(path.display().to_string(), addresses));
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn sources(&self) -> &[ExclusionSource] {
+        &self.sources
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.sources.iter().all(ExclusionSource::is_empty)
+    }
+
+    /// Returns the name of the first source that excludes `address`.
+    #[must_use]
+    pub fn matching_source(&self, address: &[u8; ADDRESS_SIZE]) -> Option<&str> {
+        self.sources
+            .iter()
+            .find(|source| source.contains(address))
+            .map(|source| source.name.as_str())
+    }
+
+    /// Removes excluded addresses in place and returns them in their original order.
+    pub fn apply(&self, addresses: &mut Vec<[u8; ADDRESS_SIZE]>) -> Vec<ExcludedAddress> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+        let mut excluded = Vec::new();
+        addresses.retain(|address| match self.matching_source(address) {
+            Some(source) => {
+                excluded.push(ExcludedAddress {
+                    address: *address,
+                    source: source.to_string(),
+                });
+                false
+            }
+            None => true,
+        });
+        excluded
+    }
+}
+
+/// Returns the zero address and the precompile addresses 0x01 through 0x0a.
+#[must_use]
+pub fn reserved_addresses() -> Vec<[u8; ADDRESS_SIZE]> {
+    (0..=LAST_PRECOMPILE)
+        .map(|last| {
+            let mut address = [0u8; ADDRESS_SIZE];
+            address[ADDRESS_SIZE - 1] = last;
+            address
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn addr(last: u8) -> [u8; ADDRESS_SIZE] {
+        let mut address = [0x11u8; ADDRESS_SIZE];
+        address[ADDRESS_SIZE - 1] = last;
+        address
+    }
+
+    #[test]
+    fn test_reserved_addresses() {
+        let reserved = reserved_addresses();
+        assert_eq!(reserved.len(), 11);
+        assert_eq!(reserved[0], [0u8; ADDRESS_SIZE]);
+        assert_eq!(reserved[10][ADDRESS_SIZE - 1], 0x0a);
+    }
+
+    #[test]
+    fn test_apply_reports_first_matching_source() {
+        let mut exclusions = Exclusions::with_reserved();
+        exclusions.add_source(ExclusionSource::new("bots", vec![addr(2), addr(1)]));
+        exclusions.add_source(ExclusionSource::new("sanctions", vec![addr(2)]));
+
+        let mut precompile = [0u8; ADDRESS_SIZE];
+        precompile[ADDRESS_SIZE - 1] = 0x05;
+        let mut addresses = vec![precompile, addr(1), addr(2), addr(3)];
+        let excluded = exclusions.apply(&mut addresses);
+
+        assert_eq!(addresses, vec![addr(3)]);
+        let sources: Vec<&str> = excluded.iter().map(|e| e.source.as_str()).collect();
+        assert_eq!(sources, vec![RESERVED_SOURCE, "bots", "bots"]);
+    }
+
+    #[test]
+    fn test_load_list_rejects_invalid_address() {
+        let mut temp = NamedTempFile::new().unwrap();
+        temp.write_all(b"0x1111111111111111111111111111111111111111\nnope\n")
+            .unwrap();
+        let err = Exclusions::default().load_list(temp.path()).unwrap_err();
+        assert!(err.to_string().contains("Line 2"));
+    }
+}
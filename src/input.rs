@@ -0,0 +1,463 @@
+//! Readers for address list inputs.
+//!
+//! This module provides functions for:
+//! - Detecting the format of an input file (text, CSV, JSON, NDJSON)
+//! - Extracting the address column or field from each record
+//! - Reporting the source line of every record for diagnostics
+
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::str::FromStr;
+
+use serde_json::Value;
+
+/// Field used for JSON/NDJSON records and CSV headers when no selector is given.
+pub const DEFAULT_ADDRESS_FIELD: &str = "address";
+
+const SNIFF_BYTES: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    /// One address per line; blank lines are ignored.
+    Text,
+    /// Comma-separated values with a header row.
+    Csv,
+    /// A single JSON array of strings or objects.
+    Json,
+    /// One JSON string or object per line.
+    Ndjson,
+}
+
+impl InputFormat {
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InputFormat::Text => "text",
+            InputFormat::Csv => "csv",
+            InputFormat::Json => "json",
+            InputFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+impl FromStr for InputFormat {
+    type Err = InputError;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw.to_ascii_lowercase().as_str() {
+            "text" | "txt" => Ok(InputFormat::Text),
+            "csv" => Ok(InputFormat::Csv),
+            "json" => Ok(InputFormat::Json),
+            "ndjson" | "jsonl" => Ok(InputFormat::Ndjson),
+            other => Err(InputError::UnknownFormat(other.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for InputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum InputError {
+    UnknownFormat(String),
+    FileIo(String),
+    Malformed(String),
+    MissingColumn(String),
+    /// A single record could not be read; the remaining records are still usable.
+    Record {
+        line: usize,
+        message: String,
+    },
+}
+
+impl InputError {
+    /// Returns the line number for errors that affect a single record only.
+    #[must_use]
+    pub fn record_line(&self) -> Option<usize> {
+        match self {
+            InputError::Record { line, .. } => Some(*line),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputError::UnknownFormat(name) => write!(
+                f,
+                "Unknown input format '{name}' (expected text, csv, json or ndjson)"
+            ),
+            InputError::FileIo(msg) => write!(f, "File I/O error: {msg}"),
+            InputError::Malformed(msg) => write!(f, "Malformed input: {msg}"),
+            InputError::MissingColumn(msg) => write!(f, "Missing column: {msg}"),
+            InputError::Record { line, message } => write!(f, "Line {line}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for InputError {}
+
+/// A raw address value extracted from one input record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputRecord {
+    /// 1-based source line (for JSON arrays, the 1-based element position).
+    pub line: usize,
+    pub value: String,
+}
+
+/// Picks a format from the file extension, falling back to sniffing the content.
+///
+/// # Errors
+/// Returns an error if the file cannot be opened or read.
+pub fn detect_format(path: &Path) -> Result<InputFormat, InputError> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("csv") => return Ok(InputFormat::Csv),
+        Some("ndjson" | "jsonl") => return Ok(InputFormat::Ndjson),
+        _ => {}
+    }
+
+    let mut head = Vec::with_capacity(SNIFF_BYTES);
+    File::open(path)
+        .map_err(|e| InputError::FileIo(format!("Failed to open {}: {e}", path.display())))?
+        .take(SNIFF_BYTES as u64)
+        .read_to_end(&mut head)
+        .map_err(|e| InputError::FileIo(format!("Failed to read {}: {e}", path.display())))?;
+    Ok(sniff_format(&head))
+}
+
+fn sniff_format(head: &[u8]) -> InputFormat {
+    let text = String::from_utf8_lossy(head);
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    if text.starts_with('[') {
+        return InputFormat::Json;
+    }
+    if text.starts_with('{') || text.starts_with('"') {
+        return InputFormat::Ndjson;
+    }
+    let first_line = text.lines().next().unwrap_or_default();
+    if first_line.contains(',') {
+        InputFormat::Csv
+    } else {
+        InputFormat::Text
+    }
+}
+
+/// Opens an input file and returns an iterator over its address values.
+///
+/// `selector` names the CSV column (header name or 0-based index) or the
+/// JSON field (dot-separated path) holding the address. When omitted, CSV and
+/// JSON inputs use the `address` column/field, and a single-column CSV uses
+/// its only column.
+///
+/// Items that fail with [`InputError::Record`] affect only that record;
+/// any other error ends the iteration.
+///
+/// # Errors
+/// Returns an error if the file cannot be opened, if a JSON array cannot be
+/// parsed, or if the requested CSV column does not exist.
+pub fn read_records(
+    path: &Path,
+    format: InputFormat,
+    selector: Option<&str>,
+) -> Result<Box<dyn Iterator<Item = Result<InputRecord, InputError>>>, InputError> {
+    let file = File::open(path)
+        .map_err(|e| InputError::FileIo(format!("Failed to open {}: {e}", path.display())))?;
+    let reader = BufReader::new(file);
+    match format {
+        InputFormat::Text => {
+            if selector.is_some() {
+                return Err(InputError::MissingColumn(
+                    "Text input has no columns or fields to select".to_string(),
+                ));
+            }
+            Ok(Box::new(text_records(reader)))
+        }
+        InputFormat::Csv => csv_records(reader, selector),
+        InputFormat::Json => json_records(reader, selector),
+        InputFormat::Ndjson => Ok(Box::new(ndjson_records(reader, selector))),
+    }
+}
+
+fn text_records<R: BufRead>(reader: R) -> impl Iterator<Item = Result<InputRecord, InputError>> {
+    reader
+        .lines()
+        .enumerate()
+        .filter_map(|(idx, line)| match line {
+            Ok(line) => {
+                let trimmed = line.trim();
+                (!trimmed.is_empty()).then(|| {
+                    Ok(InputRecord {
+                        line: idx + 1,
+                        value: trimmed.to_string(),
+                    })
+                })
+            }
+            Err(e) => Some(Err(InputError::FileIo(format!(
+                "Read failed at line {}: {e}",
+                idx + 1
+            )))),
+        })
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn csv_records<R: Read + 'static>(
+    reader: R,
+    selector: Option<&str>,
+) -> Result<Box<dyn Iterator<Item = Result<InputRecord, InputError>>>, InputError> {
+    let mut csv_reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(reader);
+    let headers = csv_reader
+        .headers()
+        .map_err(|e| InputError::Malformed(format!("Invalid CSV header: {e}")))?
+        .clone();
+    let column = select_column(&headers, selector)?;
+
+    let records = csv_reader.into_records().filter_map(move |record| {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                let line = e.position().map_or(0, |p| p.line() as usize);
+                return Some(Err(InputError::Record {
+                    line,
+                    message: format!("Invalid CSV row: {e}"),
+                }));
+            }
+        };
+        let line = record.position().map_or(0, |p| p.line() as usize);
+        if record.iter().all(str::is_empty) {
+            return None;
+        }
+        Some(match record.get(column) {
+            Some(value) if !value.is_empty() => Ok(InputRecord {
+                line,
+                value: value.to_string(),
+            }),
+            _ => Err(InputError::Record {
+                line,
+                message: format!("Missing value in column {column}"),
+            }),
+        })
+    });
+    Ok(Box::new(records))
+}
+
+fn select_column(headers: &csv::StringRecord, selector: Option<&str>) -> Result<usize, InputError> {
+    let wanted = selector.unwrap_or(DEFAULT_ADDRESS_FIELD);
+    if let Some(idx) = headers.iter().position(|h| {
+        h.trim_start_matches('\u{feff}')
+            .eq_ignore_ascii_case(wanted)
+    }) {
+        return Ok(idx);
+    }
+    if let Some(idx) = selector.and_then(|s| s.parse::<usize>().ok()) {
+        if idx < headers.len() {
+            return Ok(idx);
+        }
+    }
+    if selector.is_none() && headers.len() == 1 {
+        return Ok(0);
+    }
+    Err(InputError::MissingColumn(format!(
+        "CSV header has no column '{wanted}' (columns: {})",
+        headers.iter().collect::<Vec<_>>().join(", ")
+    )))
+}
+
+fn json_records<R: Read>(
+    reader: R,
+    selector: Option<&str>,
+) -> Result<Box<dyn Iterator<Item = Result<InputRecord, InputError>>>, InputError> {
+    let value: Value = serde_json::from_reader(reader)
+        .map_err(|e| InputError::Malformed(format!("Invalid JSON: {e}")))?;
+    let Value::Array(items) = value else {
+        return Err(InputError::Malformed(
+            "JSON input must be an array of addresses or objects".to_string(),
+        ));
+    };
+    let field = selector.unwrap_or(DEFAULT_ADDRESS_FIELD).to_string();
+    Ok(Box::new(items.into_iter().enumerate().map(
+        move |(idx, item)| {
+            extract_value(&item, &field)
+                .map(|value| InputRecord {
+                    line: idx + 1,
+                    value,
+                })
+                .map_err(|message| InputError::Record {
+                    line: idx + 1,
+                    message,
+                })
+        },
+    )))
+}
+
+fn ndjson_records<R: BufRead>(
+    reader: R,
+    selector: Option<&str>,
+) -> impl Iterator<Item = Result<InputRecord, InputError>> {
+    let field = selector.unwrap_or(DEFAULT_ADDRESS_FIELD).to_string();
+    reader.lines().enumerate().filter_map(move |(idx, line)| {
+        let line_no = idx + 1;
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                return Some(Err(InputError::FileIo(format!(
+                    "Read failed at line {line_no}: {e}"
+                ))))
+            }
+        };
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            return None;
+        }
+        let parsed = serde_json::from_str::<Value>(trimmed)
+            .map_err(|e| format!("Invalid JSON: {e}"))
+            .and_then(|item| extract_value(&item, &field));
+        Some(
+            parsed
+                .map(|value| InputRecord {
+                    line: line_no,
+                    value,
+                })
+                .map_err(|message| InputError::Record {
+                    line: line_no,
+                    message,
+                }),
+        )
+    })
+}
+
+fn extract_value(item: &Value, field: &str) -> Result<String, String> {
+    let target = match item {
+        Value::Object(_) => field
+            .split('.')
+            .try_fold(item, |current, key| current.get(key))
+            .ok_or_else(|| format!("Missing field '{field}'"))?,
+        other => other,
+    };
+    match target {
+        Value::String(s) => Ok(s.trim().to_string()),
+        _ => Err(format!("Field '{field}' is not a string")),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    const ADDR_A: &str = "0x1111111111111111111111111111111111111111";
+    const ADDR_B: &str = "0x2222222222222222222222222222222222222222";
+
+    fn temp_with(suffix: &str, contents: &str) -> NamedTempFile {
+        let mut temp = tempfile::Builder::new().suffix(suffix).tempfile().unwrap();
+        temp.write_all(contents.as_bytes()).unwrap();
+        temp
+    }
+
+    fn collect(
+        path: &Path,
+        format: InputFormat,
+        selector: Option<&str>,
+    ) -> Vec<Result<InputRecord, InputError>> {
+        read_records(path, format, selector).unwrap().collect()
+    }
+
+    #[test]
+    fn test_input_format_from_str() {
+        assert_eq!("CSV".parse::<InputFormat>().unwrap(), InputFormat::Csv);
+        assert_eq!("jsonl".parse::<InputFormat>().unwrap(), InputFormat::Ndjson);
+        assert!("xml".parse::<InputFormat>().is_err());
+    }
+
+    #[test]
+    fn test_sniff_format() {
+        assert_eq!(sniff_format(b"  [\"0x1\"]"), InputFormat::Json);
+        assert_eq!(
+            sniff_format(b"{\"address\":\"0x1\"}\n"),
+            InputFormat::Ndjson
+        );
+        assert_eq!(sniff_format(b"address,balance\n0x1,2\n"), InputFormat::Csv);
+        assert_eq!(sniff_format(b"0x1\n0x2\n"), InputFormat::Text);
+    }
+
+    #[test]
+    fn test_text_records_skip_blank_lines() {
+        let temp = temp_with(".txt", &format!("{ADDR_A}\n\n  {ADDR_B}  \n"));
+        let records = collect(temp.path(), InputFormat::Text, None);
+        assert_eq!(records.len(), 2);
+        let second = records[1].as_ref().unwrap();
+        assert_eq!(second.line, 3);
+        assert_eq!(second.value, ADDR_B);
+    }
+
+    #[test]
+    fn test_csv_records_by_header() {
+        let temp = temp_with(
+            ".csv",
+            &format!("balance,Address\n10,{ADDR_A}\n20,\n30,{ADDR_B}\n"),
+        );
+        assert_eq!(detect_format(temp.path()).unwrap(), InputFormat::Csv);
+        let records = collect(temp.path(), InputFormat::Csv, None);
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].as_ref().unwrap().value, ADDR_A);
+        assert_eq!(records[1].as_ref().unwrap_err().record_line(), Some(3));
+        assert_eq!(records[2].as_ref().unwrap().line, 4);
+    }
+
+    #[test]
+    fn test_csv_records_by_index_and_missing_column() {
+        let temp = temp_with(".csv", &format!("holder,amount\n{ADDR_A},1\n"));
+        let records = collect(temp.path(), InputFormat::Csv, Some("0"));
+        assert_eq!(records[0].as_ref().unwrap().value, ADDR_A);
+
+        let err = read_records(temp.path(), InputFormat::Csv, None)
+            .err()
+            .expect("missing address column");
+        assert!(err.to_string().contains("holder, amount"));
+    }
+
+    #[test]
+    fn test_json_account_dump() {
+        let temp = temp_with(
+            ".json",
+            &format!(
+                "[{{\"address\":\"{ADDR_A}\",\"private_key\":\"0x00\"}},{{\"private_key\":\"0x01\"}}]"
+            ),
+        );
+        assert_eq!(detect_format(temp.path()).unwrap(), InputFormat::Json);
+        let records = collect(temp.path(), InputFormat::Json, None);
+        assert_eq!(records[0].as_ref().unwrap().value, ADDR_A);
+        assert!(records[1]
+            .as_ref()
+            .unwrap_err()
+            .to_string()
+            .contains("Missing field 'address'"));
+    }
+
+    #[test]
+    fn test_ndjson_nested_field() {
+        let temp = temp_with(
+            ".ndjson",
+            &format!("{{\"holder\":{{\"id\":\"{ADDR_A}\"}}}}\n\nnot json\n\"{ADDR_B}\"\n"),
+        );
+        let records = collect(temp.path(), InputFormat::Ndjson, Some("holder.id"));
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].as_ref().unwrap().value, ADDR_A);
+        assert_eq!(records[1].as_ref().unwrap_err().record_line(), Some(3));
+        assert_eq!(records[2].as_ref().unwrap().value, ADDR_B);
+    }
+}
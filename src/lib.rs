@@ -4,13 +4,27 @@
 //! - Generating Ethereum addresses from secret keys
 //! - Building and verifying Merkle proofs for airdrop claims
 //! - Converting address lists to binary Merkle tree format
+//! - Reading address lists from text, CSV and JSON inputs
+//! - Excluding reserved and denylisted addresses at build time
+//! - Writing machine-readable ingestion reports
+//! - Combining address lists and databases with set operations
+//! - Recording and verifying reproducible build manifests
+//! - Signing Merkle roots with EIP-191 and recovering the signer
+//! - Signing EIP-712 claim vouchers
 
 use k256::elliptic_curve::sec1::ToEncodedPoint;
-use k256::SecretKey;
+use k256::{PublicKey, SecretKey};
 use sha3::{Digest, Keccak256};
 
+pub mod eip712;
+pub mod exclude;
+pub mod input;
+pub mod manifest;
 pub mod merkle;
 pub mod progress;
+pub mod report;
+pub mod setops;
+pub mod signing;
 
 pub const ADDRESS_SIZE: usize = 20;
 pub const HASH_SIZE: usize = 32;
@@ -43,7 +57,19 @@ impl std::error::Error for EthereumAddressError {}
 /// Returns `EthereumAddressError::InvalidPublicKeyLength` if the public key
 /// is not the expected 65 bytes (uncompressed format).
 pub fn ethereum_address(secret_key: &SecretKey) -> Result<String, EthereumAddressError> {
-    let public_key = secret_key.public_key();
+    let address_bytes = public_key_address(&secret_key.public_key())?;
+    Ok(to_checksum_address(&address_bytes))
+}
+
+/// Derives the raw 20-byte Ethereum address of a public key.
+///
+/// # Errors
+///
+/// Returns `EthereumAddressError::InvalidPublicKeyLength` if the public key
+/// is not the expected 65 bytes (uncompressed format).
+pub fn public_key_address(
+    public_key: &PublicKey,
+) -> Result<[u8; ADDRESS_SIZE], EthereumAddressError> {
     let encoded = public_key.to_encoded_point(false);
     let public_bytes = encoded.as_bytes();
 
@@ -54,8 +80,9 @@ pub fn ethereum_address(secret_key: &SecretKey) -> Result<String, EthereumAddres
     }
 
     let hash = Keccak256::digest(&public_bytes[1..]);
-    let address_bytes = &hash[12..];
-    Ok(to_checksum_address(address_bytes))
+    let mut address = [0u8; ADDRESS_SIZE];
+    address.copy_from_slice(&hash[12..]);
+    Ok(address)
 }
 
 #[must_use]
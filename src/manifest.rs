@@ -0,0 +1,296 @@
+//! Reproducible build manifests.
+//!
+//! This module provides functions for:
+//! - Describing which inputs and parameters produced a Merkle database
+//! - Hashing `addresses.bin` and every layer file
+//! - Comparing a rebuilt database against a recorded manifest
+//!
+//! The manifest is written as `manifest.json` next to the layer files.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+
+use crate::merkle::MerkleError;
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+pub const MANIFEST_VERSION: u32 = 1;
+pub const HASH_ALGORITHM: &str = "keccak256";
+
+const HASH_CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolInfo {
+    pub name: String,
+    pub version: String,
+}
+
+impl ToolInfo {
+    /// Describes the running build of this crate.
+    #[must_use]
+    pub fn current() -> Self {
+        ToolInfo {
+            name: env!("CARGO_PKG_NAME").to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+}
+
+/// A file with its size and content hash.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileDigest {
+    pub name: String,
+    pub size: u64,
+    pub hash: String,
+}
+
+/// One address list or database that fed the build.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestInput {
+    pub path: String,
+    pub format: String,
+    /// Set operation applied with this input; absent for the base input.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operation: Option<String>,
+    pub size: u64,
+    pub hash: String,
+}
+
+/// A denylist applied to the build.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestExclusion {
+    pub path: String,
+    pub size: u64,
+    pub hash: String,
+}
+
+/// Options that influence which leaves end up in the tree and how it is hashed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TreeParameters {
+    /// Input format forced on the command line; absent when detected per input.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selector: Option<String>,
+    pub allow_reserved: bool,
+    pub lenient: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_reject_ratio: Option<f64>,
+    pub leaf_hash: String,
+    pub node_hash: String,
+    pub odd_node: String,
+}
+
+impl TreeParameters {
+    #[must_use]
+    pub fn new(
+        format: Option<String>,
+        selector: Option<String>,
+        allow_reserved: bool,
+        lenient: bool,
+        max_reject_ratio: Option<f64>,
+    ) -> Self {
+        TreeParameters {
+            format,
+            selector,
+            allow_reserved,
+            lenient,
+            max_reject_ratio,
+            leaf_hash: "keccak256(address)".to_string(),
+            node_hash: "keccak256(left || right)".to_string(),
+            odd_node: "paired with itself".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub manifest_version: u32,
+    pub tool: ToolInfo,
+    /// Unix timestamp (seconds) of the build.
+    pub created_at: u64,
+    pub hash_algorithm: String,
+    pub inputs: Vec<ManifestInput>,
+    pub exclusions: Vec<ManifestExclusion>,
+    pub parameters: TreeParameters,
+    pub leaf_count: usize,
+    pub duplicates_removed: usize,
+    pub excluded: usize,
+    pub rejected: usize,
+    pub layer_count: usize,
+    pub root: String,
+    pub files: Vec<FileDigest>,
+}
+
+impl Manifest {
+    /// Reads `manifest.json` from a database directory.
+    ///
+    /// # Errors
+    /// Returns an error if the file is missing or not a valid manifest.
+    pub fn read(db_dir: &Path) -> Result<Self, MerkleError> {
+        let path = db_dir.join(MANIFEST_FILE);
+        let file = File::open(&path)
+            .map_err(|e| MerkleError::FileIo(format!("Failed to open {}: {e}", path.display())))?;
+        let manifest: Manifest = serde_json::from_reader(BufReader::new(file)).map_err(|e| {
+            MerkleError::CorruptedData(format!("Invalid manifest {}: {e}", path.display()))
+        })?;
+        if manifest.manifest_version != MANIFEST_VERSION {
+            return Err(MerkleError::CorruptedData(format!(
+                "Unsupported manifest version {} (expected {MANIFEST_VERSION})",
+                manifest.manifest_version
+            )));
+        }
+        Ok(manifest)
+    }
+
+    /// Writes the manifest as `manifest.json` in a database directory.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be created or written.
+    pub fn write(&self, db_dir: &Path) -> Result<(), MerkleError> {
+        let path = db_dir.join(MANIFEST_FILE);
+        let io_err = |e: std::io::Error| {
+            MerkleError::FileIo(format!("Failed to write {}: {e}", path.display()))
+        };
+        let mut writer = BufWriter::new(File::create(&path).map_err(io_err)?);
+        serde_json::to_writer_pretty(&mut writer, self)
+            .map_err(|e| MerkleError::Internal(format!("Failed to encode manifest: {e}")))?;
+        writer.write_all(b"\n").map_err(io_err)?;
+        writer.flush().map_err(io_err)
+    }
+}
+
+/// Hashes a file's contents, returning the `0x`-prefixed hash and the file size.
+///
+/// # Errors
+/// Returns an error if the file cannot be opened or read.
+pub fn hash_file(path: &Path) -> Result<(String, u64), MerkleError> {
+    let file = File::open(path)
+        .map_err(|e| MerkleError::FileIo(format!("Failed to open {}: {e}", path.display())))?;
+    let mut reader = BufReader::new(file);
+    let mut hasher = Keccak256::new();
+    let mut buf = vec![0u8; HASH_CHUNK_SIZE];
+    let mut size = 0u64;
+    loop {
+        let read = reader
+            .read(&mut buf)
+            .map_err(|e| MerkleError::FileIo(format!("Read failed in {}: {e}", path.display())))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+        size += read as u64;
+    }
+    Ok((format!("0x{}", hex::encode(hasher.finalize())), size))
+}
+
+/// Hashes `addresses.bin` and `layer00.bin` through the last layer of a database.
+///
+/// # Errors
+/// Returns an error if any of the files cannot be read.
+pub fn digest_database(db_dir: &Path, layer_count: usize) -> Result<Vec<FileDigest>, MerkleError> {
+    let names = std::iter::once("addresses.bin".to_string())
+        .chain((0..layer_count).map(|idx| format!("layer{idx:02}.bin")));
+    names
+        .map(|name| {
+            let (hash, size) = hash_file(&db_dir.join(&name))?;
+            Ok(FileDigest { name, size, hash })
+        })
+        .collect()
+}
+
+/// Lists the differences between two sets of file digests.
+#[must_use]
+pub fn compare_digests(expected: &[FileDigest], actual: &[FileDigest]) -> Vec<String> {
+    let mut mismatches = Vec::new();
+    for file in expected {
+        match actual.iter().find(|other| other.name == file.name) {
+            None => mismatches.push(format!("{}: missing", file.name)),
+            Some(other) if other.hash != file.hash || other.size != file.size => {
+                mismatches.push(format!(
+                    "{}: expected {} ({} bytes), got {} ({} bytes)",
+                    file.name, file.hash, file.size, other.hash, other.size
+                ));
+            }
+            Some(_) => {}
+        }
+    }
+    for file in actual {
+        if !expected.iter().any(|other| other.name == file.name) {
+            mismatches.push(format!("{}: unexpected file", file.name));
+        }
+    }
+    mismatches
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn digest(name: &str, hash: &str) -> FileDigest {
+        FileDigest {
+            name: name.to_string(),
+            size: 32,
+            hash: hash.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_hash_file_matches_keccak() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("data.bin");
+        std::fs::write(&path, b"merkle").unwrap();
+        let (hash, size) = hash_file(&path).unwrap();
+        assert_eq!(size, 6);
+        assert_eq!(
+            hash,
+            format!("0x{}", hex::encode(Keccak256::digest(b"merkle")))
+        );
+    }
+
+    #[test]
+    fn test_compare_digests() {
+        let expected = vec![
+            digest("addresses.bin", "0x01"),
+            digest("layer00.bin", "0x02"),
+        ];
+        assert!(compare_digests(&expected, &expected).is_empty());
+
+        let actual = vec![
+            digest("addresses.bin", "0x01"),
+            digest("layer01.bin", "0x03"),
+        ];
+        let mismatches = compare_digests(&expected, &actual);
+        assert_eq!(mismatches.len(), 2);
+        assert!(mismatches[0].contains("layer00.bin: missing"));
+        assert!(mismatches[1].contains("layer01.bin: unexpected"));
+    }
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let manifest = Manifest {
+            manifest_version: MANIFEST_VERSION,
+            tool: ToolInfo::current(),
+            created_at: 1,
+            hash_algorithm: HASH_ALGORITHM.to_string(),
+            inputs: Vec::new(),
+            exclusions: Vec::new(),
+            parameters: TreeParameters::new(None, None, false, true, Some(0.5)),
+            leaf_count: 1,
+            duplicates_removed: 0,
+            excluded: 0,
+            rejected: 0,
+            layer_count: 1,
+            root: "0x00".to_string(),
+            files: vec![digest("addresses.bin", "0x01")],
+        };
+        manifest.write(dir.path()).unwrap();
+        assert_eq!(Manifest::read(dir.path()).unwrap(), manifest);
+    }
+}
@@ -4,11 +4,16 @@
 //! - Building Merkle proofs for address inclusion
 //! - Parsing and validating Ethereum addresses
 //! - Reading and querying layer files
+//! - Reading single leaves and nodes by position
+//! - Holding the top layers of a tree in memory for repeated proofs
+//! - Validating a whole database by recomputing its layers
+//!
+//! Proof building and address lookups run inside `tracing` spans.
 
 use std::cmp::Ordering;
 use std::fmt;
 use std::fs::File;
-use std::io::{Read, Seek, SeekFrom};
+use std::io::{BufReader, Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
 
 use sha3::{Digest, Keccak256};
@@ -59,6 +64,24 @@ impl fmt::Display for MerkleError {
 
 impl std::error::Error for MerkleError {}
 
+impl MerkleError {
+    /// Short snake-case name of the variant, for logs and metrics.
+    #[must_use]
+    pub fn kind(&self) -> &'static str {
+        match self {
+            MerkleError::InvalidAddress(_) => "invalid_address",
+            MerkleError::InvalidHex(_) => "invalid_hex",
+            MerkleError::AddressNotFound => "address_not_found",
+            MerkleError::InvalidLayer(_) => "invalid_layer",
+            MerkleError::FileIo(_) => "file_io",
+            MerkleError::MissingLayer(_) => "missing_layer",
+            MerkleError::CorruptedData(_) => "corrupted_data",
+            MerkleError::IndexOutOfBounds { .. } => "index_out_of_bounds",
+            MerkleError::Internal(_) => "internal",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SiblingSide {
     Left,
@@ -100,24 +123,183 @@ pub struct ProofResult {
     pub steps: Vec<ProofStep>,
 }
 
+impl ProofResult {
+    /// Hashes the leaf with every sibling of the proof.
+    ///
+    /// This differs from `root` when the layer files changed while the proof
+    /// was read, or when they no longer match the `TopLayers` it was built
+    /// with; such a proof must not be served.
+    #[must_use]
+    pub fn computed_root(&self) -> [u8; HASH_SIZE] {
+        self.steps
+            .iter()
+            .fold(self.leaf, |node, step| match step.side {
+                SiblingSide::Left => hash_pair(&step.sibling_hash, &node),
+                SiblingSide::Right => hash_pair(&node, &step.sibling_hash),
+            })
+    }
+}
+
 /// Builds a Merkle proof for the given address.
 ///
 /// # Errors
 /// Returns an error if the address is invalid, not found in the database,
 /// or if the layer files are missing or corrupted.
 pub fn build_proof(db_dir: &Path, address_str: &str) -> Result<ProofResult, MerkleError> {
+    build_proof_with_layers(db_dir, address_str, &TopLayers::default())
+}
+
+/// Builds a Merkle proof for the given address, taking the nodes of the
+/// layers held by `top` from memory instead of their files.
+///
+/// # Errors
+/// Returns an error if the address is invalid, not found in the database,
+/// or if the layer files are missing or corrupted.
+#[tracing::instrument(skip(db_dir, top), fields(db_dir = %db_dir.display()))]
+pub fn build_proof_with_layers(
+    db_dir: &Path,
+    address_str: &str,
+    top: &TopLayers,
+) -> Result<ProofResult, MerkleError> {
     let address = parse_address(address_str)?;
     let addresses_path = db_dir.join("addresses.bin");
     let (index, steps, total) =
         find_address_index(&addresses_path, &address)?.ok_or(MerkleError::AddressNotFound)?;
+    proof_path(
+        db_dir,
+        top,
+        &address,
+        normalize_hex(address_str),
+        index,
+        steps,
+        total,
+    )
+}
 
+/// Builds a Merkle proof for the leaf at `index`, without knowing its address.
+///
+/// The proof reports zero lookups, since no search is needed.
+///
+/// # Errors
+/// Returns `IndexOutOfBounds` at level 0 if `index` is past the last leaf, or
+/// an error if the layer files are missing or corrupted.
+pub fn build_proof_at_index(db_dir: &Path, index: usize) -> Result<ProofResult, MerkleError> {
+    build_proof_at_index_with_layers(db_dir, index, &TopLayers::default())
+}
+
+/// Builds a Merkle proof for the leaf at `index`, taking the nodes of the
+/// layers held by `top` from memory instead of their files.
+///
+/// # Errors
+/// Returns `IndexOutOfBounds` at level 0 if `index` is past the last leaf, or
+/// an error if the layer files are missing or corrupted.
+pub fn build_proof_at_index_with_layers(
+    db_dir: &Path,
+    index: usize,
+    top: &TopLayers,
+) -> Result<ProofResult, MerkleError> {
+    let total = address_count(&db_dir.join("addresses.bin"))?;
+    let address = read_address(db_dir, index)?;
+    proof_path(db_dir, top, &address, to_hex20(&address), index, 0, total)
+}
+
+/// The top layers of a tree, held in memory.
+///
+/// Every proof reads one node from each layer, so keeping the small upper
+/// layers in memory saves most of the file reads of a proof.
+#[derive(Debug, Clone, Default)]
+pub struct TopLayers {
+    first_level: usize,
+    layers: Vec<Vec<[u8; HASH_SIZE]>>,
+}
+
+impl TopLayers {
+    /// Reads the top `count` layers of a database (all of them when the tree
+    /// is shorter).
+    ///
+    /// # Errors
+    /// Returns an error if a layer file cannot be read or is not a multiple of
+    /// `HASH_SIZE` bytes.
+    pub fn load(db_dir: &Path, count: usize) -> Result<Self, MerkleError> {
+        let layer_count = available_layers(db_dir).len();
+        let first_level = layer_count.saturating_sub(count);
+        let layers = (first_level..layer_count)
+            .map(|level| {
+                let path = db_dir.join(format!("layer{level:02}.bin"));
+                let data = read_layer(&path, level)?;
+                if data.len() % HASH_SIZE != 0 {
+                    return Err(MerkleError::CorruptedData(format!(
+                        "Layer file {} is not a multiple of {HASH_SIZE} bytes ({})",
+                        path.display(),
+                        data.len()
+                    )));
+                }
+                Ok(data
+                    .chunks_exact(HASH_SIZE)
+                    .map(|node| {
+                        let mut hash = [0u8; HASH_SIZE];
+                        hash.copy_from_slice(node);
+                        hash
+                    })
+                    .collect())
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(TopLayers {
+            first_level,
+            layers,
+        })
+    }
+
+    /// Number of layers held.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Number of nodes held across all layers.
+    #[must_use]
+    pub fn node_count(&self) -> usize {
+        self.layers.iter().map(Vec::len).sum()
+    }
+
+    /// The root node, when the layers are held.
+    #[must_use]
+    pub fn root(&self) -> Option<[u8; HASH_SIZE]> {
+        match self.layers.last()?.as_slice() {
+            [root] => Some(*root),
+            _ => None,
+        }
+    }
+
+    fn layer(&self, level: usize) -> Option<&[[u8; HASH_SIZE]]> {
+        level
+            .checked_sub(self.first_level)
+            .and_then(|offset| self.layers.get(offset))
+            .map(Vec::as_slice)
+    }
+}
+
+fn proof_path(
+    db_dir: &Path,
+    top: &TopLayers,
+    address: &[u8; ADDRESS_SIZE],
+    normalized_address: String,
+    index: usize,
+    steps: usize,
+    total: usize,
+) -> Result<ProofResult, MerkleError> {
     if total > MAX_ADDRESSES {
         return Err(MerkleError::InvalidLayer(format!(
             "Address count {total} exceeds maximum of {MAX_ADDRESSES}"
         )));
     }
 
-    let leaf_hash = hash_leaf(&address);
+    let leaf_hash = hash_leaf(address);
     let mut proof_steps = Vec::new();
     let mut level = 0usize;
     let mut path_index = index;
@@ -125,7 +307,12 @@ pub fn build_proof(db_dir: &Path, address_str: &str) -> Result<ProofResult, Merk
     loop {
         let filename = format!("layer{level:02}.bin");
         let layer_path = db_dir.join(filename);
-        if !layer_path.exists() {
+        let pinned = top.layer(level);
+        let read = |index: usize| match pinned {
+            Some(nodes) => Ok(nodes[index]),
+            None => read_node(&layer_path, index),
+        };
+        if pinned.is_none() && !layer_path.exists() {
             if level == 0 {
                 return Err(MerkleError::MissingLayer(
                     "No layer files found (expected layer00.bin, layer01.bin, ...)".to_string(),
@@ -138,7 +325,10 @@ pub fn build_proof(db_dir: &Path, address_str: &str) -> Result<ProofResult, Merk
             )));
         }
 
-        let node_count = layer_node_count(&layer_path)?;
+        let node_count = match pinned {
+            Some(nodes) => nodes.len(),
+            None => layer_node_count(&layer_path)?,
+        };
         if node_count == 0 {
             return Err(MerkleError::CorruptedData(format!(
                 "Layer {level:02} is empty"
@@ -153,9 +343,9 @@ pub fn build_proof(db_dir: &Path, address_str: &str) -> Result<ProofResult, Merk
         }
 
         if node_count == 1 {
-            let root = read_node(&layer_path, 0)?;
+            let root = read(0)?;
             return Ok(ProofResult {
-                normalized_address: normalize_hex(address_str),
+                normalized_address,
                 index,
                 total,
                 lookups: steps,
@@ -166,15 +356,15 @@ pub fn build_proof(db_dir: &Path, address_str: &str) -> Result<ProofResult, Merk
             });
         }
 
-        let is_left = path_index % 2 == 0;
+        let is_left = path_index.is_multiple_of(2);
         let (sibling_idx, sibling_hash) = if is_left {
             if path_index + 1 >= node_count {
-                (path_index, read_node(&layer_path, path_index)?)
+                (path_index, read(path_index)?)
             } else {
-                (path_index + 1, read_node(&layer_path, path_index + 1)?)
+                (path_index + 1, read(path_index + 1)?)
             }
         } else {
-            (path_index - 1, read_node(&layer_path, path_index - 1)?)
+            (path_index - 1, read(path_index - 1)?)
         };
 
         proof_steps.push(ProofStep {
@@ -215,6 +405,28 @@ pub fn parse_address(raw: &str) -> Result<[u8; ADDRESS_SIZE], MerkleError> {
     Ok(buf)
 }
 
+/// Parses a 32-byte hash, such as a Merkle root, from a hex string.
+///
+/// # Errors
+/// Returns an error if the hash is not 64 hex characters after an optional `0x`.
+pub fn parse_hash(raw: &str) -> Result<[u8; HASH_SIZE], MerkleError> {
+    let cleaned = raw
+        .strip_prefix("0x")
+        .or_else(|| raw.strip_prefix("0X"))
+        .unwrap_or(raw);
+    if cleaned.len() != HASH_SIZE * 2 {
+        return Err(MerkleError::InvalidHex(format!(
+            "Hash must be {} hex characters after 0x",
+            HASH_SIZE * 2
+        )));
+    }
+
+    let mut buf = [0u8; HASH_SIZE];
+    hex::decode_to_slice(cleaned, &mut buf)
+        .map_err(|_| MerkleError::InvalidHex("Invalid hex in hash".to_string()))?;
+    Ok(buf)
+}
+
 #[must_use]
 pub fn normalize_hex(raw: &str) -> String {
     let lower = raw.to_ascii_lowercase();
@@ -284,6 +496,73 @@ pub fn read_node(path: &Path, index: usize) -> Result<[u8; HASH_SIZE], MerkleErr
     Ok(buf)
 }
 
+/// Reads the address at `index` in `addresses.bin`.
+///
+/// # Errors
+/// Returns `IndexOutOfBounds` at level 0 if `index` is past the last address,
+/// or an error if the file cannot be read.
+pub fn read_address(db_dir: &Path, index: usize) -> Result<[u8; ADDRESS_SIZE], MerkleError> {
+    let path = db_dir.join("addresses.bin");
+    let count = address_count(&path)?;
+    if index >= count {
+        return Err(MerkleError::IndexOutOfBounds {
+            level: 0,
+            index,
+            count,
+        });
+    }
+    let mut file = File::open(&path)
+        .map_err(|e| MerkleError::FileIo(format!("Unable to open {}: {e}", path.display())))?;
+    file.seek(SeekFrom::Start(index as u64 * ADDRESS_SIZE as u64))
+        .map_err(|e| MerkleError::FileIo(format!("Seek failed in {}: {e}", path.display())))?;
+    let mut buf = [0u8; ADDRESS_SIZE];
+    file.read_exact(&mut buf)
+        .map_err(|e| MerkleError::FileIo(format!("Read failed in {}: {e}", path.display())))?;
+    Ok(buf)
+}
+
+/// Reads the node at `index` in the layer file for `level`.
+///
+/// # Errors
+/// Returns `MissingLayer` if the tree has no such level, `IndexOutOfBounds`
+/// if `index` is past the last node of the layer, or an error if the file
+/// cannot be read.
+pub fn read_layer_node(
+    db_dir: &Path,
+    level: usize,
+    index: usize,
+) -> Result<[u8; HASH_SIZE], MerkleError> {
+    let path = db_dir.join(format!("layer{level:02}.bin"));
+    if !path.exists() {
+        return Err(MerkleError::MissingLayer(format!(
+            "No layer file for level {level} (expected {})",
+            path.display()
+        )));
+    }
+    let count = layer_node_count(&path)?;
+    if index >= count {
+        return Err(MerkleError::IndexOutOfBounds {
+            level,
+            index,
+            count,
+        });
+    }
+    read_node(&path, index)
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn address_count(path: &Path) -> Result<usize, MerkleError> {
+    let len = std::fs::metadata(path)
+        .map_err(|e| MerkleError::FileIo(format!("Failed to stat {}: {e}", path.display())))?
+        .len();
+    if len % ADDRESS_SIZE as u64 != 0 {
+        return Err(MerkleError::CorruptedData(format!(
+            "addresses.bin is not a multiple of {ADDRESS_SIZE} bytes ({len})"
+        )));
+    }
+    Ok((len / ADDRESS_SIZE as u64) as usize)
+}
+
 /// Performs a binary search for an address in the sorted addresses.bin file.
 ///
 /// Returns `Some((index, lookups, total))` if found, where `index` is the
@@ -295,6 +574,7 @@ pub fn read_node(path: &Path, index: usize) -> Result<[u8; HASH_SIZE], MerkleErr
 /// is not a multiple of `ADDRESS_SIZE` (20 bytes), or if the address count
 /// exceeds `MAX_ADDRESSES`.
 #[allow(clippy::cast_possible_truncation)]
+#[tracing::instrument(skip_all, fields(path = %path.display(), lookups = tracing::field::Empty))]
 pub fn find_address_index(
     path: &Path,
     target: &[u8; ADDRESS_SIZE],
@@ -350,10 +630,14 @@ pub fn find_address_index(
             Ordering::Greater => {
                 high = mid;
             }
-            Ordering::Equal => return Ok(Some((mid, steps, total))),
+            Ordering::Equal => {
+                tracing::Span::current().record("lookups", steps);
+                return Ok(Some((mid, steps, total)));
+            }
         }
     }
 
+    tracing::Span::current().record("lookups", steps);
     Ok(None)
 }
 
@@ -411,6 +695,110 @@ pub fn available_layers(db_dir: &Path) -> Vec<PathBuf> {
     layers
 }
 
+/// Reads the root hash and its level from the last layer file of a database.
+///
+/// # Errors
+/// Returns an error if no layer files exist or if the last layer does not
+/// hold exactly one node.
+pub fn read_root(db_dir: &Path) -> Result<([u8; HASH_SIZE], usize), MerkleError> {
+    let layers = available_layers(db_dir);
+    let last = layers
+        .last()
+        .ok_or_else(|| MerkleError::MissingLayer("No layer files found".to_string()))?;
+    let level = layers.len() - 1;
+    let count = layer_node_count(last)?;
+    if count != 1 {
+        return Err(MerkleError::CorruptedData(format!(
+            "Top layer {level:02} holds {count} nodes (expected 1)"
+        )));
+    }
+    Ok((read_node(last, 0)?, level))
+}
+
+/// Root and shape of a database that passed [`validate_database`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DatabaseInfo {
+    pub root: [u8; HASH_SIZE],
+    pub leaf_count: usize,
+    pub layer_count: usize,
+}
+
+/// Recomputes every layer of a database from `addresses.bin` and checks that
+/// the layer files match.
+///
+/// # Errors
+/// Returns an error if `addresses.bin` is empty, unsorted or has duplicates,
+/// if a layer file is missing, has the wrong size or holds a wrong hash, or
+/// if files exist above the root layer.
+pub fn validate_database(db_dir: &Path) -> Result<DatabaseInfo, MerkleError> {
+    ensure_db_present(db_dir)?;
+    let addresses = crate::setops::read_address_set(&db_dir.join("addresses.bin"))?;
+    if addresses.is_empty() {
+        return Err(MerkleError::CorruptedData(
+            "addresses.bin holds no addresses".to_string(),
+        ));
+    }
+
+    let mut expected: Vec<[u8; HASH_SIZE]> = addresses.iter().map(hash_leaf).collect();
+    let mut level = 0usize;
+    loop {
+        let path = db_dir.join(format!("layer{level:02}.bin"));
+        let actual = read_layer(&path, level)?;
+        if actual.len() != expected.len() * HASH_SIZE {
+            return Err(MerkleError::CorruptedData(format!(
+                "Layer {level:02} holds {} bytes, expected {} nodes",
+                actual.len(),
+                expected.len()
+            )));
+        }
+        if let Some(index) = actual
+            .chunks_exact(HASH_SIZE)
+            .zip(&expected)
+            .position(|(node, hash)| node != hash.as_slice())
+        {
+            return Err(MerkleError::CorruptedData(format!(
+                "Layer {level:02} node {index} does not match the recomputed hash"
+            )));
+        }
+        if expected.len() == 1 {
+            break;
+        }
+        expected = expected
+            .chunks(2)
+            .map(|pair| hash_pair(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+            .collect();
+        level += 1;
+        if level >= MAX_LAYERS {
+            return Err(MerkleError::CorruptedData(format!(
+                "Tree exceeds {MAX_LAYERS} layers"
+            )));
+        }
+    }
+
+    let layer_count = level + 1;
+    if db_dir.join(format!("layer{layer_count:02}.bin")).exists() {
+        return Err(MerkleError::CorruptedData(format!(
+            "Unexpected layer{layer_count:02}.bin above the root layer"
+        )));
+    }
+    Ok(DatabaseInfo {
+        root: expected[0],
+        leaf_count: addresses.len(),
+        layer_count,
+    })
+}
+
+fn read_layer(path: &Path, level: usize) -> Result<Vec<u8>, MerkleError> {
+    let file = File::open(path).map_err(|e| {
+        MerkleError::MissingLayer(format!("Layer {level:02} at {}: {e}", path.display()))
+    })?;
+    let mut data = Vec::new();
+    BufReader::new(file)
+        .read_to_end(&mut data)
+        .map_err(|e| MerkleError::FileIo(format!("Read failed in {}: {e}", path.display())))?;
+    Ok(data)
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used, clippy::expect_used)]
 mod tests {
@@ -459,6 +847,18 @@ mod tests {
         assert!(err_msg.contains("Invalid hex"));
     }
 
+    #[test]
+    fn test_parse_hash() {
+        let hash = parse_hash(&format!("0x{}", "ab".repeat(32))).unwrap();
+        assert_eq!(hash, [0xabu8; HASH_SIZE]);
+        assert!(parse_hash(&"ab".repeat(32)).is_ok());
+        assert!(parse_hash("0x1234")
+            .unwrap_err()
+            .to_string()
+            .contains("64 hex"));
+        assert!(parse_hash(&format!("0x{}", "zz".repeat(32))).is_err());
+    }
+
     #[test]
     fn test_normalize_hex() {
         assert_eq!(normalize_hex("0x1234"), "0x1234");
@@ -503,4 +903,144 @@ mod tests {
         let result = layer_node_count(Path::new("/nonexistent/path"));
         assert!(result.is_err());
     }
+
+    /// Writes a three-leaf database and returns its layers, leaves first.
+    fn write_three_leaf_db(dir: &Path) -> Vec<Vec<[u8; HASH_SIZE]>> {
+        let addresses = [
+            [0x01u8; ADDRESS_SIZE],
+            [0x02u8; ADDRESS_SIZE],
+            [0x03u8; ADDRESS_SIZE],
+        ];
+        let leaves: Vec<[u8; HASH_SIZE]> = addresses.iter().map(hash_leaf).collect();
+        let branches = vec![
+            hash_pair(&leaves[0], &leaves[1]),
+            hash_pair(&leaves[2], &leaves[2]),
+        ];
+        let root = vec![hash_pair(&branches[0], &branches[1])];
+        std::fs::write(dir.join("addresses.bin"), addresses.concat()).unwrap();
+        let layers = vec![leaves, branches, root];
+        for (level, layer) in layers.iter().enumerate() {
+            std::fs::write(dir.join(format!("layer{level:02}.bin")), layer.concat()).unwrap();
+        }
+        layers
+    }
+
+    #[test]
+    fn test_validate_database() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let layers = write_three_leaf_db(dir.path());
+        let root = layers[2][0];
+
+        let info = validate_database(dir.path()).unwrap();
+        assert_eq!(info.root, root);
+        assert_eq!(info.leaf_count, 3);
+        assert_eq!(info.layer_count, 3);
+
+        std::fs::write(dir.path().join("layer03.bin"), root).unwrap();
+        assert!(validate_database(dir.path()).is_err());
+        std::fs::remove_file(dir.path().join("layer03.bin")).unwrap();
+
+        std::fs::write(
+            dir.path().join("layer01.bin"),
+            [layers[1][1], layers[1][0]].concat(),
+        )
+        .unwrap();
+        let err = validate_database(dir.path()).unwrap_err();
+        assert!(err.to_string().contains("Layer 01 node 0"));
+    }
+
+    #[test]
+    fn test_read_by_index() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let layers = write_three_leaf_db(dir.path());
+
+        assert_eq!(read_address(dir.path(), 2).unwrap(), [0x03u8; ADDRESS_SIZE]);
+        assert!(matches!(
+            read_address(dir.path(), 3),
+            Err(MerkleError::IndexOutOfBounds {
+                level: 0,
+                index: 3,
+                count: 3
+            })
+        ));
+        assert_eq!(read_layer_node(dir.path(), 1, 1).unwrap(), layers[1][1]);
+        assert!(matches!(
+            read_layer_node(dir.path(), 2, 1),
+            Err(MerkleError::IndexOutOfBounds { level: 2, .. })
+        ));
+        assert!(matches!(
+            read_layer_node(dir.path(), 3, 0),
+            Err(MerkleError::MissingLayer(_))
+        ));
+
+        let by_index = build_proof_at_index(dir.path(), 1).unwrap();
+        let by_address = build_proof(dir.path(), &to_hex20(&[0x02u8; ADDRESS_SIZE])).unwrap();
+        assert_eq!(by_index.normalized_address, by_address.normalized_address);
+        assert_eq!(by_index.root, layers[2][0]);
+        assert_eq!(by_index.lookups, 0);
+        let siblings = |proof: &ProofResult| -> Vec<[u8; HASH_SIZE]> {
+            proof.steps.iter().map(|step| step.sibling_hash).collect()
+        };
+        assert_eq!(siblings(&by_index), siblings(&by_address));
+        assert!(build_proof_at_index(dir.path(), 3).is_err());
+    }
+
+    #[test]
+    fn test_proof_with_top_layers() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let layers = write_three_leaf_db(dir.path());
+        let address = to_hex20(&[0x03u8; ADDRESS_SIZE]);
+        let from_disk = build_proof(dir.path(), &address).unwrap();
+
+        let top = TopLayers::load(dir.path(), 2).unwrap();
+        assert_eq!(top.len(), 2);
+        assert_eq!(top.node_count(), 3);
+        assert_eq!(top.root(), Some(layers[2][0]));
+        let all = TopLayers::load(dir.path(), 10).unwrap();
+        assert_eq!(all.len(), 3);
+
+        // Held layers are not read from disk again.
+        std::fs::remove_file(dir.path().join("layer01.bin")).unwrap();
+        std::fs::remove_file(dir.path().join("layer02.bin")).unwrap();
+        let pinned = build_proof_with_layers(dir.path(), &address, &top).unwrap();
+        assert_eq!(pinned.root, from_disk.root);
+        let by_index = build_proof_at_index_with_layers(dir.path(), 2, &top).unwrap();
+        assert_eq!(by_index.normalized_address, from_disk.normalized_address);
+        assert_eq!(by_index.root, from_disk.root);
+        assert_eq!(pinned.root_level, 2);
+        let siblings = |proof: &ProofResult| -> Vec<[u8; HASH_SIZE]> {
+            proof.steps.iter().map(|step| step.sibling_hash).collect()
+        };
+        assert_eq!(siblings(&pinned), siblings(&from_disk));
+        assert_eq!(pinned.computed_root(), pinned.root);
+        assert!(build_proof(dir.path(), &address).is_err());
+    }
+
+    #[test]
+    fn test_computed_root_detects_stale_top_layers() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let layers = write_three_leaf_db(dir.path());
+        let address = to_hex20(&[0x03u8; ADDRESS_SIZE]);
+        let top = TopLayers::load(dir.path(), 1).unwrap();
+        assert_eq!(
+            build_proof(dir.path(), &address).unwrap().computed_root(),
+            layers[2][0]
+        );
+
+        // The lower layers are rewritten in place; the pinned root is stale.
+        let mut level0 = layers[0].clone();
+        level0[0] = [0xAA; HASH_SIZE];
+        let mut level1 = layers[1].clone();
+        level1[0] = hash_pair(&level0[0], &level0[1]);
+        for (level, nodes) in [(0, &level0), (1, &level1)] {
+            std::fs::write(
+                dir.path().join(format!("layer{level:02}.bin")),
+                nodes.concat(),
+            )
+            .unwrap();
+        }
+        let mixed = build_proof_with_layers(dir.path(), &address, &top).unwrap();
+        assert_eq!(mixed.root, layers[2][0]);
+        assert_ne!(mixed.computed_root(), mixed.root);
+    }
 }
@@ -0,0 +1,202 @@
+//! Machine-readable ingestion reports.
+//!
+//! This module provides:
+//! - The JSON schema written by `txt_to_bin --report`
+//! - Grouping of duplicate addresses with every line they appeared on
+//! - Helpers for recording stage timings
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::merkle::to_hex20;
+use crate::ADDRESS_SIZE;
+
+/// A parsed address paired with the input line it came from.
+pub type LineEntry = ([u8; ADDRESS_SIZE], usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportStatus {
+    Ok,
+    Failed,
+}
+
+/// An input line that did not yield a valid address.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RejectedLine {
+    pub input: String,
+    pub line: usize,
+    /// The extracted value, when the record itself could be read.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    pub reason: String,
+}
+
+/// An address that appeared on more than one input line.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DuplicateAddress {
+    pub input: String,
+    pub address: String,
+    pub lines: Vec<usize>,
+}
+
+/// One input list of a build and the size of the set after applying it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InputSummary {
+    pub input: String,
+    pub format: String,
+    /// Set operation combining this input with the previous result; absent
+    /// for the base input.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operation: Option<String>,
+    /// Unique valid addresses read from this input.
+    pub addresses: usize,
+    /// Addresses in the running result after this input was applied.
+    pub result: usize,
+}
+
+/// An address left out of the tree by an exclusion source.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExcludedEntry {
+    pub address: String,
+    pub source: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ReportCounts {
+    /// Non-empty records read from all list inputs.
+    pub records: usize,
+    pub valid: usize,
+    pub rejected: usize,
+    pub duplicates_removed: usize,
+    pub excluded: usize,
+    /// Addresses written to `addresses.bin`.
+    pub leaves: usize,
+}
+
+/// Wall-clock time spent in each stage, in milliseconds.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ReportTimings {
+    pub read_ms: u64,
+    pub sort_ms: u64,
+    pub build_ms: u64,
+    pub write_ms: u64,
+    pub total_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IngestReport {
+    pub status: ReportStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub input: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    pub output_dir: String,
+    pub counts: ReportCounts,
+    pub timings: ReportTimings,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub root: Option<String>,
+    pub layers: usize,
+    pub inputs: Vec<InputSummary>,
+    pub rejected: Vec<RejectedLine>,
+    pub duplicates: Vec<DuplicateAddress>,
+    pub excluded: Vec<ExcludedEntry>,
+}
+
+impl IngestReport {
+    #[must_use]
+    pub fn new(input: impl Into<String>, output_dir: impl Into<String>) -> Self {
+        IngestReport {
+            status: ReportStatus::Ok,
+            error: None,
+            input: input.into(),
+            format: None,
+            output_dir: output_dir.into(),
+            counts: ReportCounts::default(),
+            timings: ReportTimings::default(),
+            root: None,
+            layers: 0,
+            inputs: Vec::new(),
+            rejected: Vec::new(),
+            duplicates: Vec::new(),
+            excluded: Vec::new(),
+        }
+    }
+
+    /// Marks the report as failed with the given error message.
+    pub fn fail(&mut self, error: impl Into<String>) {
+        self.status = ReportStatus::Failed;
+        self.error = Some(error.into());
+    }
+
+    /// Writes the report as pretty-printed JSON.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be created or written.
+    pub fn write_json(&self, path: &Path) -> std::io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.write_all(b"\n")?;
+        writer.flush()
+    }
+}
+
+/// Groups repeated addresses in a list sorted by `(address, line)`.
+#[must_use]
+pub fn collect_duplicates(input: &str, sorted: &[LineEntry]) -> Vec<DuplicateAddress> {
+    sorted
+        .chunk_by(|a, b| a.0 == b.0)
+        .filter(|group| group.len() > 1)
+        .map(|group| DuplicateAddress {
+            input: input.to_string(),
+            address: to_hex20(&group[0].0),
+            lines: group.iter().map(|(_, line)| *line).collect(),
+        })
+        .collect()
+}
+
+#[must_use]
+pub fn duration_ms(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_duplicates() {
+        let a = [0x0au8; ADDRESS_SIZE];
+        let b = [0x0bu8; ADDRESS_SIZE];
+        let c = [0x0cu8; ADDRESS_SIZE];
+        let sorted = vec![(a, 2), (a, 7), (a, 9), (b, 1), (c, 3), (c, 4)];
+        let duplicates = collect_duplicates("in.txt", &sorted);
+        assert_eq!(duplicates.len(), 2);
+        assert_eq!(duplicates[0].address, to_hex20(&a));
+        assert_eq!(duplicates[0].lines, vec![2, 7, 9]);
+        assert_eq!(duplicates[1].lines, vec![3, 4]);
+    }
+
+    #[test]
+    fn test_report_serialization() {
+        let mut report = IngestReport::new("in.txt", "merkledb");
+        report.rejected.push(RejectedLine {
+            input: "in.txt".to_string(),
+            line: 3,
+            value: None,
+            reason: "Invalid JSON".to_string(),
+        });
+        report.fail("1 invalid line");
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["status"], "failed");
+        assert_eq!(json["rejected"][0]["line"], 3);
+        assert!(json["rejected"][0].get("value").is_none());
+        assert!(json.get("root").is_none());
+    }
+}
@@ -0,0 +1,233 @@
+//! Set algebra over sorted address lists.
+//!
+//! This module provides functions for:
+//! - Merge-based union, intersection and difference of sorted, deduplicated lists
+//! - Loading the sorted address set of an existing Merkle database
+//!
+//! `addresses.bin` is already sorted and deduplicated, so existing databases
+//! can be combined in a single linear pass without re-sorting.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::merkle::MerkleError;
+use crate::{ADDRESS_SIZE, MAX_ADDRESSES};
+
+pub type Address = [u8; ADDRESS_SIZE];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOp {
+    Union,
+    Intersect,
+    Subtract,
+}
+
+impl SetOp {
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SetOp::Union => "union",
+            SetOp::Intersect => "intersect",
+            SetOp::Subtract => "subtract",
+        }
+    }
+
+    /// Combines two sorted, deduplicated lists.
+    #[must_use]
+    pub fn apply(&self, left: &[Address], right: &[Address]) -> Vec<Address> {
+        match self {
+            SetOp::Union => union(left, right),
+            SetOp::Intersect => intersection(left, right),
+            SetOp::Subtract => difference(left, right),
+        }
+    }
+}
+
+impl FromStr for SetOp {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw {
+            "union" | "or" => Ok(SetOp::Union),
+            "intersect" | "and" => Ok(SetOp::Intersect),
+            "subtract" | "minus" => Ok(SetOp::Subtract),
+            other => Err(format!("Unknown set operation '{other}'")),
+        }
+    }
+}
+
+impl fmt::Display for SetOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Returns every address present in either list.
+#[must_use]
+pub fn union(left: &[Address], right: &[Address]) -> Vec<Address> {
+    let mut out = Vec::with_capacity(left.len() + right.len());
+    let (mut i, mut j) = (0usize, 0usize);
+    while i < left.len() && j < right.len() {
+        match left[i].cmp(&right[j]) {
+            Ordering::Less => {
+                out.push(left[i]);
+                i += 1;
+            }
+            Ordering::Greater => {
+                out.push(right[j]);
+                j += 1;
+            }
+            Ordering::Equal => {
+                out.push(left[i]);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    out.extend_from_slice(&left[i..]);
+    out.extend_from_slice(&right[j..]);
+    out
+}
+
+/// Returns the addresses present in both lists.
+#[must_use]
+pub fn intersection(left: &[Address], right: &[Address]) -> Vec<Address> {
+    let mut out = Vec::with_capacity(left.len().min(right.len()));
+    let (mut i, mut j) = (0usize, 0usize);
+    while i < left.len() && j < right.len() {
+        match left[i].cmp(&right[j]) {
+            Ordering::Less => i += 1,
+            Ordering::Greater => j += 1,
+            Ordering::Equal => {
+                out.push(left[i]);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Returns the addresses of `left` that are not in `right`.
+#[must_use]
+pub fn difference(left: &[Address], right: &[Address]) -> Vec<Address> {
+    let mut out = Vec::with_capacity(left.len());
+    let mut j = 0usize;
+    for address in left {
+        while j < right.len() && right[j] < *address {
+            j += 1;
+        }
+        if j >= right.len() || right[j] != *address {
+            out.push(*address);
+        }
+    }
+    out
+}
+
+/// Reads a sorted, deduplicated `addresses.bin` file into memory.
+///
+/// # Errors
+/// Returns an error if the file cannot be read, is not a multiple of
+/// `ADDRESS_SIZE` bytes, holds more than `MAX_ADDRESSES` entries, or is not
+/// strictly sorted.
+#[allow(clippy::cast_possible_truncation)]
+pub fn read_address_set(path: &Path) -> Result<Vec<Address>, MerkleError> {
+    let file = File::open(path)
+        .map_err(|e| MerkleError::FileIo(format!("Failed to open {}: {e}", path.display())))?;
+    let len = file
+        .metadata()
+        .map_err(|e| MerkleError::FileIo(format!("Failed to stat {}: {e}", path.display())))?
+        .len();
+    if len % ADDRESS_SIZE as u64 != 0 {
+        return Err(MerkleError::CorruptedData(format!(
+            "{} is not a multiple of {ADDRESS_SIZE} bytes ({len})",
+            path.display()
+        )));
+    }
+    let total = (len / ADDRESS_SIZE as u64) as usize;
+    if total > MAX_ADDRESSES {
+        return Err(MerkleError::CorruptedData(format!(
+            "Address count {total} exceeds maximum of {MAX_ADDRESSES}"
+        )));
+    }
+
+    let mut reader = BufReader::new(file);
+    let mut addresses = Vec::with_capacity(total);
+    let mut buf = [0u8; ADDRESS_SIZE];
+    for idx in 0..total {
+        reader
+            .read_exact(&mut buf)
+            .map_err(|e| MerkleError::FileIo(format!("Read failed in {}: {e}", path.display())))?;
+        if addresses.last().is_some_and(|prev| *prev >= buf) {
+            return Err(MerkleError::CorruptedData(format!(
+                "{} is not sorted and deduplicated at index {idx}",
+                path.display()
+            )));
+        }
+        addresses.push(buf);
+    }
+    Ok(addresses)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn set(values: &[u8]) -> Vec<Address> {
+        values.iter().map(|v| [*v; ADDRESS_SIZE]).collect()
+    }
+
+    #[test]
+    fn test_union() {
+        assert_eq!(
+            union(&set(&[1, 3, 5]), &set(&[2, 3, 6])),
+            set(&[1, 2, 3, 5, 6])
+        );
+        assert_eq!(union(&set(&[]), &set(&[4])), set(&[4]));
+    }
+
+    #[test]
+    fn test_intersection() {
+        assert_eq!(
+            intersection(&set(&[1, 3, 5, 7]), &set(&[3, 4, 7])),
+            set(&[3, 7])
+        );
+        assert!(intersection(&set(&[1]), &set(&[])).is_empty());
+    }
+
+    #[test]
+    fn test_difference() {
+        assert_eq!(
+            difference(&set(&[1, 3, 5, 7]), &set(&[0, 3, 7, 9])),
+            set(&[1, 5])
+        );
+        assert_eq!(difference(&set(&[1, 2]), &set(&[])), set(&[1, 2]));
+    }
+
+    #[test]
+    fn test_set_op_from_str() {
+        assert_eq!("minus".parse::<SetOp>().unwrap(), SetOp::Subtract);
+        assert!("xor".parse::<SetOp>().is_err());
+    }
+
+    #[test]
+    fn test_read_address_set_rejects_unsorted() {
+        let mut temp = NamedTempFile::new().unwrap();
+        temp.write_all(&[0x02u8; ADDRESS_SIZE]).unwrap();
+        temp.write_all(&[0x01u8; ADDRESS_SIZE]).unwrap();
+        let err = read_address_set(temp.path()).unwrap_err();
+        assert!(err.to_string().contains("not sorted"));
+
+        let mut sorted = NamedTempFile::new().unwrap();
+        sorted.write_all(&[0x01u8; ADDRESS_SIZE]).unwrap();
+        sorted.write_all(&[0x02u8; ADDRESS_SIZE]).unwrap();
+        assert_eq!(read_address_set(sorted.path()).unwrap(), set(&[1, 2]));
+    }
+}
@@ -0,0 +1,406 @@
+//! EIP-191 signatures over Merkle roots.
+//!
+//! This module provides functions for:
+//! - Hashing and signing messages with the `personal_sign` (EIP-191) scheme
+//! - Recovering and verifying the signer of a 65-byte `r || s || v` signature
+//! - Attesting a database root and manifest in `signature.json`
+//!
+//! Signatures use the same encoding as wallets (`v` is 27 or 28), so clients
+//! can check them with `ethers.verifyMessage` or viem's `recoverMessageAddress`.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+use k256::ecdsa::{RecoveryId, Signature, SigningKey, VerifyingKey};
+use k256::SecretKey;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+
+use crate::manifest::{hash_file, MANIFEST_FILE};
+use crate::merkle::{parse_address, read_root, to_hex32};
+use crate::{public_key_address, to_checksum_address, ADDRESS_SIZE, HASH_SIZE};
+
+pub const SIGNATURE_FILE: &str = "signature.json";
+pub const SIGNATURE_SIZE: usize = 65;
+
+const EIP191_PREFIX: &str = "\x19Ethereum Signed Message:\n";
+const ATTESTATION_HEADER: &str = "merklebuilder root attestation";
+
+#[derive(Debug, Clone)]
+pub enum SignatureError {
+    InvalidKey(String),
+    InvalidSignature(String),
+    Recovery(String),
+    Mismatch(String),
+    FileIo(String),
+}
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignatureError::InvalidKey(msg) => write!(f, "Invalid signing key: {msg}"),
+            SignatureError::InvalidSignature(msg) => write!(f, "Invalid signature: {msg}"),
+            SignatureError::Recovery(msg) => write!(f, "Signer recovery failed: {msg}"),
+            SignatureError::Mismatch(msg) => write!(f, "Signature mismatch: {msg}"),
+            SignatureError::FileIo(msg) => write!(f, "File I/O error: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for SignatureError {}
+
+/// Hashes a message the way `personal_sign` does:
+/// `keccak256("\x19Ethereum Signed Message:\n" || len(message) || message)`.
+#[must_use]
+pub fn eip191_hash(message: &[u8]) -> [u8; HASH_SIZE] {
+    let mut hasher = Keccak256::new();
+    hasher.update(EIP191_PREFIX.as_bytes());
+    hasher.update(message.len().to_string().as_bytes());
+    hasher.update(message);
+    hasher.finalize().into()
+}
+
+/// Parses a hex-encoded 32-byte secret key, with or without `0x`.
+///
+/// # Errors
+/// Returns an error if the value is not valid hex or not a valid scalar.
+pub fn parse_secret_key(raw: &str) -> Result<SecretKey, SignatureError> {
+    let trimmed = raw.trim();
+    let cleaned = trimmed.strip_prefix("0x").unwrap_or(trimmed);
+    let bytes = hex::decode(cleaned)
+        .map_err(|_| SignatureError::InvalidKey("Key must be hex".to_string()))?;
+    SecretKey::from_slice(&bytes).map_err(|_| {
+        SignatureError::InvalidKey("Key must be a 32-byte secp256k1 scalar".to_string())
+    })
+}
+
+/// Parses a hex-encoded 65-byte `r || s || v` signature.
+///
+/// # Errors
+/// Returns an error if the value is not valid hex or not 65 bytes long.
+pub fn parse_signature(raw: &str) -> Result<[u8; SIGNATURE_SIZE], SignatureError> {
+    let cleaned = raw.strip_prefix("0x").unwrap_or(raw);
+    let mut buf = [0u8; SIGNATURE_SIZE];
+    hex::decode_to_slice(cleaned, &mut buf).map_err(|_| {
+        SignatureError::InvalidSignature(format!("Expected {SIGNATURE_SIZE} hex-encoded bytes"))
+    })?;
+    Ok(buf)
+}
+
+/// Signs a 32-byte digest, returning `r || s || v` with `v` in `{27, 28}`.
+///
+/// # Errors
+/// Returns an error if the signing operation fails.
+pub fn sign_prehash(
+    secret_key: &SecretKey,
+    digest: &[u8; HASH_SIZE],
+) -> Result<[u8; SIGNATURE_SIZE], SignatureError> {
+    let signing_key = SigningKey::from(secret_key);
+    let (signature, recovery_id) = signing_key
+        .sign_prehash_recoverable(digest)
+        .map_err(|e| SignatureError::InvalidKey(e.to_string()))?;
+    let mut out = [0u8; SIGNATURE_SIZE];
+    out[..64].copy_from_slice(&signature.to_bytes());
+    out[64] = 27 + recovery_id.to_byte();
+    Ok(out)
+}
+
+/// Recovers the address that signed a 32-byte digest.
+///
+/// # Errors
+/// Returns an error if the signature is malformed, uses a high `s` value,
+/// or does not recover to a valid public key.
+pub fn recover_prehash_signer(
+    digest: &[u8; HASH_SIZE],
+    signature: &[u8; SIGNATURE_SIZE],
+) -> Result<[u8; ADDRESS_SIZE], SignatureError> {
+    let v = match signature[64] {
+        v @ (27 | 28) => v - 27,
+        v @ (0 | 1) => v,
+        other => {
+            return Err(SignatureError::InvalidSignature(format!(
+                "Unsupported recovery byte {other}"
+            )))
+        }
+    };
+    let recovery_id = RecoveryId::from_byte(v)
+        .ok_or_else(|| SignatureError::InvalidSignature("Invalid recovery id".to_string()))?;
+    let parsed = Signature::from_slice(&signature[..64])
+        .map_err(|e| SignatureError::InvalidSignature(e.to_string()))?;
+    if parsed.normalize_s().is_some() {
+        return Err(SignatureError::InvalidSignature(
+            "Signature s value is not in the lower half of the curve order".to_string(),
+        ));
+    }
+    let verifying_key = VerifyingKey::recover_from_prehash(digest, &parsed, recovery_id)
+        .map_err(|e| SignatureError::Recovery(e.to_string()))?;
+    public_key_address(&verifying_key.into()).map_err(|e| SignatureError::Recovery(e.to_string()))
+}
+
+/// Signs a message with the `personal_sign` scheme.
+///
+/// # Errors
+/// Returns an error if the signing operation fails.
+pub fn sign_personal_message(
+    secret_key: &SecretKey,
+    message: &[u8],
+) -> Result<[u8; SIGNATURE_SIZE], SignatureError> {
+    sign_prehash(secret_key, &eip191_hash(message))
+}
+
+/// Recovers the address that produced a `personal_sign` signature.
+///
+/// # Errors
+/// Returns an error if the signature is malformed or recovery fails.
+pub fn recover_personal_signer(
+    message: &[u8],
+    signature: &[u8; SIGNATURE_SIZE],
+) -> Result<[u8; ADDRESS_SIZE], SignatureError> {
+    recover_prehash_signer(&eip191_hash(message), signature)
+}
+
+/// Checks that a `personal_sign` signature was produced by `expected`.
+///
+/// # Errors
+/// Returns an error if recovery fails or the recovered signer differs.
+pub fn verify_personal_signature(
+    message: &[u8],
+    signature: &[u8; SIGNATURE_SIZE],
+    expected: &[u8; ADDRESS_SIZE],
+) -> Result<(), SignatureError> {
+    let signer = recover_personal_signer(message, signature)?;
+    if signer != *expected {
+        return Err(SignatureError::Mismatch(format!(
+            "Signed by {}, expected {}",
+            to_checksum_address(&signer),
+            to_checksum_address(expected)
+        )));
+    }
+    Ok(())
+}
+
+/// Builds the human-readable message attesting a root.
+#[must_use]
+pub fn root_message(root: &str, leaf_count: usize, manifest_hash: Option<&str>) -> String {
+    let mut message = format!("{ATTESTATION_HEADER}\nroot: {root}\nleaf_count: {leaf_count}");
+    if let Some(hash) = manifest_hash {
+        message.push_str("\nmanifest: ");
+        message.push_str(hash);
+    }
+    message
+}
+
+/// A signed attestation of a database root, stored as `signature.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedRoot {
+    pub root: String,
+    pub leaf_count: usize,
+    /// Keccak-256 hash of `manifest.json`, when the database has one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest_hash: Option<String>,
+    /// The exact message passed to `personal_sign`.
+    pub message: String,
+    pub signature: String,
+    pub signer: String,
+}
+
+impl SignedRoot {
+    /// Signs the root, leaf count and manifest of a database directory.
+    ///
+    /// # Errors
+    /// Returns an error if the root or manifest cannot be read, or signing fails.
+    pub fn sign_database(db_dir: &Path, secret_key: &SecretKey) -> Result<Self, SignatureError> {
+        let (root, _) = read_root(db_dir).map_err(|e| SignatureError::FileIo(e.to_string()))?;
+        let leaf_count = leaf_count(db_dir)?;
+        let manifest_hash = manifest_hash(db_dir)?;
+        let root = to_hex32(&root);
+        let message = root_message(&root, leaf_count, manifest_hash.as_deref());
+        let signature = sign_personal_message(secret_key, message.as_bytes())?;
+        let signer = public_key_address(&secret_key.public_key())
+            .map_err(|e| SignatureError::InvalidKey(e.to_string()))?;
+        Ok(SignedRoot {
+            root,
+            leaf_count,
+            manifest_hash,
+            message,
+            signature: format!("0x{}", hex::encode(signature)),
+            signer: to_checksum_address(&signer),
+        })
+    }
+
+    /// Checks that the message matches the attested fields and was signed by
+    /// `signer`, returning the signer address.
+    ///
+    /// # Errors
+    /// Returns an error if the message, signature or signer do not agree.
+    pub fn verify(&self) -> Result<[u8; ADDRESS_SIZE], SignatureError> {
+        let expected_message =
+            root_message(&self.root, self.leaf_count, self.manifest_hash.as_deref());
+        if self.message != expected_message {
+            return Err(SignatureError::Mismatch(
+                "Signed message does not match the attested root".to_string(),
+            ));
+        }
+        let signer = parse_address(&self.signer)
+            .map_err(|e| SignatureError::InvalidSignature(e.to_string()))?;
+        let signature = parse_signature(&self.signature)?;
+        verify_personal_signature(self.message.as_bytes(), &signature, &signer)?;
+        Ok(signer)
+    }
+
+    /// Verifies the signature and checks that it attests the given database.
+    ///
+    /// # Errors
+    /// Returns an error if the signature is invalid or the root, leaf count
+    /// or manifest hash differ from the files in `db_dir`.
+    pub fn verify_database(&self, db_dir: &Path) -> Result<[u8; ADDRESS_SIZE], SignatureError> {
+        let signer = self.verify()?;
+        let (root, _) = read_root(db_dir).map_err(|e| SignatureError::FileIo(e.to_string()))?;
+        if to_hex32(&root) != self.root.to_ascii_lowercase() {
+            return Err(SignatureError::Mismatch(format!(
+                "Signed root {} differs from database root {}",
+                self.root,
+                to_hex32(&root)
+            )));
+        }
+        let leaf_count = leaf_count(db_dir)?;
+        if leaf_count != self.leaf_count {
+            return Err(SignatureError::Mismatch(format!(
+                "Signed leaf count {} differs from database leaf count {leaf_count}",
+                self.leaf_count
+            )));
+        }
+        if manifest_hash(db_dir)? != self.manifest_hash {
+            return Err(SignatureError::Mismatch(
+                "manifest.json changed since it was signed".to_string(),
+            ));
+        }
+        Ok(signer)
+    }
+
+    /// Reads `signature.json` from a database directory, if present.
+    ///
+    /// # Errors
+    /// Returns an error if the file exists but cannot be parsed.
+    pub fn read(db_dir: &Path) -> Result<Option<Self>, SignatureError> {
+        let path = db_dir.join(SIGNATURE_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let file = File::open(&path).map_err(|e| {
+            SignatureError::FileIo(format!("Failed to open {}: {e}", path.display()))
+        })?;
+        serde_json::from_reader(BufReader::new(file))
+            .map(Some)
+            .map_err(|e| SignatureError::InvalidSignature(format!("{}: {e}", path.display())))
+    }
+
+    /// Writes the attestation as `signature.json` in a database directory.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written.
+    pub fn write(&self, db_dir: &Path) -> Result<(), SignatureError> {
+        let path = db_dir.join(SIGNATURE_FILE);
+        let io_err = |e: std::io::Error| {
+            SignatureError::FileIo(format!("Failed to write {}: {e}", path.display()))
+        };
+        let mut writer = BufWriter::new(File::create(&path).map_err(io_err)?);
+        serde_json::to_writer_pretty(&mut writer, self)
+            .map_err(|e| SignatureError::FileIo(format!("Failed to encode signature: {e}")))?;
+        writer.write_all(b"\n").map_err(io_err)?;
+        writer.flush().map_err(io_err)
+    }
+}
+
+fn leaf_count(db_dir: &Path) -> Result<usize, SignatureError> {
+    let path = db_dir.join("addresses.bin");
+    let len = std::fs::metadata(&path)
+        .map_err(|e| SignatureError::FileIo(format!("Failed to stat {}: {e}", path.display())))?
+        .len();
+    usize::try_from(len / ADDRESS_SIZE as u64)
+        .map_err(|_| SignatureError::FileIo(format!("{} is too large", path.display())))
+}
+
+fn manifest_hash(db_dir: &Path) -> Result<Option<String>, SignatureError> {
+    let path = db_dir.join(MANIFEST_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    hash_file(&path)
+        .map(|(hash, _)| Some(hash))
+        .map_err(|e| SignatureError::FileIo(e.to_string()))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::merkle::hash_leaf;
+    use tempfile::TempDir;
+
+    const TEST_KEY: &str = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+
+    #[test]
+    fn test_eip191_hash_known_vector() {
+        // keccak256("\x19Ethereum Signed Message:\n11hello world")
+        assert_eq!(
+            hex::encode(eip191_hash(b"hello world")),
+            "d9eba16ed0ecae432b71fe008c98cc872bb4cc214d3220a36f365326cf807d68"
+        );
+    }
+
+    #[test]
+    fn test_sign_and_recover() {
+        let key = parse_secret_key(TEST_KEY).unwrap();
+        let expected = public_key_address(&key.public_key()).unwrap();
+        assert_eq!(
+            to_checksum_address(&expected),
+            "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23"
+        );
+
+        let signature = sign_personal_message(&key, b"Some data").unwrap();
+        assert!(matches!(signature[64], 27 | 28));
+        assert_eq!(
+            recover_personal_signer(b"Some data", &signature).unwrap(),
+            expected
+        );
+        assert!(verify_personal_signature(b"Other data", &signature, &expected).is_err());
+    }
+
+    #[test]
+    fn test_recover_rejects_bad_recovery_byte() {
+        let mut signature = [1u8; SIGNATURE_SIZE];
+        signature[64] = 5;
+        let err = recover_personal_signer(b"x", &signature).unwrap_err();
+        assert!(err.to_string().contains("recovery byte"));
+    }
+
+    #[test]
+    fn test_signed_root_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let address = [0x11u8; ADDRESS_SIZE];
+        std::fs::write(dir.path().join("addresses.bin"), address).unwrap();
+        std::fs::write(dir.path().join("layer00.bin"), hash_leaf(&address)).unwrap();
+
+        let key = parse_secret_key(TEST_KEY).unwrap();
+        let signed = SignedRoot::sign_database(dir.path(), &key).unwrap();
+        assert_eq!(signed.root, to_hex32(&hash_leaf(&address)));
+        signed.write(dir.path()).unwrap();
+
+        let loaded = SignedRoot::read(dir.path()).unwrap().unwrap();
+        assert_eq!(loaded, signed);
+        assert_eq!(
+            loaded.verify_database(dir.path()).unwrap(),
+            public_key_address(&key.public_key()).unwrap()
+        );
+
+        std::fs::write(dir.path().join("layer00.bin"), [0u8; HASH_SIZE]).unwrap();
+        assert!(loaded.verify_database(dir.path()).is_err());
+
+        let mut tampered = signed.clone();
+        tampered.leaf_count = 2;
+        assert!(tampered.verify().is_err());
+    }
+}